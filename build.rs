@@ -1,33 +1,56 @@
-//! This build script copies the `memory.x` file from the crate root into
-//! a directory where the linker can always find it at build time.
+//! This build script writes `memory.x`, from `memory.x.in` and the flash
+//! size of the board, into a directory where the linker can always find it
+//! at build time.
 //! For many projects this is optional, as the linker always searches the
 //! project root directory -- wherever `Cargo.toml` is. However, if you
 //! are using a workspace or have a more complicated build setup, this
 //! build script becomes required. Additionally, by requesting that
-//! Cargo re-run the build script whenever `memory.x` is changed,
-//! updating `memory.x` ensures a rebuild of the application with the
-//! new memory settings.
+//! Cargo re-run the build script whenever `memory.x.in` is changed,
+//! updating it ensures a rebuild of the application with the new memory
+//! settings.
 
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use deej_gfx::assets;
+
+/// Flash size of each `board-*` feature, as its `FLASH_SIZE` in
+/// `src/board/` has it.
+const BOARD_FLASH: [(&str, &str); 3] = [
+    ("CARGO_FEATURE_BOARD_PICO", "2M"),
+    ("CARGO_FEATURE_BOARD_RP2040_ZERO", "2M"),
+    ("CARGO_FEATURE_BOARD_TENSTAR_PRO_MICRO", "16M"),
+];
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
-    // on the linker search path.
+    // on the linker search path. Without a board feature `src/board.rs`
+    // stops the build, so any size does here.
+    let flash = BOARD_FLASH
+        .iter()
+        .find(|(feature, _)| env::var_os(feature).is_some())
+        .map_or("2M", |&(_, size)| size);
     let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
     File::create(out.join("memory.x"))
         .unwrap()
-        .write_all(include_bytes!("memory.x"))
+        .write_all(include_str!("memory.x.in").replace("{FLASH}", flash).as_bytes())
         .unwrap();
     println!("cargo:rustc-link-search={}", out.display());
 
     // By default, Cargo will re-run a build script whenever
-    // any file in the project changes. By specifying `memory.x`
+    // any file in the project changes. By specifying `memory.x.in`
     // here, we ensure the build script is only re-run when
-    // `memory.x` is changed.
-    println!("cargo:rerun-if-changed=memory.x");
+    // `memory.x.in` is changed.
+    println!("cargo:rerun-if-changed=memory.x.in");
+
+    // Convert the source sprites in `assets/` into (compressed) Gray4
+    // sheets and generate the typed handles that `src/assets.rs` includes.
+    if let Err(e) = assets::generate(Path::new("assets"), out) {
+        panic!("failed to convert sprite assets: {}", e);
+    }
+    println!("cargo:rerun-if-changed=assets");
 
     println!("cargo:rustc-link-arg-bins=--nmagic");
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
@@ -0,0 +1,16 @@
+//! Generates the same sprite handles as the firmware's `build.rs`, for the
+//! shared `src/assets.rs`.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use deej_gfx::assets;
+
+fn main() {
+    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+
+    if let Err(e) = assets::generate(Path::new("../assets"), out) {
+        panic!("failed to convert sprite assets: {}", e);
+    }
+    println!("cargo:rerun-if-changed=../assets");
+}
@@ -0,0 +1,62 @@
+//! Simulated MCP3008 with the `adc-mcp3008` API the firmware uses.
+//!
+//! Channel readings come from a shared table the emulator's input script
+//! writes to; a channel can also be made to fail its reads.
+
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+
+const CHANNELS: usize = 8;
+
+static RAW: [AtomicU16; CHANNELS] = [const { AtomicU16::new(512) }; CHANNELS];
+static FAILING: [AtomicBool; CHANNELS] = [const { AtomicBool::new(false) }; CHANNELS];
+
+/// Sets the 10-bit reading of `channel`.
+pub fn set_channel(channel: usize, raw: u16) {
+    RAW[channel].store(raw.min(1023), Ordering::Relaxed);
+}
+
+pub fn channel(channel: usize) -> u16 {
+    RAW[channel].load(Ordering::Relaxed)
+}
+
+/// Makes reads of `channel` fail until cleared.
+pub fn set_failing(channel: usize, failing: bool) {
+    FAILING[channel].store(failing, Ordering::Relaxed);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channels8 {
+    CH0,
+    CH1,
+    CH2,
+    CH3,
+    CH4,
+    CH5,
+    CH6,
+    CH7,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The channel was set to fail.
+    Read,
+}
+
+pub struct Mcp3008<SPI, CS> {
+    _spi: SPI,
+    _cs: CS,
+}
+
+impl<SPI, CS> Mcp3008<SPI, CS> {
+    pub fn new(spi: SPI, cs: CS) -> Result<Self, Error> {
+        Ok(Self { _spi: spi, _cs: cs })
+    }
+
+    pub fn read_channel(&mut self, ch: Channels8) -> Result<u16, Error> {
+        let i = ch as usize;
+        if FAILING[i].load(Ordering::Relaxed) {
+            return Err(Error::Read);
+        }
+        Ok(RAW[i].load(Ordering::Relaxed))
+    }
+}
@@ -0,0 +1,300 @@
+//! Host stand-ins for the `embassy-rp` API used by the firmware.
+//!
+//! Only what `adc.rs` and `screen.rs` touch is here. Output pins keep their
+//! level in a table the emulator can read back, and SPI writes are handed to
+//! a sink the emulator installs per SPI block (the SSD1322 model on SPI1).
+
+use core::marker::PhantomData;
+
+/// Owned peripheral, as handed out by `init`.
+pub struct Peri<'a, T> {
+    inner: T,
+    _lifetime: PhantomData<&'a mut T>,
+}
+
+impl<T> Peri<'_, T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            _lifetime: PhantomData,
+        }
+    }
+}
+
+macro_rules! peripherals {
+    ($($name:ident),* $(,)?) => {
+        pub mod peripherals {
+            $(
+                #[allow(non_camel_case_types)]
+                pub struct $name;
+            )*
+        }
+
+        #[allow(non_snake_case)]
+        pub struct Peripherals {
+            $(pub $name: Peri<'static, peripherals::$name>,)*
+        }
+
+        /// Hands out every peripheral once, like `embassy_rp::init`.
+        pub fn init(_config: config::Config) -> Peripherals {
+            Peripherals {
+                $($name: Peri::new(peripherals::$name),)*
+            }
+        }
+    };
+}
+
+peripherals!(
+    PIN_0, PIN_1, PIN_2, PIN_3, PIN_4, PIN_5, PIN_6, PIN_7, PIN_8, PIN_9, PIN_10, PIN_11, PIN_12,
+    PIN_13, PIN_14, PIN_15, PIN_16, PIN_17, PIN_18, PIN_19, PIN_20, PIN_21, PIN_22, PIN_23, PIN_24,
+    PIN_25, PIN_26, PIN_27, PIN_28, PIN_29, SPI0, SPI1, DMA_CH0, DMA_CH1, DMA_CH2, DMA_CH3, USB,
+);
+
+pub mod config {
+    #[derive(Default)]
+    pub struct Config {}
+}
+
+pub mod gpio {
+    use core::convert::Infallible;
+    use core::marker::PhantomData;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::{peripherals, Peri};
+
+    const PIN_COUNT: usize = 30;
+
+    static LEVELS: [AtomicBool; PIN_COUNT] = [const { AtomicBool::new(false) }; PIN_COUNT];
+
+    /// Level last driven on GPIO `pin`, by an `Output` or from outside.
+    pub fn level(pin: u8) -> bool {
+        LEVELS[pin as usize].load(Ordering::Relaxed)
+    }
+
+    /// Drives input `pin` from outside, like a button would.
+    pub fn drive(pin: u8, high: bool) {
+        LEVELS[pin as usize].store(high, Ordering::Relaxed);
+    }
+
+    pub trait Pin {
+        fn pin(&self) -> u8;
+    }
+
+    macro_rules! pins {
+        ($($name:ident = $n:expr),* $(,)?) => {
+            $(impl Pin for peripherals::$name {
+                fn pin(&self) -> u8 {
+                    $n
+                }
+            })*
+        };
+    }
+
+    pins! {
+        PIN_0 = 0, PIN_1 = 1, PIN_2 = 2, PIN_3 = 3, PIN_4 = 4, PIN_5 = 5, PIN_6 = 6, PIN_7 = 7,
+        PIN_8 = 8, PIN_9 = 9, PIN_10 = 10, PIN_11 = 11, PIN_12 = 12, PIN_13 = 13, PIN_14 = 14,
+        PIN_15 = 15, PIN_16 = 16, PIN_17 = 17, PIN_18 = 18, PIN_19 = 19, PIN_20 = 20,
+        PIN_21 = 21, PIN_22 = 22, PIN_23 = 23, PIN_24 = 24, PIN_25 = 25, PIN_26 = 26,
+        PIN_27 = 27, PIN_28 = 28, PIN_29 = 29,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Level {
+        Low,
+        High,
+    }
+
+    pub struct Output<'d> {
+        pin: u8,
+        _lifetime: PhantomData<&'d mut ()>,
+    }
+
+    impl<'d> Output<'d> {
+        pub fn new(pin: Peri<'d, impl Pin>, initial: Level) -> Self {
+            let mut out = Self {
+                pin: pin.inner.pin(),
+                _lifetime: PhantomData,
+            };
+            out.set_level(initial);
+            out
+        }
+
+        pub fn set_level(&mut self, level: Level) {
+            LEVELS[self.pin as usize].store(level == Level::High, Ordering::Relaxed);
+        }
+
+        pub fn set_high(&mut self) {
+            self.set_level(Level::High);
+        }
+
+        pub fn set_low(&mut self) {
+            self.set_level(Level::Low);
+        }
+
+        pub fn is_set_high(&self) -> bool {
+            level(self.pin)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Pull {
+        None,
+        Up,
+        Down,
+    }
+
+    pub struct Input<'d> {
+        pin: u8,
+        _lifetime: PhantomData<&'d mut ()>,
+    }
+
+    impl<'d> Input<'d> {
+        /// The pull sets the level until something drives the pin.
+        pub fn new(pin: Peri<'d, impl Pin>, pull: Pull) -> Self {
+            let pin = pin.inner.pin();
+            drive(pin, pull == Pull::Up);
+            Self {
+                pin,
+                _lifetime: PhantomData,
+            }
+        }
+
+        pub fn is_high(&self) -> bool {
+            level(self.pin)
+        }
+
+        pub fn is_low(&self) -> bool {
+            !self.is_high()
+        }
+    }
+
+    impl embedded_hal::digital::ErrorType for Output<'_> {
+        type Error = Infallible;
+    }
+
+    impl embedded_hal::digital::OutputPin for Output<'_> {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            Output::set_low(self);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            Output::set_high(self);
+            Ok(())
+        }
+    }
+}
+
+pub mod spi {
+    use core::convert::Infallible;
+    use core::marker::PhantomData;
+    use std::sync::Mutex;
+
+    use crate::{peripherals, Peri};
+
+    type Sink = Box<dyn FnMut(&[u8]) + Send>;
+
+    static SINKS: [Mutex<Option<Sink>>; 2] = [Mutex::new(None), Mutex::new(None)];
+
+    /// Routes everything written to SPI block `T` into `sink`.
+    pub fn set_sink<T: Instance>(sink: impl FnMut(&[u8]) + Send + 'static) {
+        *SINKS[T::INDEX].lock().unwrap() = Some(Box::new(sink));
+    }
+
+    pub trait Instance {
+        const INDEX: usize;
+    }
+
+    impl Instance for peripherals::SPI0 {
+        const INDEX: usize = 0;
+    }
+
+    impl Instance for peripherals::SPI1 {
+        const INDEX: usize = 1;
+    }
+
+    #[non_exhaustive]
+    pub struct Config {
+        pub frequency: u32,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                frequency: 1_000_000,
+            }
+        }
+    }
+
+    pub struct Async;
+    pub struct Blocking;
+
+    pub struct Spi<'d, T: Instance, M> {
+        _inner: Peri<'d, T>,
+        _mode: PhantomData<M>,
+    }
+
+    impl<'d, T: Instance> Spi<'d, T, Async> {
+        pub fn new<CLK, MOSI, MISO, TX, RX>(
+            inner: Peri<'d, T>,
+            _clk: Peri<'d, CLK>,
+            _mosi: Peri<'d, MOSI>,
+            _miso: Peri<'d, MISO>,
+            _tx_dma: Peri<'d, TX>,
+            _rx_dma: Peri<'d, RX>,
+            _config: Config,
+        ) -> Self {
+            Self {
+                _inner: inner,
+                _mode: PhantomData,
+            }
+        }
+
+        pub fn new_txonly<CLK, MOSI, TX>(
+            inner: Peri<'d, T>,
+            _clk: Peri<'d, CLK>,
+            _mosi: Peri<'d, MOSI>,
+            _tx_dma: Peri<'d, TX>,
+            _config: Config,
+        ) -> Self {
+            Self {
+                _inner: inner,
+                _mode: PhantomData,
+            }
+        }
+    }
+
+    impl<T: Instance, M> embedded_hal_async::spi::ErrorType for Spi<'_, T, M> {
+        type Error = Infallible;
+    }
+
+    impl<T: Instance> embedded_hal_async::spi::SpiBus for Spi<'_, T, Async> {
+        async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+            words.fill(0);
+            Ok(())
+        }
+
+        async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            if let Some(sink) = SINKS[T::INDEX].lock().unwrap().as_mut() {
+                sink(words);
+            }
+            Ok(())
+        }
+
+        async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+            self.write(write).await?;
+            read.fill(0);
+            Ok(())
+        }
+
+        async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+            self.write(words).await?;
+            words.fill(0);
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Host stand-in for the firmware's `crash.rs`. A crash here is an ordinary
+//! panic of the process, so there is never a stored record to report.
+
+pub fn report() {
+    log::info!("no crash recorded");
+}
+
+pub fn clear() {
+    log::info!("crash record cleared");
+}
@@ -0,0 +1,60 @@
+//! Host stand-in for the firmware's `deej_usb.rs`: same host state events
+//! and value lines, with the pty in place of the USB device.
+
+use core::fmt::Write as _;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_time::Timer;
+use heapless::String;
+
+use crate::adc::ADC_VALUES;
+use crate::events::{self, Event, HostState};
+use crate::serial;
+
+static HOST_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the host is there, for diagnostics and the ADC poll rate; the
+/// tasks that act on it going away follow `Event::HostStateChanged`.
+pub fn host_active() -> bool {
+    HOST_ACTIVE.load(Ordering::Relaxed)
+}
+
+/// The pty client cannot be woken; it resumes by opening the port.
+pub fn request_wakeup() {}
+
+/// Polling interval for pty clients coming and going.
+const POLL_MS: u64 = 100;
+
+/// Mirrors the firmware's `usb_task`: a client opening the pty is the host
+/// resuming, the last one closing it is the host suspending.
+#[embassy_executor::task]
+pub async fn usb_task() -> ! {
+    loop {
+        while !serial::connected() {
+            Timer::after_millis(POLL_MS).await;
+        }
+
+        HOST_ACTIVE.store(true, Ordering::Relaxed);
+        events::publish(Event::HostStateChanged(HostState::Active));
+
+        while serial::connected() {
+            serial::read_commands();
+            Timer::after_millis(POLL_MS).await;
+        }
+
+        HOST_ACTIVE.store(false, Ordering::Relaxed);
+        events::publish(Event::HostStateChanged(HostState::Suspended));
+    }
+}
+
+pub fn write_adc_values(values: [u32; ADC_VALUES.len()]) {
+    let mut out: String<96> = String::new();
+
+    for (i, v) in values.iter().enumerate() {
+        if i > 0 {
+            let _ = out.push('|');
+        }
+        let _ = write!(out, "{}", v);
+    }
+    log::info!("{}", out.as_str());
+}
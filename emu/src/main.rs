@@ -0,0 +1,229 @@
+//! Host emulator for the deej OLED firmware.
+//!
+//! Builds the firmware's own `adc.rs`, `buttons.rs`, `commands.rs`,
+//! `config.rs`, `diagnostics.rs`, `display.rs`, `encoder.rs`, `events.rs`,
+//! `gestures.rs`, `graphics.rs`, `perf.rs`, `screen.rs`, `ssd1322.rs` and
+//! `themes.rs` against host stand-ins: `embassy-rp` and `adc-mcp3008` are
+//! replaced by the crates in `shims/`, USB by a pty (`serial.rs`), the panel
+//! by an SSD1322 model (`panel.rs`) and crash handling and the watchdog by
+//! stubs (`crash.rs`, `watchdog.rs`). The host's clocks are not ours to
+//! scale, so there is no `power.rs`. Nothing is stored: every run starts
+//! from the default config.
+//!
+//! ```text
+//! deej-emu [--script FILE] [--link PATH] [--frames DIR] [--preview]
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+
+use embassy_executor::{Executor, Spawner};
+use embassy_rp::peripherals::{self, SPI1};
+use embassy_rp::Peri;
+
+#[path = "../../src/adc.rs"]
+mod adc;
+#[path = "../../src/assets.rs"]
+mod assets;
+#[path = "../../src/buttons.rs"]
+mod buttons;
+#[path = "../../src/commands.rs"]
+mod commands;
+#[path = "../../src/config.rs"]
+mod config;
+mod crash;
+mod deej_usb;
+#[path = "../../src/diagnostics.rs"]
+mod diagnostics;
+#[path = "../../src/display.rs"]
+mod display;
+#[path = "../../src/encoder.rs"]
+mod encoder;
+#[path = "../../src/events.rs"]
+mod events;
+#[path = "../../src/gestures.rs"]
+mod gestures;
+#[path = "../../src/graphics.rs"]
+mod graphics;
+mod panel;
+#[path = "../../src/perf.rs"]
+mod perf;
+#[path = "../../src/screen.rs"]
+mod screen;
+mod serial;
+mod sim_adc;
+#[path = "../../src/ssd1322.rs"]
+mod ssd1322;
+#[path = "../../src/themes.rs"]
+mod themes;
+mod watchdog;
+
+#[cfg(not(feature = "display-ssd1322"))]
+compile_error!("the emulator only models the SSD1322, see panel.rs");
+
+// Same fields as the `assign_resources!` groups of the firmware's boards,
+// with the Tenstar Pro Micro's pins.
+pub struct ScreenResources {
+    pub spi: Peri<'static, peripherals::SPI1>,
+    pub sck: Peri<'static, peripherals::PIN_14>,
+    pub mosi: Peri<'static, peripherals::PIN_15>,
+    pub miso: Peri<'static, peripherals::PIN_12>,
+    pub cs: Peri<'static, peripherals::PIN_13>,
+    pub reset: Peri<'static, peripherals::PIN_6>,
+    pub pwr: Peri<'static, peripherals::PIN_9>,
+    pub dc: Peri<'static, peripherals::PIN_16>,
+    pub dma_tx: Peri<'static, peripherals::DMA_CH0>,
+}
+
+pub struct AdcResources {
+    pub spi: Peri<'static, peripherals::SPI0>,
+    pub sck: Peri<'static, peripherals::PIN_2>,
+    pub mosi: Peri<'static, peripherals::PIN_7>,
+    pub miso: Peri<'static, peripherals::PIN_4>,
+    pub cs: Peri<'static, peripherals::PIN_5>,
+    pub dma_tx: Peri<'static, peripherals::DMA_CH1>,
+    pub dma_rx: Peri<'static, peripherals::DMA_CH2>,
+}
+
+pub struct ButtonResources {
+    pub button0: Peri<'static, peripherals::PIN_18>,
+    pub button1: Peri<'static, peripherals::PIN_19>,
+    pub button2: Peri<'static, peripherals::PIN_20>,
+    pub button3: Peri<'static, peripherals::PIN_21>,
+    pub button4: Peri<'static, peripherals::PIN_22>,
+}
+
+/// GPIOs of `ButtonResources`, for the script's `press` and `release`.
+const BUTTON_PINS: [u8; 5] = [18, 19, 20, 21, 22];
+
+/// GPIO of the display's D/C# line, `ScreenResources::dc`.
+const DC_PIN: u8 = 16;
+
+struct Args {
+    script: Option<PathBuf>,
+    link: Option<PathBuf>,
+    frames: Option<PathBuf>,
+    preview: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        script: None,
+        link: None,
+        frames: None,
+        preview: false,
+    };
+
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        let mut value = || {
+            it.next()
+                .map(PathBuf::from)
+                .ok_or(format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--script" => args.script = Some(value()?),
+            "--link" => args.link = Some(value()?),
+            "--frames" => args.frames = Some(value()?),
+            "--preview" => args.preview = true,
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+    Ok(args)
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("usage: deej-emu [--script FILE] [--link PATH] [--frames DIR] [--preview]");
+            std::process::exit(2);
+        }
+    };
+
+    let port = serial::init(args.link.as_deref()).expect("failed to open a pty");
+    eprintln!(
+        "deej serial port: {}",
+        args.link.as_deref().unwrap_or(port).display()
+    );
+
+    match &args.script {
+        Some(path) => {
+            let file = File::open(path).expect("failed to open the script");
+            sim_adc::spawn(Box::new(BufReader::new(file)), true);
+        }
+        None => sim_adc::spawn(Box::new(BufReader::new(io::stdin())), false),
+    }
+
+    config::init(config::defaults());
+    let p = embassy_rp::init(Default::default());
+
+    panel::attach::<SPI1>(DC_PIN);
+
+    let screen = ScreenResources {
+        spi: p.SPI1,
+        sck: p.PIN_14,
+        mosi: p.PIN_15,
+        miso: p.PIN_12,
+        cs: p.PIN_13,
+        reset: p.PIN_6,
+        pwr: p.PIN_9,
+        dc: p.PIN_16,
+        dma_tx: p.DMA_CH0,
+    };
+    let adc = AdcResources {
+        spi: p.SPI0,
+        sck: p.PIN_2,
+        mosi: p.PIN_7,
+        miso: p.PIN_4,
+        cs: p.PIN_5,
+        dma_tx: p.DMA_CH1,
+        dma_rx: p.DMA_CH2,
+    };
+
+    let adc_events = events::subscribe();
+    let render_events = events::subscribe();
+    let frame_events = events::subscribe();
+
+    spawner.spawn(deej_usb::usb_task()).unwrap();
+
+    spawner.spawn(adc::adc_task(adc, adc_events)).unwrap();
+    spawner
+        .spawn(buttons::buttons_task(ButtonResources {
+            button0: p.PIN_18,
+            button1: p.PIN_19,
+            button2: p.PIN_20,
+            button3: p.PIN_21,
+            button4: p.PIN_22,
+        }))
+        .unwrap();
+
+    // A thread with its own executor stands in for core1.
+    screen::init_display_buffers();
+    std::thread::Builder::new()
+        .name("core1".into())
+        .spawn(move || {
+            let executor = Box::leak(Box::new(Executor::new()));
+            executor.run(|spawner| {
+                spawner
+                    .spawn(screen::render_task(screen, render_events))
+                    .unwrap();
+                spawner
+                    .spawn(graphics::prepare_frame_task(frame_events))
+                    .unwrap();
+            })
+        })
+        .unwrap();
+
+    spawner.spawn(diagnostics::diagnostics_task()).unwrap();
+
+    spawner
+        .spawn(panel::output_task(panel::Output {
+            frames_dir: args.frames,
+            preview: args.preview,
+        }))
+        .unwrap();
+}
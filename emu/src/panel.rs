@@ -0,0 +1,244 @@
+//! SSD1322 model fed from the SPI1 stand-in, and the frame output.
+//!
+//! The model follows the command stream the way the controller does (column
+//! and row windows, write-RAM with horizontal auto-increment, display on and
+//! off), so partial updates from `ssd1322.rs` are exercised as well.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use embassy_rp::gpio;
+use embassy_time::Timer;
+
+/// GDDRAM is 120 column addresses of 4 pixels by 128 rows.
+const RAM_COLUMNS: usize = 120;
+const RAM_ROWS: usize = 128;
+const RAM_ROW_BYTES: usize = RAM_COLUMNS * 2;
+
+/// The 256x64 glass shows columns 0x1C..0x5B of rows 0..63.
+const FIRST_COLUMN: usize = 0x1C;
+pub const WIDTH: usize = 256;
+pub const HEIGHT: usize = 64;
+
+const SET_COLUMN_ADDRESS: u8 = 0x15;
+const WRITE_RAM: u8 = 0x5C;
+const SET_ROW_ADDRESS: u8 = 0x75;
+const DISPLAY_OFF: u8 = 0xAE;
+const DISPLAY_ON: u8 = 0xAF;
+
+pub struct Panel {
+    ram: [u8; RAM_ROW_BYTES * RAM_ROWS],
+    command: u8,
+    args: [u8; 2],
+    arg_count: usize,
+    columns: (usize, usize),
+    rows: (usize, usize),
+    /// Write pointer: column address, row and byte within the column.
+    col: usize,
+    row: usize,
+    half: usize,
+    on: bool,
+    changed: bool,
+    /// SPI bytes received since the last snapshot.
+    bytes: usize,
+}
+
+impl Panel {
+    const fn new() -> Self {
+        Self {
+            ram: [0; RAM_ROW_BYTES * RAM_ROWS],
+            command: 0,
+            args: [0; 2],
+            arg_count: 0,
+            columns: (0, RAM_COLUMNS - 1),
+            rows: (0, RAM_ROWS - 1),
+            col: 0,
+            row: 0,
+            half: 0,
+            on: false,
+            changed: false,
+            bytes: 0,
+        }
+    }
+
+    fn command(&mut self, byte: u8) {
+        self.command = byte;
+        self.arg_count = 0;
+
+        match byte {
+            WRITE_RAM => {
+                self.col = self.columns.0;
+                self.row = self.rows.0;
+                self.half = 0;
+            }
+            DISPLAY_ON | DISPLAY_OFF => {
+                self.on = byte == DISPLAY_ON;
+                self.changed = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn data(&mut self, byte: u8) {
+        if self.command == WRITE_RAM {
+            return self.write_ram(byte);
+        }
+
+        if self.arg_count < self.args.len() {
+            self.args[self.arg_count] = byte;
+        }
+        self.arg_count += 1;
+
+        if self.arg_count == 2 {
+            let (a, b) = (self.args[0] as usize, self.args[1] as usize);
+            match self.command {
+                SET_COLUMN_ADDRESS => {
+                    self.columns = (a.min(RAM_COLUMNS - 1), b.min(RAM_COLUMNS - 1))
+                }
+                SET_ROW_ADDRESS => self.rows = (a.min(RAM_ROWS - 1), b.min(RAM_ROWS - 1)),
+                _ => {}
+            }
+        }
+    }
+
+    fn write_ram(&mut self, byte: u8) {
+        self.ram[self.row * RAM_ROW_BYTES + self.col * 2 + self.half] = byte;
+        self.changed = true;
+
+        self.half += 1;
+        if self.half < 2 {
+            return;
+        }
+        self.half = 0;
+        self.col += 1;
+        if self.col > self.columns.1 {
+            self.col = self.columns.0;
+            self.row += 1;
+            if self.row > self.rows.1 {
+                self.row = self.rows.0;
+            }
+        }
+    }
+
+    /// The visible pixels, one nibble per byte, or black while the display
+    /// is off.
+    fn snapshot(&self) -> Vec<u8> {
+        let mut out = vec![0u8; WIDTH * HEIGHT];
+        if !self.on {
+            return out;
+        }
+        for (y, row) in out.chunks_exact_mut(WIDTH).enumerate() {
+            let start = y * RAM_ROW_BYTES + FIRST_COLUMN * 2;
+            let src = &self.ram[start..start + WIDTH / 2];
+            for (px, b) in row.chunks_exact_mut(2).zip(src) {
+                px[0] = b >> 4;
+                px[1] = b & 0x0F;
+            }
+        }
+        out
+    }
+}
+
+static PANEL: Mutex<Panel> = Mutex::new(Panel::new());
+
+/// Connects the model to the SPI block the display is on, with the D/C# line
+/// on GPIO `dc_pin`.
+pub fn attach<T: embassy_rp::spi::Instance>(dc_pin: u8) {
+    embassy_rp::spi::set_sink::<T>(move |bytes| {
+        let mut panel = PANEL.lock().unwrap();
+        panel.bytes += bytes.len();
+        let is_data = gpio::level(dc_pin);
+        for &b in bytes {
+            if is_data {
+                panel.data(b);
+            } else {
+                panel.command(b);
+            }
+        }
+    });
+}
+
+/// Where snapshots of the panel go.
+pub struct Output {
+    /// Directory for numbered PGM files.
+    pub frames_dir: Option<PathBuf>,
+    /// Redraw a preview in the terminal.
+    pub preview: bool,
+}
+
+/// How often the panel is looked at; faster than any frame rate the firmware
+/// reaches, so every flushed frame is caught.
+const SNAPSHOT_MS: u64 = 20;
+
+#[embassy_executor::task]
+pub async fn output_task(output: Output) {
+    if let Some(dir) = &output.frames_dir {
+        fs::create_dir_all(dir).unwrap();
+    }
+
+    let mut index = 0usize;
+    loop {
+        Timer::after_millis(SNAPSHOT_MS).await;
+
+        let (pixels, bytes) = {
+            let mut panel = PANEL.lock().unwrap();
+            if !panel.changed {
+                continue;
+            }
+            panel.changed = false;
+            (panel.snapshot(), core::mem::take(&mut panel.bytes))
+        };
+
+        if let Some(dir) = &output.frames_dir {
+            let path = dir.join(format!("frame_{:05}.pgm", index));
+            if let Err(e) = write_pgm(&path, &pixels) {
+                eprintln!("{}: {}", path.display(), e);
+            }
+        }
+        if output.preview {
+            let _ = draw_preview(&pixels, index, bytes);
+        }
+        index += 1;
+    }
+}
+
+/// Binary PGM with a maximum value of 15, so the nibbles are stored as is.
+fn write_pgm(path: &std::path::Path, pixels: &[u8]) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write!(w, "P5\n{} {}\n15\n", WIDTH, HEIGHT)?;
+    w.write_all(pixels)?;
+    w.flush()
+}
+
+/// Half-block preview at half the horizontal resolution: 128 columns by 32
+/// lines, each cell showing two rows in its foreground and background.
+fn draw_preview(pixels: &[u8], index: usize, bytes: usize) -> io::Result<()> {
+    let gray = |x: usize, y: usize| {
+        let i = y * WIDTH + x * 2;
+        ((pixels[i] as u16 + pixels[i + 1] as u16) * 17 / 2) as u8
+    };
+
+    let mut out = String::with_capacity(128 * 32 * 40);
+    out.push_str("\x1b[H");
+    for y in (0..HEIGHT).step_by(2) {
+        for x in 0..WIDTH / 2 {
+            let (top, bottom) = (gray(x, y), gray(x, y + 1));
+            out.push_str(&format!(
+                "\x1b[38;2;{t};{t};{t}m\x1b[48;2;{b};{b};{b}m\u{2580}",
+                t = top,
+                b = bottom
+            ));
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out.push_str(&format!(
+        "frame {:>6}  {:>5} SPI bytes\x1b[K\n",
+        index, bytes
+    ));
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(out.as_bytes())?;
+    stdout.flush()
+}
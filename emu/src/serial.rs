@@ -0,0 +1,121 @@
+//! The deej serial stream on a pseudo-terminal.
+//!
+//! The firmware logs the fader values through `embassy-usb-logger`, one
+//! `"{}\r\n"` line per record on the CDC-ACM port. Here the same records go
+//! to the master side of a pty; the deej app opens the slave side like any
+//! serial port. A client having the slave open stands in for the USB host
+//! being active.
+
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::pty::{grantpt, posix_openpt, ptsname_r, unlockpt, PtyMaster};
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+
+pub struct Pty {
+    master: PtyMaster,
+    path: PathBuf,
+}
+
+static PTY: OnceLock<Pty> = OnceLock::new();
+static LOGGER: SerialLogger = SerialLogger;
+
+/// Opens the pty, symlinks it to `link` if given, and routes `log` records to
+/// it. Returns the slave path.
+pub fn init(link: Option<&Path>) -> io::Result<&'static Path> {
+    let master = posix_openpt(OFlag::O_RDWR | OFlag::O_NOCTTY)?;
+    grantpt(&master)?;
+    unlockpt(&master)?;
+    let path = PathBuf::from(ptsname_r(&master)?);
+
+    // Raw mode, so that the "\r\n" line endings reach the client untouched.
+    // Opening the slave once here also keeps its settings for later opens.
+    let slave: OwnedFd = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)?
+        .into();
+    let mut termios = tcgetattr(&slave)?;
+    cfmakeraw(&mut termios);
+    tcsetattr(&slave, SetArg::TCSANOW, &termios)?;
+    drop(slave);
+
+    // Never block the executor on a client that does not read.
+    let flags = OFlag::from_bits_truncate(fcntl(master.as_raw_fd(), FcntlArg::F_GETFL)?);
+    fcntl(
+        master.as_raw_fd(),
+        FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK),
+    )?;
+
+    if let Some(link) = link {
+        let _ = fs::remove_file(link);
+        std::os::unix::fs::symlink(&path, link)?;
+    }
+
+    let pty = PTY.get_or_init(|| Pty { master, path });
+
+    log::set_logger(&LOGGER).map_err(|_| io::Error::other("a logger is already set"))?;
+    log::set_max_level(log::LevelFilter::Info);
+
+    Ok(&pty.path)
+}
+
+/// True while a client has the slave side open.
+pub fn connected() -> bool {
+    let Some(pty) = PTY.get() else {
+        return false;
+    };
+    let mut fds = [PollFd::new(pty.master.as_fd(), PollFlags::empty())];
+    match poll(&mut fds, PollTimeout::ZERO) {
+        Ok(_) => !fds[0]
+            .revents()
+            .is_some_and(|r| r.contains(PollFlags::POLLHUP)),
+        Err(_) => false,
+    }
+}
+
+/// Hands whatever the client sent to the firmware's command parser, like the
+/// USB logger's receive handler does.
+pub fn read_commands() {
+    let Some(pty) = PTY.get() else {
+        return;
+    };
+    let mut buf = [0u8; 64];
+    while let Ok(n) = nix::unistd::read(pty.master.as_raw_fd(), &mut buf) {
+        if n == 0 {
+            break;
+        }
+        crate::commands::feed(&buf[..n]);
+    }
+}
+
+fn write(bytes: &[u8]) {
+    let Some(pty) = PTY.get() else {
+        return;
+    };
+    // Like the USB logger, drop output nobody is listening to.
+    if connected() {
+        let _ = nix::unistd::write(pty.master.as_fd(), bytes);
+    }
+}
+
+struct SerialLogger;
+
+impl log::Log for SerialLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            write(format!("{}\r\n", record.args()).as_bytes());
+        }
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,109 @@
+//! Feeds the simulated MCP3008 and buttons from a script file or stdin.
+//!
+//! One command per line, `#` starts a comment:
+//!
+//! ```text
+//! set <channel> <raw>                 # 10-bit reading, 0..=1023
+//! ramp <channel> <from> <to> <ms>     # move linearly over <ms>
+//! sleep <ms>
+//! fail <channel> / ok <channel>       # make reads fail, or recover
+//! press <button> / release <button>   # index into `buttons::BUTTONS`
+//! quit
+//! ```
+//!
+//! A bare `<channel> <raw>` is short for `set`. Readings are raw ADC counts,
+//! so the firmware's per-channel inversion and calibration still apply.
+
+use std::io::{self, BufRead};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Step of `ramp`, a bit faster than the ADC task polls.
+const RAMP_STEP_MS: u64 = 20;
+
+/// Runs `input` on its own thread. A `script` ending (or `quit`) ends the
+/// process, and its lines up to the first `sleep` or `ramp` have run by the
+/// time this returns, so the firmware boots with the readings they set.
+pub fn spawn(input: Box<dyn BufRead + Send>, script: bool) {
+    let (ready, set_up) = mpsc::channel();
+    thread::spawn(move || {
+        for (n, line) in input.lines().enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    eprintln!("input: {}", e);
+                    break;
+                }
+            };
+            if matches!(line.split_whitespace().next(), Some("sleep" | "ramp")) {
+                let _ = ready.send(());
+            }
+            match run(&line) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(0),
+                Err(e) => eprintln!("input line {}: {}: {:?}", n + 1, e, line),
+            }
+        }
+        if script {
+            std::process::exit(0);
+        }
+    });
+    if script {
+        // Also returns once the thread is done and `ready` is gone.
+        let _ = set_up.recv();
+    }
+}
+
+/// Runs one line; returns false on `quit`.
+fn run(line: &str) -> io::Result<bool> {
+    let line = line.split('#').next().unwrap_or("").trim();
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    let num = |i: usize| -> io::Result<u32> {
+        words
+            .get(i)
+            .ok_or_else(|| invalid("missing argument"))?
+            .parse()
+            .map_err(|_| invalid("not a number"))
+    };
+    let channel = |i: usize| -> io::Result<usize> {
+        let ch = num(i)? as usize;
+        if ch < 8 {
+            Ok(ch)
+        } else {
+            Err(invalid("channel must be 0..=7"))
+        }
+    };
+
+    match words.first().copied() {
+        None => {}
+        Some("set") => adc_mcp3008::set_channel(channel(1)?, num(2)? as u16),
+        Some("ramp") => {
+            let (ch, from, to, ms) = (channel(1)?, num(2)? as i64, num(3)? as i64, num(4)?);
+            let steps = (ms as u64 / RAMP_STEP_MS).max(1) as i64;
+            for i in 1..=steps {
+                adc_mcp3008::set_channel(ch, (from + (to - from) * i / steps) as u16);
+                thread::sleep(Duration::from_millis(RAMP_STEP_MS));
+            }
+        }
+        Some("sleep") => thread::sleep(Duration::from_millis(num(1)? as u64)),
+        Some("fail") => adc_mcp3008::set_failing(channel(1)?, true),
+        Some("ok") => adc_mcp3008::set_failing(channel(1)?, false),
+        Some("press" | "release") => {
+            let pin = crate::BUTTON_PINS
+                .get(num(1)? as usize)
+                .ok_or_else(|| invalid("no such button"))?;
+            // Active low.
+            embassy_rp::gpio::drive(*pin, words[0] == "release");
+        }
+        Some("quit") => return Ok(false),
+        Some(_) if words.len() == 2 => adc_mcp3008::set_channel(channel(0)?, num(1)? as u16),
+        Some(_) => return Err(invalid("unknown command")),
+    }
+    Ok(true)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
@@ -0,0 +1,18 @@
+//! Host stand-in for the firmware's `watchdog.rs`. There is no hardware to
+//! reset, so check-ins go nowhere.
+
+use core::future::Future;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Task {
+    Adc,
+    Render,
+    PrepareFrame,
+    Buttons,
+}
+
+pub fn check_in(_task: Task) {}
+
+pub async fn idle<F: Future>(_task: Task, fut: F) -> F::Output {
+    fut.await
+}
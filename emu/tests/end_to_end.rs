@@ -0,0 +1,329 @@
+//! Runs the emulator with a fader script and talks to it like the deej app.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Emulator {
+    child: Child,
+    dir: PathBuf,
+}
+
+impl Emulator {
+    fn start(name: &str, script: &str) -> Self {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("script.txt"), script).unwrap();
+
+        let child = Command::new(env!("CARGO_BIN_EXE_deej-emu"))
+            .arg("--script")
+            .arg(dir.join("script.txt"))
+            .arg("--link")
+            .arg(dir.join("port"))
+            .arg("--frames")
+            .arg(dir.join("frames"))
+            .spawn()
+            .unwrap();
+
+        Self { child, dir }
+    }
+
+    /// Opens the serial port once the emulator has created it.
+    fn connect(&self) -> BufReader<File> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let port = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(self.dir.join("port"));
+            if let Ok(port) = port {
+                return BufReader::new(port);
+            }
+            assert!(Instant::now() < deadline, "no serial port showed up");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn frames(&self) -> Vec<Vec<u8>> {
+        let mut paths: Vec<_> = fs::read_dir(self.dir.join("frames"))
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .map(|p| {
+                let data = fs::read(p).unwrap();
+                let header = b"P5\n256 64\n15\n";
+                assert!(data.starts_with(header), "{}", p.display());
+                data[header.len()..].to_vec()
+            })
+            .collect()
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn fader_moves_reach_the_serial_port_and_the_screen() {
+    // Channels are inverted, so a raw 0 is full volume.
+    let emu = Emulator::start(
+        "fader_moves",
+        "sleep 1500\nramp 0 512 0 600\nsleep 1500\nquit\n",
+    );
+    let port = emu.connect();
+
+    let mut lines = Vec::new();
+    for line in port.lines() {
+        // The port goes away when the script quits.
+        let Ok(line) = line else { break };
+        lines.push(line.trim_end().to_string());
+    }
+
+    // The first line is pushed as soon as the host shows up.
+    assert_eq!(
+        lines.first().map(String::as_str),
+        Some("511|511|511|511|511")
+    );
+    assert!(
+        lines.iter().any(|l| l == "1023|511|511|511|511"),
+        "{:?}",
+        lines
+    );
+    for line in &lines {
+        let values: Vec<u32> = line.split('|').map(|v| v.parse().unwrap()).collect();
+        assert_eq!(values.len(), 5, "{:?}", line);
+    }
+
+    let frames = emu.frames();
+    assert!(!frames.is_empty());
+    assert!(frames.iter().any(|f| f.iter().any(|&v| v != 0)));
+}
+
+/// Reads lines until one equals `want`, returning everything skipped.
+fn read_until(port: &mut BufReader<File>, want: &str) -> Vec<String> {
+    let mut seen = Vec::new();
+    for line in port.lines() {
+        let line = line.unwrap().trim_end().to_string();
+        if line == want {
+            return seen;
+        }
+        seen.push(line);
+    }
+    panic!("port closed before {:?}, saw {:?}", want, seen);
+}
+
+#[test]
+fn theme_command_lists_and_switches_themes() {
+    let emu = Emulator::start("theme_command", "sleep 3000\nquit\n");
+    let mut port = emu.connect();
+
+    port.get_mut().write_all(b"theme\r\n").unwrap();
+    read_until(&mut port, "* muffet");
+    read_until(&mut port, "  cobweb");
+
+    port.get_mut().write_all(b"theme cobweb\n").unwrap();
+    read_until(&mut port, "theme cobweb");
+    port.get_mut().write_all(b"theme\n").unwrap();
+    read_until(&mut port, "* cobweb");
+
+    port.get_mut().write_all(b"theme nope\n").unwrap();
+    read_until(&mut port, "no theme nope");
+}
+
+#[test]
+fn fader_targets_can_be_changed_and_mapped_for_deej() {
+    let emu = Emulator::start("targets_command", "sleep 3000\nquit\n");
+    let mut port = emu.connect();
+
+    port.get_mut().write_all(b"targets\n").unwrap();
+    read_until(&mut port, "2 chrome.exe Browser logo_browser");
+
+    port.get_mut()
+        .write_all(b"target 3 id discord.exe\ntarget 3 icon logo_discord\n")
+        .unwrap();
+    read_until(&mut port, "target 3 icon logo_discord");
+    port.get_mut().write_all(b"target 3 icon muffet\n").unwrap();
+    read_until(&mut port, "no icon muffet");
+    port.get_mut().write_all(b"target 7 id mic\n").unwrap();
+    read_until(&mut port, "no fader 7");
+
+    port.get_mut()
+        .write_all(b"target 2 id chrome.exe,firefox.exe\nmapping\n")
+        .unwrap();
+    read_until(&mut port, "slider_mapping:");
+    // Fader values go out in between.
+    let mapping: Vec<String> = read_until(&mut port, "  4: spotify.exe")
+        .into_iter()
+        .filter(|l| !l.contains('|'))
+        .collect();
+    assert_eq!(
+        mapping,
+        [
+            "  0: master",
+            "  1: mic",
+            "  2:",
+            "    - chrome.exe",
+            "    - firefox.exe",
+            "  3: discord.exe",
+        ]
+    );
+}
+
+#[test]
+fn diag_command_reports_noise_and_failing_channels() {
+    let emu = Emulator::start("diag_command", "fail 2\nsleep 5000\nquit\n");
+    let mut port = emu.connect();
+
+    port.get_mut().write_all(b"diag\n").unwrap();
+    read_until(&mut port, "diagnostics for 60 s, `diag stop` ends them");
+    let report = read_until(&mut port, "diag usb active");
+
+    assert!(
+        report
+            .iter()
+            .any(|l| l.starts_with("diag ch0 raw 512 min 512 max 512 mean 512.0 sd 0.0")),
+        "{:?}",
+        report
+    );
+    assert!(
+        report
+            .iter()
+            .any(|l| l.starts_with("diag ch2 no readings, 0 reads,")),
+        "{:?}",
+        report
+    );
+    assert!(report.iter().any(|l| l == "diag mcp3008 read errors"));
+
+    port.get_mut().write_all(b"diag stop\n").unwrap();
+    read_until(&mut port, "diagnostics done");
+}
+
+#[test]
+fn holding_the_first_fader_up_at_boot_runs_diagnostics() {
+    // Inverted, so a raw 0 is full volume.
+    let emu = Emulator::start("diag_boot", "set 0 0\nsleep 2500\nquit\n");
+    let mut port = emu.connect();
+    read_until(&mut port, "diag mcp3008 ok");
+    drop(port);
+
+    // The test pattern's white border.
+    let frames = emu.frames();
+    assert!(
+        frames
+            .iter()
+            .any(|f| f[..256].iter().all(|&v| v == 15)
+                && f[f.len() - 256..].iter().all(|&v| v == 15)),
+        "no test pattern"
+    );
+}
+
+#[test]
+fn perf_command_reports_timings_and_failures() {
+    let emu = Emulator::start("perf_command", "fail 2\nsleep 4000\nquit\n");
+    let mut port = emu.connect();
+    // Past the first poll after the host shows up, which is a slow one.
+    thread::sleep(Duration::from_millis(1500));
+
+    port.get_mut().write_all(b"perf\nperf overlay\n").unwrap();
+    let report = read_until(&mut port, "perf overlay on");
+
+    for metric in ["render", "flush", "frame", "adc loop"] {
+        let prefix = format!("perf {} n ", metric);
+        assert!(
+            report
+                .iter()
+                .any(|l| l.starts_with(&prefix) && l.ends_with(" ms")),
+            "{:?}",
+            report
+        );
+    }
+    let failures = report
+        .iter()
+        .find_map(|l| l.strip_prefix("perf ")?.split_once(" dropped frames, "))
+        .and_then(|(_, rest)| rest.strip_suffix(" adc read failures"))
+        .expect("no counts");
+    assert!(failures.parse::<u32>().unwrap() > 0);
+
+    port.get_mut().write_all(b"perf reset\nperf\n").unwrap();
+    read_until(&mut port, "perf reset");
+    read_until(&mut port, "perf over 0 s");
+}
+
+#[test]
+fn panel_sleeps_while_the_host_is_suspended() {
+    let emu = Emulator::start("suspend", "sleep 9000\nquit\n");
+    let mut port = emu.connect();
+    read_until(&mut port, "511|511|511|511|511");
+    thread::sleep(Duration::from_millis(1500));
+
+    // Closing the port suspends the host: outro, then the panel goes off.
+    drop(port);
+    thread::sleep(Duration::from_millis(3000));
+    let asleep = emu.frames();
+    assert!(asleep.last().unwrap().iter().all(|&v| v == 0));
+
+    let mut port = emu.connect();
+    read_until(&mut port, "511|511|511|511|511");
+    thread::sleep(Duration::from_millis(1000));
+    drop(port);
+
+    let frames = emu.frames();
+    assert!(
+        frames[asleep.len()..]
+            .iter()
+            .any(|f| f.iter().any(|&v| v != 0)),
+        "panel did not come back"
+    );
+}
+
+#[test]
+fn mute_button_zeroes_its_channel_until_pressed_again() {
+    let emu = Emulator::start(
+        "mute_button",
+        "sleep 1500\n\
+         press 1\nsleep 100\nrelease 1\nsleep 1500\n\
+         press 1\nsleep 100\nrelease 1\nsleep 1500\n\
+         press 2\nsleep 80\nrelease 2\nsleep 100\npress 2\nsleep 80\nrelease 2\nsleep 1500\n\
+         quit\n",
+    );
+    let mut port = emu.connect();
+
+    read_until(&mut port, "ch1 muted");
+    read_until(&mut port, "511|0|511|511|511");
+    read_until(&mut port, "ch1 unmuted");
+    read_until(&mut port, "511|511|511|511|511");
+    read_until(&mut port, "ch2 solo");
+    read_until(&mut port, "0|0|511|0|0");
+}
+
+#[test]
+fn fader_flicks_mute_and_reach_the_host() {
+    let emu = Emulator::start(
+        "fader_flicks",
+        "sleep 1500\n\
+         set 3 1023\nsleep 150\nset 3 512\nsleep 1500\n\
+         set 1 1023\nsleep 150\nset 1 512\nsleep 300\n\
+         set 1 1023\nsleep 150\nset 1 512\nsleep 1500\n\
+         quit\n",
+    );
+    let mut port = emu.connect();
+
+    read_until(&mut port, "ch3 muted");
+    read_until(&mut port, "511|511|511|0|511");
+    let skipped = read_until(&mut port, "gesture ch1 double-flick");
+    assert!(
+        !skipped.iter().any(|l| l.contains("ch1 muted")),
+        "a double flick also muted: {:?}",
+        skipped
+    );
+}
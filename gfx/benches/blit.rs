@@ -0,0 +1,86 @@
+//! Per-pixel `draw_iter` versus the direct blitter, on the sheets the
+//! firmware draws every frame.
+//!
+//! On the host `Gray4Frame::draw_iter` gets inlined into the reference
+//! functions, which the `opt-level = 'z'` firmware build never does, so the
+//! reference draws go through `OutOfLine` to keep one call per pixel.
+//!
+//! `cargo bench-host`
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use deej_gfx::blit::{blit_sheet_frame_masked, blit_sheet_frame_masked_crt};
+use deej_gfx::sprite::{draw_sheet_frame_masked, draw_sheet_frame_masked_crt};
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+use embedded_graphics::Pixel;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::{Frame, H, W};
+
+const ITERATIONS: u32 = 2000;
+
+struct OutOfLine<'a>(&'a mut Frame);
+
+impl OriginDimensions for OutOfLine<'_> {
+    fn size(&self) -> Size {
+        self.0.size()
+    }
+}
+
+impl DrawTarget for OutOfLine<'_> {
+    type Color = Gray4;
+    type Error = core::convert::Infallible;
+
+    #[inline(never)]
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0.draw_iter(pixels)
+    }
+}
+
+fn bench(name: &str, mut f: impl FnMut(usize)) {
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        f(i as usize);
+    }
+    let per_iter = start.elapsed() / ITERATIONS;
+    println!("{:<32} {:>8.1} us", name, per_iter.as_secs_f64() * 1e6);
+}
+
+fn main() {
+    let mut frame = Box::new(Frame::new(W, H));
+
+    for s in common::converted() {
+        if s.frames < 2 {
+            continue;
+        }
+        let sheet = s.leak();
+        let pos = Point::new(15, 0);
+
+        bench(&format!("{} draw_iter masked", s.name), |i| {
+            draw_sheet_frame_masked(&mut OutOfLine(&mut frame), &sheet, i % sheet.frames, pos)
+                .unwrap();
+            black_box(frame.as_bytes());
+        });
+        bench(&format!("{} blit masked", s.name), |i| {
+            blit_sheet_frame_masked(&mut frame, &sheet, i % sheet.frames, pos);
+            black_box(frame.as_bytes());
+        });
+        bench(&format!("{} draw_iter crt", s.name), |i| {
+            let mut target = OutOfLine(&mut frame);
+            draw_sheet_frame_masked_crt(&mut target, &sheet, i % sheet.frames, pos, i as u8, true)
+                .unwrap();
+            black_box(frame.as_bytes());
+        });
+        bench(&format!("{} blit crt", s.name), |i| {
+            blit_sheet_frame_masked_crt(&mut frame, &sheet, i % sheet.frames, pos, i as u8, true);
+            black_box(frame.as_bytes());
+        });
+    }
+}
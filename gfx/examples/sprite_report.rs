@@ -0,0 +1,11 @@
+//! Prints how much flash every sprite sheet takes, raw versus encoded.
+//!
+//! `cargo sprite-report`
+
+use std::path::Path;
+
+fn main() {
+    let assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets");
+    let sheets = deej_gfx::assets::convert_manifest(&assets_dir).expect("failed to convert assets");
+    print!("{}", deej_gfx::assets::size_report(&sheets));
+}
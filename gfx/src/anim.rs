@@ -0,0 +1,80 @@
+//! Time-based animation helpers.
+//!
+//! The scenes are advanced by the milliseconds since the previous frame
+//! rather than once per frame, so the frame rate can change without changing
+//! how fast anything moves.
+
+use crate::sheet::SpriteSheet;
+
+/// Turns elapsed time into a whole number of fixed-length steps, carrying the
+/// remainder over to the next call.
+#[derive(Clone, Copy, Debug)]
+pub struct Stepper {
+    steps: u32,
+    period_ms: u32,
+    acc: u32,
+}
+
+impl Stepper {
+    /// `steps` steps every `period_ms` milliseconds.
+    pub const fn new(steps: u32, period_ms: u32) -> Self {
+        Self {
+            steps,
+            period_ms,
+            acc: 0,
+        }
+    }
+
+    /// One step every `period_ms` milliseconds.
+    pub const fn every(period_ms: u32) -> Self {
+        Self::new(1, period_ms)
+    }
+
+    /// Returns how many steps `dt_ms` milliseconds are worth.
+    pub fn advance(&mut self, dt_ms: u32) -> u32 {
+        self.acc += dt_ms * self.steps;
+        let n = self.acc / self.period_ms;
+        self.acc %= self.period_ms;
+        n
+    }
+
+    /// Drops any carried-over time.
+    pub fn reset(&mut self) {
+        self.acc = 0;
+    }
+}
+
+/// Plays the frames of a sprite sheet in a loop, each for the sheet's
+/// `frame_ms`.
+#[derive(Clone, Copy, Debug)]
+pub struct Animation {
+    frames: usize,
+    frame: usize,
+    clock: Stepper,
+}
+
+impl Animation {
+    pub const fn new(sheet: &SpriteSheet) -> Self {
+        Self {
+            frames: sheet.frames,
+            frame: 0,
+            clock: Stepper::every(sheet.frame_ms),
+        }
+    }
+
+    /// The frame to draw now.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn advance(&mut self, dt_ms: u32) {
+        let n = self.clock.advance(dt_ms) as usize;
+        self.frame = (self.frame + n) % self.frames;
+    }
+
+    /// Jumps to frame `frame` and restarts its duration.
+    pub fn restart_at(&mut self, frame: usize) {
+        self.frame = frame % self.frames;
+        self.clock.reset();
+    }
+}
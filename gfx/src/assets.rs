@@ -0,0 +1,431 @@
+//! Converts the PNG strips and animated GIFs listed in `assets/sprites.toml`
+//! into packed Gray4 sheets and generates the firmware's `crate::assets`
+//! handles.
+//!
+//! Raw sheets have frames back to back, every frame row-major with two
+//! pixels per byte and the left pixel in the high nibble, which is what
+//! `ImageRawLE<Gray4>` and the `draw_sheet_frame*` functions expect. Unless
+//! an entry sets `compress = false`, the sheet is then PackBits-coded frame by
+//! frame (see `crate::packbits`).
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::dither::{self, Dither, Gamma, RowDither};
+use crate::sheet::{SheetEncoding, SpriteSheet};
+use crate::{gray4, packbits};
+
+pub type BoxResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    path: String,
+    frame_width: Option<usize>,
+    frame_height: Option<usize>,
+    frames: Option<usize>,
+    #[serde(default)]
+    dither: Dither,
+    gamma: Option<f32>,
+    transparent: Option<String>,
+    #[serde(default = "default_alpha_threshold")]
+    alpha_threshold: u8,
+    #[serde(default = "default_compress")]
+    compress: bool,
+    frame_ms: Option<u32>,
+}
+
+fn default_alpha_threshold() -> u8 {
+    128
+}
+
+fn default_compress() -> bool {
+    true
+}
+
+/// Frame duration of sheets that set none and are not GIFs with a delay.
+const DEFAULT_FRAME_MS: u32 = 140;
+
+/// Decoded source image, always expanded to 8-bit RGBA.
+struct Rgba {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+/// One converted manifest entry.
+pub struct Sheet {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub frames: usize,
+    pub frame_ms: u32,
+    /// Uncompressed frames, back to back.
+    pub raw: Vec<u8>,
+    pub encoding: SheetEncoding,
+}
+
+impl Sheet {
+    pub fn frame_bytes(&self) -> usize {
+        gray4::row_bytes(self.width) * self.height
+    }
+
+    /// The bytes that end up in flash for this sheet's `encoding`.
+    pub fn encoded(&self) -> Vec<u8> {
+        match self.encoding {
+            SheetEncoding::Raw => self.raw.clone(),
+            SheetEncoding::PackBits => packbits::encode_sheet(&self.raw, self.frame_bytes()),
+        }
+    }
+
+    /// A `SpriteSheet` over a leaked copy of the encoded bytes, for host
+    /// tools and tests that draw sheets converted at run time.
+    pub fn leak(&self) -> SpriteSheet {
+        SpriteSheet {
+            data: Box::leak(self.encoded().into_boxed_slice()),
+            width: self.width as u32,
+            height: self.height as u32,
+            frames: self.frames,
+            frame_ms: self.frame_ms,
+            encoding: self.encoding,
+        }
+    }
+}
+
+/// Converts every entry of `assets_dir/sprites.toml`, in name order.
+pub fn convert_manifest(assets_dir: &Path) -> BoxResult<Vec<Sheet>> {
+    let manifest_path = assets_dir.join("sprites.toml");
+    let manifest: BTreeMap<String, Entry> = toml::from_str(&fs::read_to_string(&manifest_path)?)?;
+
+    manifest
+        .iter()
+        .map(|(name, entry)| {
+            convert(name, &assets_dir.join(&entry.path), entry)
+                .map_err(|e| format!("{}: {}", entry.path, e).into())
+        })
+        .collect()
+}
+
+/// Converts every manifest entry, writes the sheets and the generated
+/// `assets.rs` into `out_dir`.
+pub fn generate(assets_dir: &Path, out_dir: &Path) -> BoxResult<()> {
+    let mut module = String::new();
+    writeln!(module, "// @generated by build.rs from assets/sprites.toml")?;
+
+    let sheets = convert_manifest(assets_dir)?;
+    for sheet in &sheets {
+        let file = out_dir.join(format!("{}.gray4", sheet.name));
+        fs::write(&file, sheet.encoded())?;
+
+        writeln!(
+            module,
+            "pub const {}: SpriteSheet = SpriteSheet {{ data: include_bytes!({:?}), width: {}, height: {}, frames: {}, frame_ms: {}, encoding: SheetEncoding::{:?} }};",
+            sheet.name.to_uppercase(),
+            file.display().to_string(),
+            sheet.width,
+            sheet.height,
+            sheet.frames,
+            sheet.frame_ms,
+            sheet.encoding,
+        )?;
+    }
+
+    // Every sheet by manifest name, for settings that refer to one.
+    writeln!(module, "pub const SHEETS: &[(&str, &SpriteSheet)] = &[")?;
+    for sheet in &sheets {
+        writeln!(
+            module,
+            "    ({:?}, &{}),",
+            sheet.name,
+            sheet.name.to_uppercase()
+        )?;
+    }
+    writeln!(module, "];")?;
+
+    fs::write(out_dir.join("assets.rs"), module)?;
+    Ok(())
+}
+
+/// Flash usage per sheet, raw versus encoded.
+pub fn size_report(sheets: &[Sheet]) -> String {
+    let mut out = String::new();
+    let (mut raw_total, mut enc_total) = (0usize, 0usize);
+
+    let _ = writeln!(
+        out,
+        "{:<20} {:>9} {:>6} {:>9} {:>9} {:>6}",
+        "sheet", "size", "frames", "raw", "encoded", "ratio"
+    );
+    for sheet in sheets {
+        let raw = sheet.raw.len();
+        let enc = sheet.encoded().len();
+        raw_total += raw;
+        enc_total += enc;
+        let _ = writeln!(
+            out,
+            "{:<20} {:>9} {:>6} {:>9} {:>9} {:>5.1}%",
+            sheet.name,
+            format!("{}x{}", sheet.width, sheet.height),
+            sheet.frames,
+            raw,
+            enc,
+            enc as f64 * 100.0 / raw as f64,
+        );
+    }
+    let _ = writeln!(
+        out,
+        "{:<20} {:>9} {:>6} {:>9} {:>9} {:>5.1}%",
+        "total",
+        "",
+        "",
+        raw_total,
+        enc_total,
+        enc_total as f64 * 100.0 / raw_total as f64,
+    );
+    out
+}
+
+fn convert(name: &str, path: &Path, entry: &Entry) -> BoxResult<Sheet> {
+    let (frames, has_alpha, delay_ms) = match path.extension().and_then(|e| e.to_str()) {
+        Some("png") => {
+            let (img, has_alpha) = load_png(path)?;
+            (split_strip(img, entry)?, has_alpha, None)
+        }
+        Some("gif") => {
+            let (frames, delay_ms) = load_gif(path)?;
+            (frames, true, delay_ms)
+        }
+        _ => return Err("unsupported source format, expected .png or .gif".into()),
+    };
+
+    let frames = match entry.frames {
+        Some(n) if n > frames.len() => {
+            return Err(format!("asked for {} frames, source has {}", n, frames.len()).into())
+        }
+        Some(n) => &frames[..n],
+        None => &frames[..],
+    };
+    if frames.is_empty() {
+        return Err("no frames".into());
+    }
+
+    let key = entry.transparent.as_deref().map(parse_color).transpose()?;
+    let keyed = key.is_some() || has_alpha;
+
+    let width = frames[0].width;
+    let height = frames[0].height;
+    if width > dither::MAX_WIDTH {
+        let max = dither::MAX_WIDTH;
+        return Err(format!("frames are {} px wide, at most {} fit", width, max).into());
+    }
+    let mut data = Vec::with_capacity(frames.len() * gray4::row_bytes(width) * height);
+
+    for frame in frames {
+        let mut luma = Vec::with_capacity(width * height);
+        let mut opaque = Vec::with_capacity(width * height);
+        for px in &frame.pixels {
+            let transparent =
+                px[3] < entry.alpha_threshold || key.is_some_and(|k| k == [px[0], px[1], px[2]]);
+            luma.push(luma_of(*px));
+            opaque.push(!transparent);
+        }
+
+        let mut nibbles = quantize(&luma, width, entry.dither, entry.gamma);
+        for (n, &o) in nibbles.iter_mut().zip(&opaque) {
+            if !o {
+                *n = 0;
+            } else if keyed && *n == 0 {
+                *n = 1;
+            }
+        }
+
+        for row in nibbles.chunks(width) {
+            for pair in row.chunks(2) {
+                let hi = pair[0];
+                let lo = pair.get(1).copied().unwrap_or(0);
+                data.push((hi << 4) | lo);
+            }
+        }
+    }
+
+    Ok(Sheet {
+        name: name.to_owned(),
+        width,
+        height,
+        frames: frames.len(),
+        frame_ms: entry.frame_ms.or(delay_ms).unwrap_or(DEFAULT_FRAME_MS),
+        raw: data,
+        encoding: if entry.compress {
+            SheetEncoding::PackBits
+        } else {
+            SheetEncoding::Raw
+        },
+    })
+}
+
+fn load_png(path: &Path) -> BoxResult<(Rgba, bool)> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let buf = &buf[..info.buffer_size()];
+
+    let (pixels, has_alpha): (Vec<[u8; 4]>, bool) = match info.color_type {
+        png::ColorType::Grayscale => (buf.iter().map(|&l| [l, l, l, 255]).collect(), false),
+        png::ColorType::GrayscaleAlpha => (
+            buf.chunks_exact(2)
+                .map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            true,
+        ),
+        png::ColorType::Rgb => (
+            buf.chunks_exact(3)
+                .map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            false,
+        ),
+        png::ColorType::Rgba => (
+            buf.chunks_exact(4)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+            true,
+        ),
+        png::ColorType::Indexed => return Err("palette was not expanded".into()),
+    };
+
+    Ok((
+        Rgba {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        },
+        has_alpha,
+    ))
+}
+
+/// Cuts a PNG strip into frames, left to right and then top to bottom.
+fn split_strip(img: Rgba, entry: &Entry) -> BoxResult<Vec<Rgba>> {
+    let fw = entry.frame_width.unwrap_or(img.width);
+    let fh = entry.frame_height.unwrap_or(img.height);
+    if fw == 0 || fh == 0 || !img.width.is_multiple_of(fw) || !img.height.is_multiple_of(fh) {
+        return Err(format!(
+            "{}x{} image is not a grid of {}x{} frames",
+            img.width, img.height, fw, fh
+        )
+        .into());
+    }
+
+    let mut frames = Vec::new();
+    for gy in 0..img.height / fh {
+        for gx in 0..img.width / fw {
+            let mut pixels = Vec::with_capacity(fw * fh);
+            for y in 0..fh {
+                let start = (gy * fh + y) * img.width + gx * fw;
+                pixels.extend_from_slice(&img.pixels[start..start + fw]);
+            }
+            frames.push(Rgba {
+                width: fw,
+                height: fh,
+                pixels,
+            });
+        }
+    }
+    Ok(frames)
+}
+
+/// Composites every GIF frame onto the logical screen, honouring the
+/// disposal method of the previous frame. Also returns the first frame's
+/// delay, if it has one; sheets play at a single frame rate.
+fn load_gif(path: &Path) -> BoxResult<(Vec<Rgba>, Option<u32>)> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(File::open(path)?)?;
+
+    let width = decoder.width() as usize;
+    let height = decoder.height() as usize;
+    let mut canvas = vec![[0u8; 4]; width * height];
+    let mut frames = Vec::new();
+    let mut delay_ms = None;
+
+    while let Some(frame) = decoder.read_next_frame()? {
+        if frames.is_empty() && frame.delay > 0 {
+            // GIF delays are in hundredths of a second.
+            delay_ms = Some(frame.delay as u32 * 10);
+        }
+        let saved = (frame.dispose == gif::DisposalMethod::Previous).then(|| canvas.clone());
+        let (left, top) = (frame.left as usize, frame.top as usize);
+        let (fw, fh) = (frame.width as usize, frame.height as usize);
+
+        for y in 0..fh {
+            for x in 0..fw {
+                let (cx, cy) = (left + x, top + y);
+                if cx >= width || cy >= height {
+                    continue;
+                }
+                let i = (y * fw + x) * 4;
+                let px = &frame.buffer[i..i + 4];
+                if px[3] != 0 {
+                    canvas[cy * width + cx] = [px[0], px[1], px[2], px[3]];
+                }
+            }
+        }
+
+        frames.push(Rgba {
+            width,
+            height,
+            pixels: canvas.clone(),
+        });
+
+        match frame.dispose {
+            gif::DisposalMethod::Background => {
+                for y in top..(top + fh).min(height) {
+                    for x in left..(left + fw).min(width) {
+                        canvas[y * width + x] = [0; 4];
+                    }
+                }
+            }
+            gif::DisposalMethod::Previous => {
+                if let Some(saved) = saved {
+                    canvas = saved;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((frames, delay_ms))
+}
+
+fn parse_color(s: &str) -> BoxResult<[u8; 3]> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 {
+        return Err(format!("bad colour {:?}, expected #rrggbb", s).into());
+    }
+    let v = u32::from_str_radix(hex, 16)?;
+    Ok([(v >> 16) as u8, (v >> 8) as u8, v as u8])
+}
+
+#[inline]
+fn luma_of(px: [u8; 4]) -> u8 {
+    ((px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114 + 500) / 1000) as u8
+}
+
+/// Maps 8-bit luma to 0..=15, see `crate::dither`.
+fn quantize(luma: &[u8], width: usize, dither: Dither, gamma: Option<f32>) -> Vec<u8> {
+    let mut rows = RowDither::new(dither, width);
+    if let Some(gamma) = gamma {
+        rows = rows.with_gamma(Gamma::new(gamma));
+    }
+
+    let mut out = vec![0u8; luma.len()];
+    for (src, dst) in luma.chunks(width).zip(out.chunks_mut(width)) {
+        rows.next_row(src, dst);
+    }
+    out
+}
@@ -0,0 +1,295 @@
+//! Direct-to-framebuffer Gray4 blitter.
+//!
+//! Same output as the `sprite::draw_sheet_frame*` functions, but instead of
+//! one `draw_iter` call per pixel it clips each sheet row once and writes the
+//! nibbles straight into the `Gray4Frame` bytes.
+
+use embedded_graphics::prelude::Point;
+
+use crate::frame::Gray4Frame;
+use crate::sheet::SpriteSheet;
+
+/// Blits frame `idx` of `sheet` at `pos`, clipped to the frame.
+///
+/// `row_shift(y)` moves sheet row `y` horizontally. `transform(x, y, v)`
+/// maps every non-zero source nibble at sheet coordinates `(x, y)` to the
+/// value written; returning 0 leaves the destination pixel untouched.
+///
+/// The clipped bounding box of the sprite is added to the frame's dirty set.
+pub fn blit_sheet_frame_with<const N: usize, R, F>(
+    frame: &mut Gray4Frame<N>,
+    sheet: &SpriteSheet,
+    idx: usize,
+    pos: Point,
+    mut row_shift: R,
+    mut transform: F,
+) where
+    R: FnMut(u32) -> i32,
+    F: FnMut(u32, u32, u8) -> u8,
+{
+    let frame_w = frame.width() as i32;
+    let frame_h = frame.height() as i32;
+    let w = sheet.width as i32;
+
+    let mut rows = sheet.rows(idx);
+    let (mut bx0, mut by0, mut bx1, mut by1) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+
+    for y in 0..sheet.height {
+        // Always pull the row, coded sheets can only be read in order.
+        let row = rows.next_row();
+
+        let dy = pos.y + y as i32;
+        if dy < 0 {
+            continue;
+        }
+        if dy >= frame_h {
+            break;
+        }
+
+        let dx0 = pos.x + row_shift(y);
+        let x_start = core::cmp::max(0, -dx0);
+        let x_end = core::cmp::min(w, frame_w - dx0);
+        if x_start >= x_end {
+            continue;
+        }
+
+        bx0 = bx0.min(dx0 + x_start);
+        bx1 = bx1.max(dx0 + x_end);
+        by0 = by0.min(dy);
+        by1 = by1.max(dy + 1);
+
+        let dst = frame.row_mut(dy as usize);
+
+        if (dx0 & 1) == 0 {
+            // Source and destination nibbles line up: walk whole bytes and
+            // skip fully transparent ones.
+            let s0 = (x_start >> 1) as usize;
+            let d0 = ((dx0 + x_start) >> 1) as usize;
+            let n = ((x_end - x_start + 1) >> 1) as usize;
+
+            for (i, (s, d)) in row[s0..s0 + n].iter().zip(&mut dst[d0..d0 + n]).enumerate() {
+                if *s == 0 {
+                    continue;
+                }
+                let x = x_start + 2 * i as i32;
+
+                let hi = s >> 4;
+                if hi != 0 {
+                    let out = transform(x as u32, y, hi) & 0x0F;
+                    if out != 0 {
+                        *d = (*d & 0x0F) | (out << 4);
+                    }
+                }
+
+                let lo = s & 0x0F;
+                if lo != 0 && x + 1 < x_end {
+                    let out = transform(x as u32 + 1, y, lo) & 0x0F;
+                    if out != 0 {
+                        *d = (*d & 0xF0) | out;
+                    }
+                }
+            }
+        } else {
+            let mut x = x_start;
+            while x < x_end {
+                let b = row[(x >> 1) as usize];
+                if b == 0 {
+                    // Jump to the first pixel of the next source byte.
+                    x = (x | 1) + 1;
+                    continue;
+                }
+
+                let v = if (x & 1) == 0 { b >> 4 } else { b & 0x0F };
+                if v != 0 {
+                    let out = transform(x as u32, y, v) & 0x0F;
+                    if out != 0 {
+                        let dx = (dx0 + x) as usize;
+                        let d = &mut dst[dx >> 1];
+                        if (dx & 1) == 0 {
+                            *d = (*d & 0x0F) | (out << 4);
+                        } else {
+                            *d = (*d & 0xF0) | out;
+                        }
+                    }
+                }
+                x += 1;
+            }
+        }
+    }
+
+    if bx0 < bx1 {
+        frame.mark_dirty(bx0, by0, bx1 - bx0, by1 - by0);
+    }
+}
+
+/// Counterpart of `draw_sheet_frame_masked`: nibble 0 is transparent.
+pub fn blit_sheet_frame_masked<const N: usize>(
+    frame: &mut Gray4Frame<N>,
+    sheet: &SpriteSheet,
+    idx: usize,
+    pos: Point,
+) {
+    blit_sheet_frame_with(frame, sheet, idx, pos, |_| 0, |_, _, v| v);
+}
+
+/// Counterpart of `draw_sheet_frame_flash`.
+pub fn blit_sheet_frame_flash<const N: usize>(
+    frame: &mut Gray4Frame<N>,
+    sheet: &SpriteSheet,
+    idx: usize,
+    pos: Point,
+    flash_step: u8,
+    flash_steps: u8,
+) {
+    if flash_steps == 0 || flash_step >= flash_steps {
+        return blit_sheet_frame_masked(frame, sheet, idx, pos);
+    }
+
+    const MAX_BOOST: u8 = 6;
+
+    let steps = flash_steps as u16;
+    let remaining = steps.saturating_sub(flash_step as u16);
+    let boost: u8 = ((remaining * MAX_BOOST as u16 + steps / 2) / steps) as u8;
+
+    blit_sheet_frame_with(
+        frame,
+        sheet,
+        idx,
+        pos,
+        |_| 0,
+        |_, _, v| core::cmp::min(v.saturating_add(boost), 15),
+    );
+}
+
+/// Counterpart of `draw_sheet_frame_masked_crt`.
+pub fn blit_sheet_frame_masked_crt<const N: usize>(
+    frame: &mut Gray4Frame<N>,
+    sheet: &SpriteSheet,
+    idx: usize,
+    pos: Point,
+    frame_tick: u8,
+    is_glitching: bool,
+) {
+    if !is_glitching {
+        return blit_sheet_frame_masked(frame, sheet, idx, pos);
+    }
+
+    blit_sheet_frame_with(
+        frame,
+        sheet,
+        idx,
+        pos,
+        |y| {
+            let seed = (y as u8)
+                .wrapping_mul(13)
+                .wrapping_add(frame_tick.wrapping_mul(7));
+            match seed & 0x07 {
+                0 => -2,
+                1 => -1,
+                2 => 0,
+                3 => 1,
+                4 => 2,
+                5 => 0,
+                6 => -1,
+                _ => 1,
+            }
+        },
+        |_, _, v| v.saturating_sub(1),
+    );
+}
+
+/// Counterpart of `draw_sheet_frame_fade_dither`.
+pub fn blit_sheet_frame_fade_dither<const N: usize>(
+    frame: &mut Gray4Frame<N>,
+    sheet: &SpriteSheet,
+    idx: usize,
+    pos: Point,
+    fade_step: u8,
+    fade_steps: u8,
+) {
+    if fade_steps == 0 || fade_step == 0 {
+        return blit_sheet_frame_masked(frame, sheet, idx, pos);
+    }
+
+    let clamped_step = core::cmp::min(fade_step, fade_steps) as u16;
+    let num = (fade_steps as u16).saturating_sub(clamped_step);
+    let den = fade_steps as u16;
+    let dither_on = clamped_step > (fade_steps as u16 / 3);
+
+    blit_sheet_frame_with(
+        frame,
+        sheet,
+        idx,
+        pos,
+        |_| 0,
+        |_, y, v| {
+            if num == 0 {
+                return 0;
+            }
+            let v = core::cmp::min(((v as u16 * num + den / 2) / den) as u8, 15);
+            // The reference picks the dither parity once per source byte,
+            // at its even pixel, so it ends up alternating by row only.
+            if dither_on && (y & 1) != 0 {
+                v.saturating_sub(1)
+            } else {
+                v
+            }
+        },
+    );
+}
+
+/// Copies a packed Gray4 image (left pixel in the high nibble) opaquely,
+/// clipped to the frame.
+pub fn blit_image<const N: usize>(
+    frame: &mut Gray4Frame<N>,
+    data: &[u8],
+    w: u32,
+    h: u32,
+    pos: Point,
+) {
+    let stride = crate::gray4::row_bytes(w as usize);
+    let frame_w = frame.width() as i32;
+    let frame_h = frame.height() as i32;
+
+    frame.mark_dirty(pos.x, pos.y, w as i32, h as i32);
+
+    for y in 0..h as i32 {
+        let dy = pos.y + y;
+        if dy < 0 {
+            continue;
+        }
+        if dy >= frame_h {
+            break;
+        }
+
+        let x_start = core::cmp::max(0, -pos.x);
+        let x_end = core::cmp::min(w as i32, frame_w - pos.x);
+        if x_start >= x_end {
+            return;
+        }
+
+        let src = &data[y as usize * stride..(y as usize + 1) * stride];
+        let dst = frame.row_mut(dy as usize);
+
+        if (pos.x & 1) == 0 && (x_start & 1) == 0 && (x_end & 1) == 0 {
+            // Byte-aligned: whole bytes can be copied.
+            let d0 = ((pos.x + x_start) >> 1) as usize;
+            let s0 = (x_start >> 1) as usize;
+            let n = ((x_end - x_start) >> 1) as usize;
+            dst[d0..d0 + n].copy_from_slice(&src[s0..s0 + n]);
+            continue;
+        }
+
+        for x in x_start..x_end {
+            let b = src[(x >> 1) as usize];
+            let v = if (x & 1) == 0 { b >> 4 } else { b & 0x0F };
+            let dx = (pos.x + x) as usize;
+            let d = &mut dst[dx >> 1];
+            if (dx & 1) == 0 {
+                *d = (*d & 0x0F) | (v << 4);
+            } else {
+                *d = (*d & 0xF0) | v;
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Press detection for a push button sampled at a steady rate.
+//!
+//! `Button` debounces the raw samples and tells short, double and long
+//! presses apart. A short press is only reported once `DOUBLE_PRESS_MS` have
+//! passed without a second one, so a double press never starts with a short
+//! one. A long press is reported while the button is still held, and its
+//! release reports nothing.
+
+/// How long the pin has to stay at a new level to count.
+pub const DEBOUNCE_MS: u32 = 20;
+/// Holding the button this long is a long press.
+pub const LONG_PRESS_MS: u32 = 600;
+/// Longest gap between the release of a first press and a second press.
+pub const DOUBLE_PRESS_MS: u32 = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Press {
+    Short,
+    Double,
+    Long,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Button {
+    /// Last sample, and since when it has read that.
+    raw: bool,
+    raw_since: u32,
+    /// Debounced state.
+    down: bool,
+    down_since: u32,
+    /// The long press of the current hold has been reported.
+    long_reported: bool,
+    /// Release time of a short press that may still become a double one.
+    first_release: Option<u32>,
+}
+
+impl Button {
+    pub const fn new() -> Self {
+        Self {
+            raw: false,
+            raw_since: 0,
+            down: false,
+            down_since: 0,
+            long_reported: false,
+            first_release: None,
+        }
+    }
+
+    /// Feeds the sample taken at `now_ms`, `down` while pressed, and returns
+    /// the press it completes, if any.
+    pub fn update(&mut self, now_ms: u32, down: bool) -> Option<Press> {
+        if down != self.raw {
+            self.raw = down;
+            self.raw_since = now_ms;
+        }
+
+        if self.raw != self.down && now_ms.wrapping_sub(self.raw_since) >= DEBOUNCE_MS {
+            self.down = self.raw;
+            if self.down {
+                self.down_since = now_ms;
+                self.long_reported = false;
+            } else if !self.long_reported {
+                if self.first_release.take().is_some() {
+                    return Some(Press::Double);
+                }
+                self.first_release = Some(now_ms);
+            }
+        }
+
+        if self.down {
+            if !self.long_reported && now_ms.wrapping_sub(self.down_since) >= LONG_PRESS_MS {
+                self.long_reported = true;
+                // A tap just before a long press is part of it.
+                self.first_release = None;
+                return Some(Press::Long);
+            }
+        } else if let Some(release) = self.first_release {
+            if now_ms.wrapping_sub(release) >= DOUBLE_PRESS_MS {
+                self.first_release = None;
+                return Some(Press::Short);
+            }
+        }
+        None
+    }
+}
+
+impl Default for Button {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,161 @@
+//! The settings the firmware keeps across power cycles, and their encoding.
+//!
+//! A stored `Config` is a schema version byte followed by the config in
+//! postcard; the store it goes in (`kv`) checks it with a CRC. Decoding an
+//! older version migrates it, taking whatever it lacks from the defaults.
+//! Bytes that do not decode, and versions newer than this build knows, read
+//! as nothing, and the caller falls back to the defaults.
+//!
+//! Version 1 is the settings record of earlier builds, which only held a
+//! theme name: its magic word, 0xDEE1_0001 in little endian, happens to
+//! start with the 1. Version 2 had no fader targets.
+
+use core::fmt;
+
+use heapless::String;
+use serde::{Deserialize, Serialize};
+
+/// Version written by this build.
+pub const SCHEMA: u8 = 3;
+
+/// Faders on the board.
+pub const CHANNELS: usize = 5;
+
+/// Longest encoding of a `Config`, with every string full.
+pub const MAX_BYTES: usize = 576;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    /// Theme on screen, by name rather than index so that builds with other
+    /// themes still find it.
+    pub theme: String<16>,
+    pub channels: [ChannelConfig; CHANNELS],
+    /// Smallest fader change passed on to deej.
+    pub noise_threshold: u16,
+    /// How long the active channel screen stays up after the last change.
+    pub active_channel_ttl_ms: u32,
+    pub frame_period_ms: u32,
+    /// Read once, when USB comes up.
+    pub usb: UsbStrings,
+    pub targets: [TargetConfig; CHANNELS],
+}
+
+/// Calibration of one fader: its raw readings at either end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelConfig {
+    pub invert: bool,
+    pub min: u16,
+    pub max: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsbStrings {
+    pub manufacturer: String<32>,
+    pub product: String<32>,
+    pub serial_number: String<16>,
+}
+
+/// What a fader controls on the host, and how the device shows it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetConfig {
+    /// deej's name for the target: `master`, `mic`, a process such as
+    /// `chrome.exe`, or several of them separated by commas.
+    pub id: String<48>,
+    /// For people, e.g. `Browser`.
+    pub name: String<16>,
+    /// Sprite on the active channel screen, by its name in
+    /// `assets/sprites.toml`.
+    pub icon: String<16>,
+}
+
+/// Schema 2: everything but the targets.
+#[derive(Deserialize)]
+struct ConfigV2 {
+    theme: String<16>,
+    channels: [ChannelConfig; CHANNELS],
+    noise_threshold: u16,
+    active_channel_ttl_ms: u32,
+    frame_period_ms: u32,
+    usb: UsbStrings,
+}
+
+/// The settings record of schema 1: magic word, then the theme name,
+/// zero-padded.
+const V1_MAGIC: [u8; 4] = 0xDEE1_0001u32.to_le_bytes();
+const V1_NAME: usize = 16;
+
+impl Config {
+    /// Writes the schema version and the config into `buf` and returns the
+    /// bytes used.
+    pub fn encode<'a>(&self, buf: &'a mut [u8; MAX_BYTES]) -> &'a [u8] {
+        buf[0] = SCHEMA;
+        let len = postcard::to_slice(self, &mut buf[1..])
+            .expect("MAX_BYTES fits any config")
+            .len();
+        &buf[..1 + len]
+    }
+
+    /// CRC-32 of the encoded config, to tell whether it changed since it was
+    /// last saved.
+    pub fn checksum(&self) -> u32 {
+        let mut buf = [0u8; MAX_BYTES];
+        crate::kv::CRC.checksum(self.encode(&mut buf))
+    }
+
+    /// Reads a config of this or an older schema.
+    pub fn decode(bytes: &[u8], defaults: &Self) -> Option<Self> {
+        match bytes.first()? {
+            1 => Self::from_v1(bytes, defaults),
+            2 => Self::from_v2(&bytes[1..], defaults),
+            &SCHEMA => postcard::from_bytes(&bytes[1..]).ok(),
+            _ => None,
+        }
+    }
+
+    fn from_v1(bytes: &[u8], defaults: &Self) -> Option<Self> {
+        let name = bytes.strip_prefix(&V1_MAGIC)?.get(..V1_NAME)?;
+        let len = name.iter().position(|&b| b == 0).unwrap_or(V1_NAME);
+        let name = core::str::from_utf8(&name[..len]).ok()?;
+
+        let mut config = defaults.clone();
+        config.theme = String::try_from(name).ok()?;
+        Some(config)
+    }
+
+    fn from_v2(bytes: &[u8], defaults: &Self) -> Option<Self> {
+        let v2: ConfigV2 = postcard::from_bytes(bytes).ok()?;
+        Some(Self {
+            theme: v2.theme,
+            channels: v2.channels,
+            noise_threshold: v2.noise_threshold,
+            active_channel_ttl_ms: v2.active_channel_ttl_ms,
+            frame_period_ms: v2.frame_period_ms,
+            usb: v2.usb,
+            targets: defaults.targets.clone(),
+        })
+    }
+
+    /// Hands `line` the `slider_mapping` block of a deej `config.yaml` for
+    /// the targets, one line at a time. Faders without a target are left
+    /// out.
+    pub fn slider_mapping(&self, mut line: impl FnMut(fmt::Arguments)) {
+        line(format_args!("slider_mapping:"));
+        for (i, target) in self.targets.iter().enumerate() {
+            let mut ids = target
+                .id
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty());
+            match (ids.next(), ids.next()) {
+                (None, _) => {}
+                (Some(id), None) => line(format_args!("  {}: {}", i, id)),
+                (Some(first), Some(second)) => {
+                    line(format_args!("  {}:", i));
+                    for id in [first, second].into_iter().chain(ids) {
+                        line(format_args!("    - {}", id));
+                    }
+                }
+            }
+        }
+    }
+}
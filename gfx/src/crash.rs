@@ -0,0 +1,209 @@
+//! What the firmware keeps about its last crash, and the screen it shows on
+//! the way down.
+//!
+//! A `CrashRecord` is fixed-size and built without allocating, so the panic
+//! and HardFault handlers can fill one in, draw it and write it to flash as
+//! is. Text that does not fit is cut short.
+
+use core::fmt::{self, Write};
+
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+
+use crate::frame::Gray4Frame;
+use crate::text::{FixedStr, Lines};
+
+/// Longest source path kept; longer ones keep their tail.
+pub const FILE_BYTES: usize = 48;
+/// Longest panic message kept.
+pub const MESSAGE_BYTES: usize = 160;
+
+/// Changes version the record layout; anything else reads as no record.
+const MAGIC: u32 = 0xDEEC_0001;
+
+/// Registers the core stacks on an exception, in stacking order.
+pub type Registers = [u32; 8];
+pub const REGISTER_NAMES: [&str; 8] = ["r0", "r1", "r2", "r3", "r12", "lr", "pc", "xpsr"];
+
+const _: () = assert!(CrashRecord::BYTES <= 256);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrashKind {
+    Panic,
+    HardFault,
+    /// A task stopped checking in and the watchdog reset the chip.
+    Watchdog,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrashRecord {
+    pub kind: CrashKind,
+    /// Source line of a panic, 0 when unknown.
+    pub line: u32,
+    file: FixedStr<FILE_BYTES>,
+    message: FixedStr<MESSAGE_BYTES>,
+    /// Stacked registers of a HardFault.
+    pub registers: Option<Registers>,
+}
+
+impl CrashRecord {
+    /// Serialized size; fits one 256-byte flash page.
+    pub const BYTES: usize = 4 + 4 + 4 + 4 * 8 + FILE_BYTES + MESSAGE_BYTES;
+
+    pub fn panic(file: &str, line: u32, message: impl fmt::Display) -> Self {
+        let mut record = Self::empty(CrashKind::Panic);
+        // The tail of a path says more than its head.
+        let skip = file.len().saturating_sub(FILE_BYTES);
+        let start = (skip..=file.len())
+            .find(|&i| file.is_char_boundary(i))
+            .unwrap_or(file.len());
+        record.file.push_str(&file[start..]);
+        record.line = line;
+        let _ = write!(record.message, "{}", message);
+        record
+    }
+
+    pub fn hard_fault(registers: Registers) -> Self {
+        let mut record = Self::empty(CrashKind::HardFault);
+        record.registers = Some(registers);
+        record
+    }
+
+    /// The message names the task that missed its deadline.
+    pub fn watchdog(task: &str) -> Self {
+        let mut record = Self::empty(CrashKind::Watchdog);
+        record.message.push_str(task);
+        record
+    }
+
+    fn empty(kind: CrashKind) -> Self {
+        Self {
+            kind,
+            line: 0,
+            file: FixedStr::new(),
+            message: FixedStr::new(),
+            registers: None,
+        }
+    }
+
+    pub fn file(&self) -> &str {
+        self.file.as_str()
+    }
+
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::BYTES] {
+        let mut bytes = [0u8; Self::BYTES];
+        bytes[..4].copy_from_slice(&MAGIC.to_le_bytes());
+        bytes[4] = match self.kind {
+            CrashKind::Panic => 1,
+            CrashKind::HardFault => 2,
+            CrashKind::Watchdog => 3,
+        };
+        bytes[5] = self.registers.is_some() as u8;
+        bytes[6] = self.file.len;
+        bytes[7] = self.message.len;
+        bytes[8..12].copy_from_slice(&self.line.to_le_bytes());
+        let registers = self.registers.unwrap_or_default();
+        for (chunk, r) in bytes[12..44].chunks_exact_mut(4).zip(registers) {
+            chunk.copy_from_slice(&r.to_le_bytes());
+        }
+        bytes[44..44 + FILE_BYTES].copy_from_slice(&self.file.buf);
+        bytes[44 + FILE_BYTES..].copy_from_slice(&self.message.buf);
+        bytes
+    }
+
+    /// Reads a record back; `None` for erased flash, another layout or a
+    /// torn write.
+    pub fn from_bytes(bytes: &[u8; Self::BYTES]) -> Option<Self> {
+        let word =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        if word(0) != MAGIC {
+            return None;
+        }
+        let kind = match bytes[4] {
+            1 => CrashKind::Panic,
+            2 => CrashKind::HardFault,
+            3 => CrashKind::Watchdog,
+            _ => return None,
+        };
+        let mut registers = [0u32; 8];
+        for (i, r) in registers.iter_mut().enumerate() {
+            *r = word(12 + 4 * i);
+        }
+        Some(Self {
+            kind,
+            line: word(8),
+            file: FixedStr::from_bytes(&bytes[44..44 + FILE_BYTES], bytes[6])?,
+            message: FixedStr::from_bytes(&bytes[44 + FILE_BYTES..], bytes[7])?,
+            registers: (bytes[5] == 1).then_some(registers),
+        })
+    }
+
+    /// Draws the crash screen: what happened, where, the message wrapped to
+    /// the frame width and the registers of a HardFault. Whatever does not
+    /// fit below is left out.
+    pub fn draw<const N: usize>(&self, frame: &mut Gray4Frame<N>) {
+        let _ = frame.clear(Gray4::BLACK);
+        let mut screen = Lines::new(frame, Point::zero());
+        let columns = screen.columns();
+
+        match self.kind {
+            CrashKind::Panic => screen.line(format_args!("PANIC")),
+            CrashKind::HardFault => screen.line(format_args!("HARDFAULT")),
+            CrashKind::Watchdog => screen.line(format_args!("WATCHDOG")),
+        }
+        if !self.file().is_empty() {
+            screen.line(format_args!("{}:{}", self.file(), self.line));
+        }
+
+        let mut rest = self.message();
+        while !rest.is_empty() {
+            let split = rest
+                .char_indices()
+                .nth(columns)
+                .map_or(rest.len(), |(i, _)| i);
+            let (line, tail) = rest.split_at(split);
+            screen.line(format_args!("{}", line));
+            rest = tail;
+        }
+
+        if let Some(registers) = self.registers {
+            // Two registers per line when the panel is narrow.
+            let per_line = if columns >= 4 * 14 { 4 } else { 2 };
+            for first in (0..registers.len()).step_by(per_line) {
+                let mut line = FixedStr::<64>::new();
+                let names = REGISTER_NAMES.iter().zip(registers);
+                for (name, value) in names.skip(first).take(per_line) {
+                    let _ = write!(line, "{:>4}={:08x} ", name, value);
+                }
+                screen.line(format_args!("{}", line.as_str()));
+            }
+        }
+    }
+}
+
+impl fmt::Display for CrashRecord {
+    /// One line for the log.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            CrashKind::Panic => write!(
+                f,
+                "panic at {}:{}: {}",
+                self.file(),
+                self.line,
+                self.message()
+            )?,
+            CrashKind::HardFault => f.write_str("hard fault")?,
+            CrashKind::Watchdog => write!(f, "watchdog reset: {} stalled", self.message())?,
+        }
+        if let Some(registers) = self.registers {
+            for (name, value) in REGISTER_NAMES.iter().zip(registers) {
+                write!(f, " {}={:#010x}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
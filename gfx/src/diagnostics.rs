@@ -0,0 +1,173 @@
+//! The diagnostics screens and the per-channel statistics behind them.
+//!
+//! `draw_test_pattern` shows whether every grey level and every pixel of the
+//! panel works; `draw_readout` shows the raw ADC counts with how much each
+//! channel wanders while the faders are left alone.
+
+use core::fmt;
+
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+
+use crate::frame::Gray4Frame;
+use crate::text::{Lines, FONT_HEIGHT, FONT_WIDTH};
+
+/// Characters left for the text of a readout line.
+const READOUT_COLUMNS: i32 = 28;
+/// Narrower bars are not drawn.
+const MIN_BAR_WIDTH: i32 = 32;
+
+/// Raw readings of one ADC channel, and failed reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoiseStats {
+    reads: u32,
+    errors: u32,
+    last: u16,
+    min: u16,
+    max: u16,
+    sum: u64,
+    sum_sq: u64,
+}
+
+impl NoiseStats {
+    pub const fn new() -> Self {
+        Self {
+            reads: 0,
+            errors: 0,
+            last: 0,
+            min: u16::MAX,
+            max: 0,
+            sum: 0,
+            sum_sq: 0,
+        }
+    }
+
+    /// Adds a reading, or a failed read for `None`.
+    pub fn add(&mut self, raw: Option<u16>) {
+        let Some(raw) = raw else {
+            self.errors += 1;
+            return;
+        };
+        self.reads += 1;
+        self.last = raw;
+        self.min = self.min.min(raw);
+        self.max = self.max.max(raw);
+        self.sum += raw as u64;
+        self.sum_sq += raw as u64 * raw as u64;
+    }
+
+    pub fn reads(&self) -> u32 {
+        self.reads
+    }
+
+    pub fn errors(&self) -> u32 {
+        self.errors
+    }
+
+    /// Latest reading.
+    pub fn last(&self) -> Option<u16> {
+        (self.reads > 0).then_some(self.last)
+    }
+
+    /// Lowest and highest reading.
+    pub fn range(&self) -> Option<(u16, u16)> {
+        (self.reads > 0).then_some((self.min, self.max))
+    }
+
+    pub fn mean(&self) -> Option<f32> {
+        (self.reads > 0).then(|| self.sum as f32 / self.reads as f32)
+    }
+
+    /// Standard deviation of the readings, the noise of a fader at rest.
+    pub fn std_dev(&self) -> Option<f32> {
+        let n = self.reads as u64;
+        if n == 0 {
+            return None;
+        }
+        // n² times the variance, exact in integers.
+        let spread = (n * self.sum_sq).saturating_sub(self.sum * self.sum);
+        Some(libm::sqrtf(spread as f32) / n as f32)
+    }
+}
+
+impl Default for NoiseStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for NoiseStats {
+    /// One line for the log.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.last(), self.range(), self.mean(), self.std_dev()) {
+            (Some(last), Some((min, max)), Some(mean), Some(sd)) => write!(
+                f,
+                "raw {} min {} max {} mean {:.1} sd {:.1}",
+                last, min, max, mean, sd
+            )?,
+            _ => f.write_str("no readings")?,
+        }
+        write!(f, ", {} reads, {} errors", self.reads, self.errors)
+    }
+}
+
+/// Draws a strip of the 16 grey levels over a one-pixel checkerboard, in a
+/// full-white border.
+pub fn draw_test_pattern<const N: usize>(frame: &mut Gray4Frame<N>) {
+    let (w, h) = (frame.width(), frame.height());
+    let strip = h / 2;
+    for y in 0..h {
+        for x in 0..w {
+            let edge = x == 0 || y == 0 || x == w - 1 || y == h - 1;
+            let level = if edge {
+                15
+            } else if y < strip {
+                (x * 16 / w) as u8
+            } else {
+                ((x + y) % 2 * 15) as u8
+            };
+            frame.set(x, y, level);
+        }
+    }
+    frame.mark_all_dirty();
+}
+
+/// Draws `status` and one line per channel, raw reading and noise, with a
+/// bar of the reading right of the text where the panel is wide enough.
+pub fn draw_readout<const N: usize>(
+    frame: &mut Gray4Frame<N>,
+    status: fmt::Arguments,
+    channels: &[NoiseStats],
+) {
+    let _ = frame.clear(Gray4::BLACK);
+
+    let mut lines = Lines::new(frame, Point::zero());
+    lines.line(status);
+    for (i, stats) in channels.iter().enumerate() {
+        match (stats.last(), stats.std_dev()) {
+            (Some(raw), Some(sd)) => lines.line(format_args!(
+                "ch{} {:4} sd {:4.1} err {}",
+                i, raw, sd, stats.errors
+            )),
+            _ => lines.line(format_args!("ch{} ---- err {}", i, stats.errors)),
+        }
+    }
+
+    let bar_x = READOUT_COLUMNS * FONT_WIDTH;
+    let bar_width = frame.width() as i32 - bar_x - 1;
+    if bar_width < MIN_BAR_WIDTH {
+        return;
+    }
+    for (i, stats) in channels.iter().enumerate() {
+        let y = FONT_HEIGHT * (i as i32 + 1);
+        let Some(raw) = stats.last() else { continue };
+        let len = bar_width * raw as i32 / 1023;
+        let _ = Rectangle::new(
+            Point::new(bar_x, y + 1),
+            Size::new(len as u32, FONT_HEIGHT as u32 - 2),
+        )
+        .into_styled(PrimitiveStyle::with_fill(Gray4::new(10)))
+        .draw(frame);
+    }
+}
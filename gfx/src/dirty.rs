@@ -0,0 +1,156 @@
+//! Dirty-rectangle bookkeeping for partial display updates.
+//!
+//! A frame's dirty set covers every pixel that may differ from a black
+//! frame. What has to be sent to the panel is the union of the new frame's
+//! set and the set of the frame currently shown.
+
+/// Most rectangles kept per set. Adding one more merges the pair whose
+/// union grows the covered area the least.
+pub const MAX_DIRTY_RECTS: usize = 4;
+
+/// Pixel rectangle with exclusive right and bottom edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x0: u16,
+    pub y0: u16,
+    pub x1: u16,
+    pub y1: u16,
+}
+
+impl Rect {
+    pub const fn new(x0: u16, y0: u16, x1: u16, y1: u16) -> Self {
+        Self { x0, y0, x1, y1 }
+    }
+
+    #[inline]
+    pub const fn width(&self) -> u32 {
+        (self.x1 - self.x0) as u32
+    }
+
+    #[inline]
+    pub const fn height(&self) -> u32 {
+        (self.y1 - self.y0) as u32
+    }
+
+    #[inline]
+    pub const fn area(&self) -> u32 {
+        self.width() * self.height()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.x0 >= self.x1 || self.y0 >= self.y1
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+
+    /// True if the rectangles overlap or share an edge.
+    pub fn touches(&self, other: &Rect) -> bool {
+        self.x0 <= other.x1 && other.x0 <= self.x1 && self.y0 <= other.y1 && other.y0 <= self.y1
+    }
+
+    /// Widens the rectangle so both edges fall on multiples of `align`
+    /// pixels, clamped to `max_x`.
+    pub fn align_x(&self, align: u16, max_x: u16) -> Rect {
+        Rect {
+            x0: self.x0 / align * align,
+            x1: core::cmp::min(self.x1.div_ceil(align) * align, max_x),
+            ..*self
+        }
+    }
+}
+
+/// Small fixed-capacity set of non-touching rectangles.
+#[derive(Clone, Copy, Debug)]
+pub struct DirtyRects {
+    rects: [Rect; MAX_DIRTY_RECTS],
+    len: usize,
+}
+
+impl DirtyRects {
+    pub const fn new() -> Self {
+        Self {
+            rects: [Rect::new(0, 0, 0, 0); MAX_DIRTY_RECTS],
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[Rect] {
+        &self.rects[..self.len]
+    }
+
+    /// Total covered area in pixels.
+    pub fn area(&self) -> u32 {
+        self.as_slice().iter().map(Rect::area).sum()
+    }
+
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+
+        // Absorb everything the new rectangle touches. A grown rectangle may
+        // reach others, so keep going until nothing touches it.
+        let mut rect = rect;
+        let mut i = 0;
+        while i < self.len {
+            if self.rects[i].touches(&rect) {
+                rect = rect.union(&self.rects[i]);
+                self.remove(i);
+                i = 0;
+            } else {
+                i += 1;
+            }
+        }
+
+        if self.len == MAX_DIRTY_RECTS {
+            let (best, merged) = self
+                .as_slice()
+                .iter()
+                .enumerate()
+                .map(|(i, r)| (i, r.union(&rect)))
+                .min_by_key(|(i, u)| u.area() - self.rects[*i].area())
+                .unwrap();
+            self.remove(best);
+            return self.add(merged);
+        }
+
+        self.rects[self.len] = rect;
+        self.len += 1;
+    }
+
+    pub fn extend(&mut self, other: &DirtyRects) {
+        for r in other.as_slice() {
+            self.add(*r);
+        }
+    }
+
+    fn remove(&mut self, i: usize) {
+        self.rects[i] = self.rects[self.len - 1];
+        self.len -= 1;
+    }
+}
+
+impl Default for DirtyRects {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,160 @@
+//! 8-bit grey to Gray4 conversion with optional dithering and gamma.
+//!
+//! Rounding 256 levels down to the panel's 16 bands smooth gradients;
+//! dithering trades the bands for fine noise that averages out to the source
+//! level. `RowDither` works one row at a time with a fixed amount of state,
+//! so the firmware can convert streamed images without a full 8-bit buffer,
+//! and `crate::assets` uses the same code for the sprite sheets.
+
+use crate::sheet::MAX_ROW_BYTES;
+
+/// Widest row `RowDither` takes, in pixels.
+pub const MAX_WIDTH: usize = 2 * MAX_ROW_BYTES;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "assets",
+    derive(serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Dither {
+    /// Round to the nearest level.
+    #[default]
+    None,
+    /// 4x4 Bayer threshold pattern: stable, so it suits animation.
+    Ordered,
+    /// Floyd–Steinberg error diffusion: finer, but any change in the source
+    /// moves the noise around.
+    FloydSteinberg,
+}
+
+const BAYER4: [[u32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// The `Dither::Ordered` offset at `(x, y)`, 7..=247 out of 255.
+#[inline]
+pub fn bayer_threshold(x: usize, y: usize) -> u32 {
+    (BAYER4[y % 4][x % 4] * 2 + 1) * 255 / 32
+}
+
+/// A 256-entry curve applied to the source before quantizing.
+#[derive(Clone)]
+pub struct Gamma {
+    lut: [u8; 256],
+}
+
+impl Gamma {
+    /// Leaves the source as it is.
+    pub const LINEAR: Gamma = {
+        let mut lut = [0u8; 256];
+        let mut i = 0;
+        while i < 256 {
+            lut[i] = i as u8;
+            i += 1;
+        }
+        Gamma { lut }
+    };
+
+    /// `255 * (v / 255) ^ gamma`: above 1 darkens the midtones, below 1
+    /// lifts them. Black and white stay put.
+    pub fn new(gamma: f32) -> Self {
+        let mut lut = [0u8; 256];
+        for (i, out) in lut.iter_mut().enumerate() {
+            let v = libm::powf(i as f32 / 255.0, gamma) * 255.0;
+            *out = (v + 0.5).clamp(0.0, 255.0) as u8;
+        }
+        lut[0] = 0;
+        lut[255] = 255;
+        Self { lut }
+    }
+
+    #[inline]
+    pub fn apply(&self, v: u8) -> u8 {
+        self.lut[v as usize]
+    }
+}
+
+/// Streams rows of an 8-bit image out as Gray4 levels. Rows must come top to
+/// bottom; start a new `RowDither` for every image.
+pub struct RowDither {
+    mode: Dither,
+    gamma: Gamma,
+    width: usize,
+    y: usize,
+    /// Floyd–Steinberg error pushed onto this row and the next, in fifteenths
+    /// of an 8-bit level.
+    carry: [[i16; MAX_WIDTH]; 2],
+}
+
+impl RowDither {
+    pub fn new(mode: Dither, width: usize) -> Self {
+        assert!(width <= MAX_WIDTH);
+        Self {
+            mode,
+            gamma: Gamma::LINEAR,
+            width,
+            y: 0,
+            carry: [[0; MAX_WIDTH]; 2],
+        }
+    }
+
+    pub fn with_gamma(mut self, gamma: Gamma) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    /// Converts the next row of `width` 8-bit pixels into one level
+    /// (0..=15) per byte of `out`. Exact multiples of 17 always land on
+    /// their own level, so already-quantized sources round-trip unchanged.
+    pub fn next_row(&mut self, src8: &[u8], out: &mut [u8]) {
+        let width = self.width;
+        debug_assert!(src8.len() >= width && out.len() >= width);
+
+        let y = self.y;
+        self.y += 1;
+
+        match self.mode {
+            Dither::None => {
+                for (o, &v) in out[..width].iter_mut().zip(src8) {
+                    *o = ((self.gamma.apply(v) as u32 * 15 + 127) / 255) as u8;
+                }
+            }
+            Dither::Ordered => {
+                for (x, (o, &v)) in out[..width].iter_mut().zip(src8).enumerate() {
+                    let t = bayer_threshold(x, y);
+                    *o = ((self.gamma.apply(v) as u32 * 15 + t) / 255).min(15) as u8;
+                }
+            }
+            Dither::FloydSteinberg => {
+                let [a, b] = &mut self.carry;
+                let (this, below) = if y.is_multiple_of(2) { (a, b) } else { (b, a) };
+                below[..width].fill(0);
+
+                for x in 0..width {
+                    let acc = self.gamma.apply(src8[x]) as i32 * 15 + this[x] as i32;
+                    let q = ((acc + 127) / 255).clamp(0, 15);
+                    out[x] = q as u8;
+
+                    let err = acc - q * 255;
+                    if x + 1 < width {
+                        this[x + 1] += (err * 7 / 16) as i16;
+                        below[x + 1] += (err / 16) as i16;
+                    }
+                    if x > 0 {
+                        below[x - 1] += (err * 3 / 16) as i16;
+                    }
+                    below[x] += (err * 5 / 16) as i16;
+                }
+            }
+        }
+    }
+
+    /// `next_row` packed two pixels per byte, left pixel in the high nibble,
+    /// as in `crate::frame::Gray4Frame` rows and sprite sheets.
+    pub fn next_row_packed(&mut self, src8: &[u8], dst4: &mut [u8]) {
+        let mut levels = [0u8; MAX_WIDTH];
+        self.next_row(src8, &mut levels);
+        for (d, pair) in dst4.iter_mut().zip(levels[..self.width].chunks(2)) {
+            *d = (pair[0] << 4) | pair.get(1).copied().unwrap_or(0);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Rotary encoders in place of faders.
+//!
+//! `Quadrature` turns the levels of an encoder's A and B pins into detents,
+//! and `EncoderValue` turns detents into a 0..=1023 position like a fader's,
+//! moving further per detent the faster the knob is turned.
+
+/// Highest position, the same scale as a normalized fader.
+pub const MAX_VALUE: u16 = 1023;
+
+/// Detents turned for a move from state `prev` to `cur` (`a << 1 | b`), in
+/// quarter steps; +1 is clockwise, with A leading B. Moves that skip a state
+/// say nothing about the direction and count as 0.
+const TRANSITIONS: [i8; 16] = [
+    0, -1, 1, 0, //
+    1, 0, 0, -1, //
+    -1, 0, 0, 1, //
+    0, 1, -1, 0,
+];
+
+/// Both pins high, where a common-ground encoder on pull-ups rests.
+const REST: u8 = 0b11;
+
+/// Quadrature decoder for an encoder with one full cycle per detent.
+#[derive(Clone, Copy, Debug)]
+pub struct Quadrature {
+    state: u8,
+    /// Quarter steps since the last detent.
+    count: i8,
+}
+
+impl Quadrature {
+    pub const fn new() -> Self {
+        Self {
+            state: REST,
+            count: 0,
+        }
+    }
+
+    /// Feeds the pin levels after an edge and returns the detents turned,
+    /// -1, 0 or 1. A detent counts on coming back to rest at least half way
+    /// round, so contact bounce cancels out and a missed edge is forgiven.
+    pub fn update(&mut self, a: bool, b: bool) -> i32 {
+        let state = (a as u8) << 1 | b as u8;
+        self.count += TRANSITIONS[(self.state << 2 | state) as usize];
+        self.state = state;
+
+        if state != REST {
+            return 0;
+        }
+        let detents = match self.count {
+            c if c >= 2 => 1,
+            c if c <= -2 => -1,
+            _ => 0,
+        };
+        self.count = 0;
+        detents
+    }
+}
+
+impl Default for Quadrature {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Speed-up for quick turns: detents less than `window_ms` apart move
+/// `window_ms / gap` steps each, up to `max_factor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Acceleration {
+    pub window_ms: u32,
+    pub max_factor: u16,
+}
+
+impl Acceleration {
+    /// Every detent is one step.
+    pub const NONE: Self = Self {
+        window_ms: 0,
+        max_factor: 1,
+    };
+}
+
+/// Position of an encoder on the fader scale.
+#[derive(Clone, Copy, Debug)]
+pub struct EncoderValue {
+    value: u16,
+    step: u16,
+    acceleration: Acceleration,
+    /// Time and direction of the last detent.
+    last: Option<(u32, i32)>,
+}
+
+impl EncoderValue {
+    /// Starts at `value`, moving `step` per detent when turned slowly.
+    pub const fn new(value: u16, step: u16, acceleration: Acceleration) -> Self {
+        Self {
+            value,
+            step,
+            acceleration,
+            last: None,
+        }
+    }
+
+    pub fn value(&self) -> u16 {
+        self.value
+    }
+
+    /// Moves by `detents` turned at `now_ms` and returns the new position.
+    pub fn turn(&mut self, detents: i32, now_ms: u32) -> u16 {
+        if detents == 0 {
+            return self.value;
+        }
+        let dir = detents.signum();
+        let factor = match self.last {
+            Some((at, last_dir)) if last_dir == dir => {
+                let gap = now_ms.wrapping_sub(at).max(1);
+                (self.acceleration.window_ms / gap).clamp(1, self.acceleration.max_factor as u32)
+            }
+            // Turning back is always fine-grained.
+            _ => 1,
+        };
+        self.last = Some((now_ms, dir));
+
+        let delta = detents * (self.step as u32 * factor) as i32;
+        self.value = (self.value as i32 + delta).clamp(0, MAX_VALUE as i32) as u16;
+        self.value
+    }
+}
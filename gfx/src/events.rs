@@ -0,0 +1,121 @@
+//! What the firmware's tasks tell each other, and the screen state machine
+//! that follows it.
+//!
+//! The firmware carries `Event`s on a publish-subscribe channel (see its
+//! `events.rs`); every task takes the events it cares about and ignores the
+//! rest. The fader positions, which change every ADC loop, go beside it.
+//! `ScreenFlow` is what `prepare_frame_task` does with them: it is the only
+//! owner of the `ScreenState`, so no two tasks race to rewrite it.
+
+use crate::config::CHANNELS;
+use crate::scene::ScreenState;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostState {
+    Active,
+    Suspended,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The fader to show on the active channel screen, `None` once it has
+    /// been left alone for the active channel timeout. `input` is whether
+    /// any fader moved in the last ADC loop.
+    ActiveChannelChanged {
+        channel: Option<usize>,
+        input: bool,
+    },
+    HostStateChanged(HostState),
+    /// Something in the config changed; read it again.
+    ConfigChanged,
+    /// The scene of this state played to its end.
+    SceneFinished(ScreenState),
+    /// A button or gesture muted or soloed this channel, or undid it, so
+    /// deej needs the values again.
+    MuteChanged(usize),
+}
+
+/// The screen state and what the active channel screen shows, as the events
+/// have left them.
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenFlow {
+    state: ScreenState,
+    active: Option<usize>,
+    input: bool,
+    values: [u16; CHANNELS],
+}
+
+impl ScreenFlow {
+    /// The screen stays off until the host turns up.
+    pub const fn new() -> Self {
+        Self {
+            state: ScreenState::OFF,
+            active: None,
+            input: false,
+            values: [0; CHANNELS],
+        }
+    }
+
+    /// Takes the fader positions, 0..=1023, for the active channel screen.
+    pub fn set_faders(&mut self, values: [u16; CHANNELS]) {
+        self.values = values;
+    }
+
+    pub fn handle(&mut self, event: &Event) {
+        match *event {
+            Event::ActiveChannelChanged { channel, input } => {
+                self.active = channel.filter(|&c| c < CHANNELS);
+                self.input = input;
+                match (self.active, self.state) {
+                    (Some(_), ScreenState::OUTRO | ScreenState::OFF) => {}
+                    (Some(_), _) => self.state = ScreenState::ACTIVE,
+                    (None, ScreenState::ACTIVE) => self.state = ScreenState::STANDBY,
+                    (None, _) => {}
+                }
+            }
+            Event::HostStateChanged(HostState::Active) => self.state = ScreenState::INTRO,
+            Event::HostStateChanged(HostState::Suspended) => {
+                if !matches!(self.state, ScreenState::OUTRO | ScreenState::OFF) {
+                    self.state = ScreenState::OUTRO;
+                }
+            }
+            Event::ConfigChanged | Event::SceneFinished(_) | Event::MuteChanged(_) => {}
+        }
+    }
+
+    /// Plays the intro again, e.g. after diagnostics took over the screen.
+    pub fn restart(&mut self) {
+        self.state = ScreenState::INTRO;
+    }
+
+    /// Takes the state `Scenes::draw` returned. If the scene finished, says
+    /// so with the event to publish.
+    pub fn advance(&mut self, next: ScreenState) -> Option<Event> {
+        if next == self.state {
+            return None;
+        }
+        let finished = self.state;
+        self.state = match next {
+            ScreenState::STANDBY if self.active.is_some() => ScreenState::ACTIVE,
+            next => next,
+        };
+        Some(Event::SceneFinished(finished))
+    }
+
+    pub fn state(&self) -> ScreenState {
+        self.state
+    }
+
+    /// The channel on the active channel screen, its value and whether a
+    /// fader is moving.
+    pub fn active(&self) -> Option<(usize, u16, bool)> {
+        let channel = self.active?;
+        Some((channel, self.values[channel], self.input))
+    }
+}
+
+impl Default for ScreenFlow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,155 @@
+use core::convert::Infallible;
+
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+use embedded_graphics::Pixel;
+
+use crate::dirty::{DirtyRects, Rect};
+use crate::gray4::row_bytes;
+
+/// In-memory Gray4 frame in SSD1322 RAM layout: row-major, two pixels per
+/// byte, left pixel in the high nibble. `N` must be `size_bytes(width, height)`.
+///
+/// The frame keeps the set of areas drawn since the last black `clear`, see
+/// `crate::dirty`. Writes through `as_bytes_mut` or `row_mut` must be
+/// reported with `mark_dirty`.
+pub struct Gray4Frame<const N: usize> {
+    width: usize,
+    height: usize,
+    buf: [u8; N],
+    dirty: DirtyRects,
+}
+
+impl<const N: usize> Gray4Frame<N> {
+    pub const fn new(width: usize, height: usize) -> Self {
+        assert!(row_bytes(width) * height == N);
+        Self {
+            width,
+            height,
+            buf: [0; N],
+            dirty: DirtyRects::new(),
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    pub fn row_bytes(&self) -> usize {
+        row_bytes(self.width)
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    #[inline]
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+
+    #[inline]
+    pub fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        let rb = self.row_bytes();
+        &mut self.buf[y * rb..(y + 1) * rb]
+    }
+
+    /// Areas that may differ from a black frame.
+    #[inline]
+    pub fn dirty(&self) -> &DirtyRects {
+        &self.dirty
+    }
+
+    /// Records that the `w` x `h` area at `(x, y)` was drawn; clipped to the
+    /// frame.
+    pub fn mark_dirty(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        let x0 = x.clamp(0, self.width as i32);
+        let y0 = y.clamp(0, self.height as i32);
+        let x1 = x.saturating_add(w).clamp(0, self.width as i32);
+        let y1 = y.saturating_add(h).clamp(0, self.height as i32);
+        self.dirty
+            .add(Rect::new(x0 as u16, y0 as u16, x1 as u16, y1 as u16));
+    }
+
+    /// Marks the whole frame dirty.
+    pub fn mark_all_dirty(&mut self) {
+        self.mark_dirty(0, 0, self.width as i32, self.height as i32);
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        let b = self.buf[y * self.row_bytes() + (x >> 1)];
+        if (x & 1) == 0 {
+            b >> 4
+        } else {
+            b & 0x0F
+        }
+    }
+
+    /// Writes one pixel without touching the dirty set.
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, v: u8) {
+        let i = y * self.row_bytes() + (x >> 1);
+        let r = &mut self.buf[i];
+        if (x & 1) == 0 {
+            *r = (*r & 0x0F) | (v << 4);
+        } else {
+            *r = (*r & 0xF0) | (v & 0x0F);
+        }
+    }
+}
+
+impl<const N: usize> OriginDimensions for Gray4Frame<N> {
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+}
+
+impl<const N: usize> DrawTarget for Gray4Frame<N> {
+    type Color = Gray4;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (mut x0, mut y0, mut x1, mut y1) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+
+        for Pixel(pt, color) in pixels {
+            if pt.x >= 0
+                && pt.y >= 0
+                && (pt.x as usize) < self.width
+                && (pt.y as usize) < self.height
+            {
+                self.set(pt.x as usize, pt.y as usize, color.luma());
+                x0 = x0.min(pt.x);
+                y0 = y0.min(pt.y);
+                x1 = x1.max(pt.x);
+                y1 = y1.max(pt.y);
+            }
+        }
+
+        if x0 <= x1 {
+            self.mark_dirty(x0, y0, x1 - x0 + 1, y1 - y0 + 1);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let v = color.luma();
+        self.buf.fill((v << 4) | v);
+        self.dirty.clear();
+        if v != 0 {
+            self.mark_all_dirty();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! Gestures on a fader sampled at a steady rate.
+//!
+//! `GestureDetector` watches one channel's normalized position (0..=1023)
+//! and reports:
+//!
+//! - a flick: slammed to the bottom and back to about where it was, all
+//!   within `FLICK_MS`;
+//! - a double flick: a second flick within `DOUBLE_FLICK_MS` of the first.
+//!   Like a button's short press, a single flick is only reported once that
+//!   window has closed;
+//! - a hold at max: at the top for `HOLD_MAX_MS`, reported once per hold.
+//!
+//! A slow move to the bottom, or one that stays there, is just a volume
+//! change.
+//!
+//! `GestureBindings` says what each gesture on a fader does.
+
+/// Longest time from leaving rest to coming back for a flick.
+pub const FLICK_MS: u32 = 600;
+/// Longest gap between the ends of the two flicks of a double flick.
+pub const DOUBLE_FLICK_MS: u32 = 800;
+/// Time at the top for a hold at max.
+pub const HOLD_MAX_MS: u32 = 3000;
+
+/// At or below this is the bottom.
+const BOTTOM: u32 = 32;
+/// At or above this is the top.
+const TOP: u32 = 1000;
+/// A flick starts at least this high.
+const FLICK_FROM: u32 = 256;
+/// How close to its start a flick has to come back.
+const RETURN_MARGIN: u32 = 96;
+/// Smaller changes between two samples are noise on a fader at rest.
+const STILL: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    Flick,
+    DoubleFlick,
+    HoldMax,
+}
+
+/// What a gesture can be bound to. `B` is what a button can do, which the
+/// firmware defines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GestureAction<B> {
+    /// Anything a button can do.
+    Button(B),
+    /// Starts diagnostics: the fader check and live readout used for
+    /// calibrating.
+    Calibrate,
+    /// Switches to the next theme, and so to its scenes.
+    NextTheme,
+    /// Logs `gesture ch<N> <gesture>` for host-side tools reading the port,
+    /// to show an overview or run a script.
+    Host,
+}
+
+/// What each gesture on one fader does, if anything.
+#[derive(Clone, Copy, Debug)]
+pub struct GestureBindings<B> {
+    pub flick: Option<GestureAction<B>>,
+    pub double_flick: Option<GestureAction<B>>,
+    pub hold_max: Option<GestureAction<B>>,
+}
+
+impl<B: Copy> GestureBindings<B> {
+    pub fn action(&self, gesture: Gesture) -> Option<GestureAction<B>> {
+        match gesture {
+            Gesture::Flick => self.flick,
+            Gesture::DoubleFlick => self.double_flick,
+            Gesture::HoldMax => self.hold_max,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GestureDetector {
+    /// Last sample, once there is one.
+    last: Option<u32>,
+    /// Where and when the fader was last at rest.
+    rest: (u32, u32),
+    /// A flick has reached the bottom and is on its way back.
+    bottomed: bool,
+    /// End of a flick that may still become a double one.
+    first_flick: Option<u32>,
+    /// Since when the fader has been at the top.
+    top_since: Option<u32>,
+    /// The hold at max of the current stay at the top has been reported.
+    hold_reported: bool,
+}
+
+impl GestureDetector {
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            rest: (0, 0),
+            bottomed: false,
+            first_flick: None,
+            top_since: None,
+            hold_reported: false,
+        }
+    }
+
+    /// Feeds the position sampled at `now_ms` and returns the gesture it
+    /// completes, if any.
+    pub fn update(&mut self, now_ms: u32, value: u32) -> Option<Gesture> {
+        let Some(last) = self.last.replace(value) else {
+            self.rest = (value, now_ms);
+            return None;
+        };
+        let (from, since) = self.rest;
+        let elapsed = now_ms.wrapping_sub(since);
+
+        if self.bottomed {
+            if value.abs_diff(from) <= RETURN_MARGIN {
+                self.bottomed = false;
+                self.rest = (value, now_ms);
+                if self.first_flick.take().is_some() {
+                    return Some(Gesture::DoubleFlick);
+                }
+                self.first_flick = Some(now_ms);
+            } else if elapsed > FLICK_MS {
+                // Left down there, or brought back somewhere else.
+                self.bottomed = false;
+            }
+        } else if value <= BOTTOM && from >= FLICK_FROM && elapsed <= FLICK_MS {
+            self.bottomed = true;
+        } else if value.abs_diff(last) < STILL {
+            self.rest = (value, now_ms);
+        }
+
+        if let Some(end) = self.first_flick {
+            // A second flick under way gets to finish first.
+            if !self.bottomed && now_ms.wrapping_sub(end) >= DOUBLE_FLICK_MS {
+                self.first_flick = None;
+                return Some(Gesture::Flick);
+            }
+        }
+
+        if value < TOP {
+            self.top_since = None;
+        } else if let Some(top_since) = self.top_since {
+            if !self.hold_reported && now_ms.wrapping_sub(top_since) >= HOLD_MAX_MS {
+                self.hold_reported = true;
+                return Some(Gesture::HoldMax);
+            }
+        } else {
+            self.top_since = Some(now_ms);
+            self.hold_reported = false;
+        }
+        None
+    }
+}
+
+impl Default for GestureDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+pub mod compose;
+
 use core::ops::{Deref, DerefMut};
 
 #[inline]
@@ -0,0 +1,207 @@
+//! Layer compositor: stacks Gray4 layers onto a frame in z order.
+//!
+//! Every layer has an offset, an optional clip rectangle, a blend mode and an
+//! opacity, so overlays (the perf overlay, a badge, the whole scene shifted
+//! against burn-in) can go on top of whatever the scenes drew without the
+//! scenes knowing about them. Sources are read one row at a time, so sprite
+//! sheets stay compressed.
+
+use embedded_graphics::prelude::Point;
+
+use crate::dirty::Rect;
+use crate::frame::Gray4Frame;
+use crate::gray4::{row_bytes, MUL4};
+use crate::sheet::SpriteSheet;
+
+/// Most layers one `compose` call takes.
+pub const MAX_LAYERS: usize = 8;
+
+/// Fully opaque, the default `Layer::opacity`.
+pub const OPAQUE: u8 = 15;
+
+/// How a layer's pixel `s` combines with the pixel `d` under it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blend {
+    /// `s`
+    Replace,
+    /// `s + d`, saturating at white.
+    Add,
+    /// The brighter of `s` and `d`.
+    Max,
+    /// `s * d`, with white leaving `d` as it is.
+    Multiply,
+}
+
+impl Blend {
+    #[inline]
+    pub fn apply(self, s: u8, d: u8) -> u8 {
+        match self {
+            Blend::Replace => s,
+            Blend::Add => (s + d).min(15),
+            Blend::Max => s.max(d),
+            Blend::Multiply => MUL4[s as usize][d as usize],
+        }
+    }
+}
+
+/// Pixels of a layer.
+#[derive(Clone, Copy)]
+pub enum Source<'a> {
+    /// Packed Gray4 in frame layout (left pixel in the high nibble), such as
+    /// another frame's `as_bytes()`.
+    Image { data: &'a [u8], w: u32, h: u32 },
+    /// Frame `idx` of a sprite sheet.
+    Sheet { sheet: &'a SpriteSheet, idx: usize },
+    /// A solid block of `level`.
+    Fill { level: u8, w: u32, h: u32 },
+}
+
+impl Source<'_> {
+    pub fn size(&self) -> (u32, u32) {
+        match *self {
+            Source::Image { w, h, .. } | Source::Fill { w, h, .. } => (w, h),
+            Source::Sheet { sheet, .. } => (sheet.width, sheet.height),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Layer<'a> {
+    pub source: Source<'a>,
+    /// Lower layers are composed first; equal ones keep their order.
+    pub z: i8,
+    /// Where the source's top left corner lands on the frame.
+    pub offset: Point,
+    /// Frame area the layer may touch, on top of the frame bounds.
+    pub clip: Option<Rect>,
+    pub blend: Blend,
+    /// 0 (invisible) to `OPAQUE`; the blended pixel is mixed with the one
+    /// under it in this proportion.
+    pub opacity: u8,
+    /// Source nibble 0 is transparent, as with the masked blitters.
+    pub keyed: bool,
+}
+
+impl<'a> Layer<'a> {
+    /// An opaque, keyed `Replace` layer at the origin, on z 0.
+    pub const fn new(source: Source<'a>) -> Self {
+        Self {
+            source,
+            z: 0,
+            offset: Point::zero(),
+            clip: None,
+            blend: Blend::Replace,
+            opacity: OPAQUE,
+            keyed: true,
+        }
+    }
+}
+
+/// Mixes `over` into `under` at `opacity` out of `OPAQUE`.
+#[inline]
+fn mix(over: u8, under: u8, opacity: u8) -> u8 {
+    let a = opacity as u16;
+    ((over as u16 * a + under as u16 * (15 - a) + 7) / 15) as u8
+}
+
+/// Composes `layers` onto `frame` from the lowest z up and adds what they
+/// cover to the frame's dirty set. At most `MAX_LAYERS` are used.
+pub fn compose<const N: usize>(frame: &mut Gray4Frame<N>, layers: &[Layer]) {
+    let count = layers.len().min(MAX_LAYERS);
+    let mut order = [0usize; MAX_LAYERS];
+    for (i, o) in order[..count].iter_mut().enumerate() {
+        *o = i;
+    }
+    order[..count].sort_unstable_by_key(|&i| (layers[i].z, i));
+
+    for &i in &order[..count] {
+        compose_layer(frame, &layers[i]);
+    }
+}
+
+fn compose_layer<const N: usize>(frame: &mut Gray4Frame<N>, layer: &Layer) {
+    let opacity = layer.opacity.min(OPAQUE);
+    if opacity == 0 {
+        return;
+    }
+
+    let (w, h) = layer.source.size();
+    let frame_area = Rect::new(0, 0, frame.width() as u16, frame.height() as u16);
+    let clip = match layer.clip {
+        Some(c) => Rect::new(
+            c.x0.min(frame_area.x1),
+            c.y0.min(frame_area.y1),
+            c.x1.min(frame_area.x1),
+            c.y1.min(frame_area.y1),
+        ),
+        None => frame_area,
+    };
+
+    // Destination span, in frame coordinates.
+    let x0 = layer.offset.x.max(clip.x0 as i32);
+    let x1 = (layer.offset.x + w as i32).min(clip.x1 as i32);
+    let y0 = layer.offset.y.max(clip.y0 as i32);
+    let y1 = (layer.offset.y + h as i32).min(clip.y1 as i32);
+    if x0 >= x1 || y0 >= y1 {
+        return;
+    }
+
+    let mut sheet_rows = match layer.source {
+        Source::Sheet { sheet, idx } => Some(sheet.rows(idx)),
+        _ => None,
+    };
+
+    for sy in 0..(y1 - layer.offset.y) {
+        // Coded sheets can only be read in order, so rows above the span are
+        // still pulled.
+        let src: Option<&[u8]> = match layer.source {
+            Source::Image { data, w, .. } => {
+                let stride = row_bytes(w as usize);
+                Some(&data[sy as usize * stride..(sy as usize + 1) * stride])
+            }
+            Source::Sheet { .. } => sheet_rows.as_mut().map(|rows| rows.next_row()),
+            Source::Fill { .. } => None,
+        };
+
+        let dy = layer.offset.y + sy;
+        if dy < y0 {
+            continue;
+        }
+
+        let dst = frame.row_mut(dy as usize);
+        for dx in x0..x1 {
+            let s = match (src, layer.source) {
+                (Some(src), _) => {
+                    let sx = (dx - layer.offset.x) as usize;
+                    let b = src[sx >> 1];
+                    if (sx & 1) == 0 {
+                        b >> 4
+                    } else {
+                        b & 0x0F
+                    }
+                }
+                (None, Source::Fill { level, .. }) => level.min(15),
+                (None, _) => 0,
+            };
+            if s == 0 && layer.keyed {
+                continue;
+            }
+
+            let d_byte = &mut dst[dx as usize >> 1];
+            let d = if (dx & 1) == 0 {
+                *d_byte >> 4
+            } else {
+                *d_byte & 0x0F
+            };
+
+            let out = mix(layer.blend.apply(s, d), d, opacity);
+            if (dx & 1) == 0 {
+                *d_byte = (*d_byte & 0x0F) | (out << 4);
+            } else {
+                *d_byte = (*d_byte & 0xF0) | out;
+            }
+        }
+    }
+
+    frame.mark_dirty(x0, y0, x1 - x0, y1 - y0);
+}
@@ -0,0 +1,305 @@
+//! A small key-value store for NOR flash, wear-levelled over a few sectors.
+//!
+//! Values are appended to a log in the active sector; the last copy of a key
+//! with a good CRC is its value. When the sector fills up, the live values
+//! move to the next sector round the ring, which is only then erased, so
+//! every sector takes its turn and a save rarely costs an erase at all.
+//!
+//! ```text
+//! sector:  magic u32 | sequence u32 | record | record | ... | 0xFF..
+//! record:  key u8 | len u16 | value | CRC-32 of key, len and value
+//! ```
+//!
+//! The header goes in last when moving to a sector, and the old one is left
+//! alone until its turn comes round again, so losing power at any point
+//! keeps either the old or the new copy of everything. A record cut short
+//! fails its CRC and the previous copy stands. All integers are little
+//! endian, and the flash has to take writes of single bytes, as the
+//! RP2040's does.
+
+use crc::{Crc, CRC_32_ISO_HDLC};
+use embedded_storage::nor_flash::NorFlash;
+
+/// Keys are `0..MAX_KEYS`.
+pub const MAX_KEYS: usize = 16;
+/// Longest value.
+pub const MAX_VALUE: usize = 1024;
+
+const MAGIC: u32 = 0xDEE5_70E1;
+const HEADER: u32 = 8;
+/// Key and length before a value, CRC after it.
+const RECORD_HEAD: u32 = 3;
+const RECORD_TAIL: u32 = 4;
+/// What erased flash reads as, where the log ends.
+const ERASED: u8 = 0xFF;
+/// Values are read and copied in pieces of this size.
+const CHUNK: usize = 32;
+
+pub(crate) const CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    Flash(E),
+    /// A key outside `0..MAX_KEYS`, or a value longer than `MAX_VALUE`.
+    Invalid,
+    /// The live values do not fit in a sector.
+    Full,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(e: E) -> Self {
+        Error::Flash(e)
+    }
+}
+
+/// Offset and length of the live record of each key in a sector.
+type Index = [Option<(u32, u16)>; MAX_KEYS];
+
+/// The store's position in the flash; the flash itself is passed to each
+/// call, so its owner can use it for other things in between.
+#[derive(Clone, Copy, Debug)]
+pub struct Store {
+    /// Offset of the first sector.
+    base: u32,
+    sectors: u32,
+    sector_size: u32,
+    /// Index of the sector in use and its sequence number.
+    active: u32,
+    sequence: u32,
+    /// Offset in the active sector where the next record goes.
+    end: u32,
+}
+
+impl Store {
+    /// Opens the store in the `sectors` erase blocks from `base`, formatting
+    /// them if none holds a store yet.
+    pub fn open<F: NorFlash>(flash: &mut F, base: u32, sectors: u32) -> Result<Self, F::Error> {
+        const { assert!(F::WRITE_SIZE == 1 && F::READ_SIZE == 1) };
+        assert!(sectors >= 2, "moving to the next sector needs two");
+
+        let mut store = Self {
+            base,
+            sectors,
+            sector_size: F::ERASE_SIZE as u32,
+            active: 0,
+            sequence: 0,
+            end: HEADER,
+        };
+
+        let mut newest = None;
+        for sector in 0..sectors {
+            let mut header = [0u8; HEADER as usize];
+            flash.read(store.offset(sector, 0), &mut header)?;
+            if u32_at(&header, 0) != MAGIC {
+                continue;
+            }
+            let sequence = u32_at(&header, 4);
+            let newer = match newest {
+                None => true,
+                // Wrapping, for the day the counter goes round.
+                Some((_, best)) => sequence.wrapping_sub(best) as i32 > 0,
+            };
+            if newer {
+                newest = Some((sector, sequence));
+            }
+        }
+
+        match newest {
+            Some((sector, sequence)) => {
+                store.active = sector;
+                store.sequence = sequence;
+                store.end = store.scan(flash, sector)?.1;
+            }
+            None => {
+                flash.erase(store.offset(0, 0), store.offset(0, store.sector_size))?;
+                store.write_header(flash, 0, 0)?;
+            }
+        }
+        Ok(store)
+    }
+
+    /// Reads the value of `key` into `buf` and returns its length, or
+    /// `None` if there is no good copy of it or it does not fit.
+    pub fn read<F: NorFlash>(
+        &self,
+        flash: &mut F,
+        key: u8,
+        buf: &mut [u8],
+    ) -> Result<Option<usize>, F::Error> {
+        let Some(&Some((at, len))) = self.scan(flash, self.active)?.0.get(key as usize) else {
+            return Ok(None);
+        };
+        let len = len as usize;
+        if len > buf.len() {
+            return Ok(None);
+        }
+        flash.read(self.offset(self.active, at + RECORD_HEAD), &mut buf[..len])?;
+        Ok(Some(len))
+    }
+
+    /// Stores `value` under `key`. Writing the value already stored does
+    /// nothing.
+    pub fn write<F: NorFlash>(
+        &mut self,
+        flash: &mut F,
+        key: u8,
+        value: &[u8],
+    ) -> Result<(), Error<F::Error>> {
+        if key as usize >= MAX_KEYS || value.len() > MAX_VALUE {
+            return Err(Error::Invalid);
+        }
+
+        let (index, _) = self.scan(flash, self.active)?;
+        if let Some((at, len)) = index[key as usize] {
+            if len as usize == value.len() && self.holds(flash, at + RECORD_HEAD, value)? {
+                return Ok(());
+            }
+        }
+
+        let size = record_size(value.len() as u16);
+        if self.end + size > self.sector_size {
+            // The old copy of `key` moves too, so it survives losing power
+            // before the new one is written.
+            let live: u32 = (index.iter().flatten())
+                .map(|&(_, len)| record_size(len))
+                .sum();
+            if HEADER + live + size > self.sector_size {
+                return Err(Error::Full);
+            }
+            self.move_on(flash, &index)?;
+        }
+
+        let head = [key, value.len() as u8, (value.len() >> 8) as u8];
+        let mut digest = CRC.digest();
+        digest.update(&head);
+        digest.update(value);
+        let crc = digest.finalize().to_le_bytes();
+
+        let at = self.offset(self.active, self.end);
+        flash.write(at, &head)?;
+        flash.write(at + RECORD_HEAD, value)?;
+        flash.write(at + RECORD_HEAD + value.len() as u32, &crc)?;
+        self.end += size;
+        Ok(())
+    }
+
+    /// Copies the live records of `index` into the next sector and makes it
+    /// the active one.
+    fn move_on<F: NorFlash>(&mut self, flash: &mut F, index: &Index) -> Result<(), F::Error> {
+        let next = (self.active + 1) % self.sectors;
+        flash.erase(self.offset(next, 0), self.offset(next, self.sector_size))?;
+
+        let mut end = HEADER;
+        for &(at, len) in index.iter().flatten() {
+            let size = record_size(len);
+            let mut chunk = [0u8; CHUNK];
+            let mut done = 0;
+            while done < size {
+                let n = (size - done).min(CHUNK as u32);
+                let chunk = &mut chunk[..n as usize];
+                flash.read(self.offset(self.active, at + done), chunk)?;
+                flash.write(self.offset(next, end + done), chunk)?;
+                done += n;
+            }
+            end += size;
+        }
+
+        let sequence = self.sequence.wrapping_add(1);
+        self.write_header(flash, next, sequence)?;
+        self.active = next;
+        self.sequence = sequence;
+        self.end = end;
+        Ok(())
+    }
+
+    fn write_header<F: NorFlash>(
+        &self,
+        flash: &mut F,
+        sector: u32,
+        sequence: u32,
+    ) -> Result<(), F::Error> {
+        let mut header = [0u8; HEADER as usize];
+        header[..4].copy_from_slice(&MAGIC.to_le_bytes());
+        header[4..].copy_from_slice(&sequence.to_le_bytes());
+        flash.write(self.offset(sector, 0), &header)
+    }
+
+    /// Walks the log of `sector`. Returns the live record of each key and
+    /// where the next record goes; a log that runs off the sector leaves no
+    /// room, so the next write moves on.
+    fn scan<F: NorFlash>(&self, flash: &mut F, sector: u32) -> Result<(Index, u32), F::Error> {
+        let mut index: Index = [None; MAX_KEYS];
+        let mut at = HEADER;
+        while at + RECORD_HEAD + RECORD_TAIL <= self.sector_size {
+            let mut head = [0u8; RECORD_HEAD as usize];
+            flash.read(self.offset(sector, at), &mut head)?;
+            let key = head[0];
+            if key == ERASED {
+                return Ok((index, at));
+            }
+            let len = u16::from_le_bytes([head[1], head[2]]);
+            let size = record_size(len);
+            if len as usize > MAX_VALUE || at + size > self.sector_size {
+                break;
+            }
+            if (key as usize) < MAX_KEYS && self.crc_ok(flash, sector, at, &head, len)? {
+                index[key as usize] = Some((at, len));
+            }
+            at += size;
+        }
+        Ok((index, self.sector_size))
+    }
+
+    fn crc_ok<F: NorFlash>(
+        &self,
+        flash: &mut F,
+        sector: u32,
+        at: u32,
+        head: &[u8],
+        len: u16,
+    ) -> Result<bool, F::Error> {
+        let mut digest = CRC.digest();
+        digest.update(head);
+        let mut chunk = [0u8; CHUNK];
+        let mut done = 0;
+        while done < len as u32 {
+            let n = (len as u32 - done).min(CHUNK as u32);
+            let chunk = &mut chunk[..n as usize];
+            flash.read(self.offset(sector, at + RECORD_HEAD + done), chunk)?;
+            digest.update(chunk);
+            done += n;
+        }
+        let mut stored = [0u8; RECORD_TAIL as usize];
+        flash.read(
+            self.offset(sector, at + RECORD_HEAD + len as u32),
+            &mut stored,
+        )?;
+        Ok(digest.finalize() == u32::from_le_bytes(stored))
+    }
+
+    /// Whether the flash at `at` in the active sector reads `value`.
+    fn holds<F: NorFlash>(&self, flash: &mut F, at: u32, value: &[u8]) -> Result<bool, F::Error> {
+        let mut chunk = [0u8; CHUNK];
+        for (i, part) in value.chunks(CHUNK).enumerate() {
+            let chunk = &mut chunk[..part.len()];
+            let offset = at + (i * CHUNK) as u32;
+            flash.read(self.offset(self.active, offset), chunk)?;
+            if chunk != part {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn offset(&self, sector: u32, at: u32) -> u32 {
+        self.base + sector * self.sector_size + at
+    }
+}
+
+fn record_size(len: u16) -> u32 {
+    RECORD_HEAD + len as u32 + RECORD_TAIL
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
@@ -0,0 +1,31 @@
+//! Gray4 rendering primitives, and the hardware-free logic behind them,
+//! shared by the firmware, `build.rs` and the host-side tests and tools.
+#![cfg_attr(not(feature = "assets"), no_std)]
+
+pub mod anim;
+#[cfg(feature = "assets")]
+pub mod assets;
+pub mod blit;
+pub mod button;
+pub mod config;
+pub mod crash;
+pub mod diagnostics;
+pub mod dirty;
+pub mod dither;
+pub mod encoder;
+pub mod events;
+pub mod frame;
+pub mod gesture;
+pub mod gray4;
+pub mod gray4_effects;
+pub mod kv;
+pub mod mono;
+pub mod packbits;
+pub mod particles;
+pub mod perf;
+pub mod scene;
+pub mod sheet;
+pub mod sprite;
+mod text;
+pub mod theme;
+pub mod volume_indicator;
@@ -0,0 +1,47 @@
+//! 1bpp output for monochrome panels such as the SSD1306.
+//!
+//! The scenes always draw Gray4; on a mono panel each frame is thresholded
+//! against the same 4x4 Bayer pattern as `crate::dither::Dither::Ordered`, so
+//! greys come out as a fixed pattern that keeps their brightness on average
+//! and does not crawl while things move. Error diffusion would be finer but
+//! reshuffles the noise every frame.
+//!
+//! The output is in SSD1306 page layout: one byte per column for every band
+//! of 8 rows, top pixel in bit 0.
+
+use crate::dirty::Rect;
+use crate::dither::bayer_threshold;
+use crate::frame::Gray4Frame;
+
+/// Bytes of a `width` x `height` page buffer.
+pub const fn page_bytes(width: usize, height: usize) -> usize {
+    width * height.div_ceil(8)
+}
+
+/// Whether Gray4 `level` lights the pixel at `(x, y)`. Black stays off and
+/// white on everywhere.
+#[inline]
+pub fn lit(level: u8, x: usize, y: usize) -> bool {
+    level as u32 * 17 + bayer_threshold(x, y) >= 255
+}
+
+/// Converts the pages `rect` touches (its rows rounded out to whole pages)
+/// from `frame` into `pages`, a `page_bytes` buffer of the frame's size.
+pub fn pack_pages<const N: usize>(frame: &Gray4Frame<N>, rect: &Rect, pages: &mut [u8]) {
+    let width = frame.width();
+    let x1 = (rect.x1 as usize).min(width);
+    let y1 = (rect.y1 as usize).min(frame.height());
+
+    for page in rect.y0 as usize / 8..y1.div_ceil(8) {
+        let rows = page * 8..(page * 8 + 8).min(frame.height());
+        for x in rect.x0 as usize..x1 {
+            let mut byte = 0u8;
+            for (bit, y) in rows.clone().enumerate() {
+                if lit(frame.get(x, y), x, y) {
+                    byte |= 1 << bit;
+                }
+            }
+            pages[page * width + x] = byte;
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! PackBits run-length coding for packed Gray4 frames.
+//!
+//! Every header byte `h` is followed by its payload:
+//! - `0..=127`: `h + 1` literal bytes,
+//! - `129..=255`: one byte repeated `257 - h` times,
+//! - `128`: no-op.
+//!
+//! Frames are coded independently, so a frame can be streamed out row by row
+//! without touching the rest of the sheet. Transparent areas are long runs of
+//! `0x00` and shrink to two bytes per 128 bytes of source.
+
+/// Streaming decoder over one coded frame.
+pub struct Decoder<'a> {
+    src: &'a [u8],
+    pos: usize,
+    remaining: usize,
+    literal: bool,
+    value: u8,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(src: &'a [u8]) -> Self {
+        Self {
+            src,
+            pos: 0,
+            remaining: 0,
+            literal: false,
+            value: 0,
+        }
+    }
+
+    /// Fills `out` with the next decoded bytes. Truncated input is padded
+    /// with zeros (transparent) rather than panicking mid-frame.
+    pub fn read(&mut self, out: &mut [u8]) {
+        let mut o = 0usize;
+
+        while o < out.len() {
+            if self.remaining == 0 && !self.next_header() {
+                out[o..].fill(0);
+                return;
+            }
+
+            let n = core::cmp::min(self.remaining, out.len() - o);
+            if self.literal {
+                let end = core::cmp::min(self.pos + n, self.src.len());
+                let got = end - self.pos;
+                out[o..o + got].copy_from_slice(&self.src[self.pos..end]);
+                out[o + got..o + n].fill(0);
+                self.pos = end;
+            } else {
+                out[o..o + n].fill(self.value);
+            }
+
+            o += n;
+            self.remaining -= n;
+        }
+    }
+
+    fn next_header(&mut self) -> bool {
+        loop {
+            let Some(&h) = self.src.get(self.pos) else {
+                return false;
+            };
+            self.pos += 1;
+
+            match h {
+                0..=127 => {
+                    self.literal = true;
+                    self.remaining = h as usize + 1;
+                }
+                128 => continue,
+                _ => {
+                    let Some(&v) = self.src.get(self.pos) else {
+                        return false;
+                    };
+                    self.pos += 1;
+                    self.literal = false;
+                    self.value = v;
+                    self.remaining = 257 - h as usize;
+                }
+            }
+            return true;
+        }
+    }
+}
+
+/// Length of the run of equal bytes starting at `i`, capped at 128.
+#[cfg(feature = "assets")]
+fn run_len(src: &[u8], i: usize) -> usize {
+    let mut n = 1;
+    while i + n < src.len() && n < 128 && src[i + n] == src[i] {
+        n += 1;
+    }
+    n
+}
+
+/// Appends the PackBits coding of `src` to `out`.
+#[cfg(feature = "assets")]
+pub fn encode(src: &[u8], out: &mut Vec<u8>) {
+    let mut i = 0usize;
+
+    while i < src.len() {
+        let run = run_len(src, i);
+        if run >= 2 {
+            out.push((257 - run) as u8);
+            out.push(src[i]);
+            i += run;
+            continue;
+        }
+
+        // Collect literals until a run of three or more starts; shorter
+        // runs are cheaper to keep inline.
+        let start = i;
+        i += 1;
+        while i < src.len() && i - start < 128 && run_len(src, i) < 3 {
+            i += 1;
+        }
+        out.push((i - start - 1) as u8);
+        out.extend_from_slice(&src[start..i]);
+    }
+}
+
+/// Codes a raw sheet frame by frame. The result starts with `frames + 1`
+/// little-endian `u32` offsets into the blob, one per frame plus the end.
+#[cfg(feature = "assets")]
+pub fn encode_sheet(raw: &[u8], frame_bytes: usize) -> Vec<u8> {
+    let frames = raw.len() / frame_bytes;
+    let table = (frames + 1) * 4;
+
+    let mut body = Vec::new();
+    let mut offsets = Vec::with_capacity(frames + 1);
+    for frame in raw.chunks_exact(frame_bytes) {
+        offsets.push((table + body.len()) as u32);
+        encode(frame, &mut body);
+    }
+    offsets.push((table + body.len()) as u32);
+
+    let mut out = Vec::with_capacity(table + body.len());
+    for off in offsets {
+        out.extend_from_slice(&off.to_le_bytes());
+    }
+    out.extend_from_slice(&body);
+    out
+}
+
+/// Returns the coded stream of frame `idx` in a blob built by `encode_sheet`.
+pub fn sheet_frame(blob: &[u8], idx: usize) -> &[u8] {
+    let offset = |i: usize| {
+        let b = &blob[i * 4..i * 4 + 4];
+        u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize
+    };
+    &blob[offset(idx)..offset(idx + 1)]
+}
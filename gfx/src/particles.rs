@@ -0,0 +1,330 @@
+//! Sprite particles: a fixed pool of animated sprites that are spawned,
+//! moved, aged and respawned by an `Emitter` following a `ParticleConfig`.
+//!
+//! The background's cobweb rain and the intro halo are the two presets at the
+//! bottom; other effects (snow, embers, sparkles) are just other configs.
+//! Randomness comes from a small LCG with a fixed seed, so a given sequence
+//! of frame times always renders the same, which the golden tests rely on.
+
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+use crate::anim::Stepper;
+use crate::blit::{blit_sheet_frame_flash, blit_sheet_frame_masked};
+use crate::frame::Gray4Frame;
+use crate::sheet::SpriteSheet;
+
+/// Most particles one emitter keeps alive.
+pub const MAX_PARTICLES: usize = 16;
+
+const SEED: u32 = 0x1234_5678;
+
+/// Evenly spaced values from `min` to `max`, `step` apart, one picked at
+/// random per particle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spread {
+    pub min: i32,
+    pub max: i32,
+    pub step: i32,
+}
+
+impl Spread {
+    pub const fn fixed(value: i32) -> Self {
+        Self::stepped(value, value, 1)
+    }
+
+    pub const fn range(min: i32, max: i32) -> Self {
+        Self::stepped(min, max, 1)
+    }
+
+    pub const fn stepped(min: i32, max: i32, step: i32) -> Self {
+        Self { min, max, step }
+    }
+
+    fn pick(self, r: u32) -> i32 {
+        let choices = ((self.max - self.min) / self.step.max(1)).max(0) as u32 + 1;
+        self.min + (r % choices) as i32 * self.step
+    }
+}
+
+/// Where new particles appear: the sprite's top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spawn {
+    /// Centred in one of this many equal columns across the screen, up to a
+    /// screen (plus a sprite) above its top edge, so they trickle in.
+    Lanes(u32),
+    /// Anywhere in the rectangle.
+    Area(Rectangle),
+}
+
+#[derive(Clone, Copy)]
+pub struct ParticleConfig {
+    pub sheet: &'static SpriteSheet,
+    /// Particles kept alive, up to `MAX_PARTICLES`.
+    pub count: usize,
+    pub spawn: Spawn,
+    /// Velocity in pixels per step.
+    pub vx: Spread,
+    pub vy: Spread,
+    /// Added to the velocity every step.
+    pub gravity: Point,
+    /// Steps a particle lives; `None` keeps it until it leaves the screen.
+    pub lifetime: Option<Spread>,
+    /// Steps a new particle spends flashing in before its sprite animates.
+    pub fade_in: u8,
+    /// Step length of spawned particles, and of those placed by
+    /// `Emitter::burst`.
+    pub step_ms: u32,
+    pub burst_step_ms: u32,
+    /// Particles that leave the screen or run out of life are replaced.
+    pub respawn: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Particle {
+    pos: Point,
+    vel: Point,
+    frame: usize,
+    /// Steps since it appeared.
+    age: u32,
+    life: Option<u32>,
+    burst: bool,
+}
+
+pub struct Emitter {
+    config: ParticleConfig,
+    particles: [Option<Particle>; MAX_PARTICLES],
+    screen_width: i32,
+    screen_height: i32,
+    rng_state: u32,
+    clock: Stepper,
+    burst_clock: Stepper,
+}
+
+impl Emitter {
+    /// An emitter with no particles yet; see `fill` and `burst`.
+    pub fn new(config: ParticleConfig, screen_width: i32, screen_height: i32) -> Self {
+        Self {
+            config,
+            particles: [None; MAX_PARTICLES],
+            screen_width,
+            screen_height,
+            rng_state: SEED,
+            clock: Stepper::every(config.step_ms),
+            burst_clock: Stepper::every(config.burst_step_ms),
+        }
+    }
+
+    pub fn config(&self) -> &ParticleConfig {
+        &self.config
+    }
+
+    /// Live particles.
+    pub fn len(&self) -> usize {
+        self.particles.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Spawns particles until `count` are alive.
+    pub fn fill(&mut self) {
+        let mut missing = self.config.count.saturating_sub(self.len());
+        for i in 0..self.config.count.min(MAX_PARTICLES) {
+            if missing == 0 {
+                break;
+            }
+            if self.particles[i].is_none() {
+                self.spawn(i);
+                missing -= 1;
+            }
+        }
+    }
+
+    /// Switches to `config` and replaces all particles with still ones at
+    /// `points`, animating in sheet order from the first frame. No randomness
+    /// is used, so a burst looks the same every time.
+    pub fn burst(&mut self, config: ParticleConfig, points: &[Point]) {
+        self.config = config;
+        self.clock = Stepper::every(config.step_ms);
+        self.burst_clock = Stepper::every(config.burst_step_ms);
+        self.particles = [None; MAX_PARTICLES];
+        for (i, (slot, &pos)) in self.particles.iter_mut().zip(points).enumerate() {
+            *slot = Some(Particle {
+                pos,
+                vel: Point::zero(),
+                frame: i % self.config.sheet.frames,
+                age: 0,
+                life: None,
+                burst: true,
+            });
+        }
+    }
+
+    /// Switches to `config`, keeping the live particles where they are but
+    /// giving them velocities and lifetimes from it.
+    pub fn hand_over(&mut self, config: ParticleConfig) {
+        self.config = config;
+        self.clock = Stepper::every(config.step_ms);
+        self.burst_clock = Stepper::every(config.burst_step_ms);
+
+        for i in 0..MAX_PARTICLES {
+            if let Some(mut p) = self.particles[i] {
+                p.vel = self.roll_velocity();
+                p.life = self.roll_lifetime();
+                self.particles[i] = Some(p);
+            }
+        }
+    }
+
+    pub fn draw<const N: usize>(&self, frame: &mut Gray4Frame<N>) {
+        let sheet = self.config.sheet;
+        let fade_in = self.config.fade_in;
+        for p in self.particles.iter().flatten() {
+            if p.age < fade_in as u32 {
+                blit_sheet_frame_flash(frame, sheet, p.frame, p.pos, p.age as u8, fade_in);
+            } else {
+                blit_sheet_frame_masked(frame, sheet, p.frame, p.pos);
+            }
+        }
+    }
+
+    /// Moves every particle on by `dt_ms` and replaces or drops those that
+    /// are done.
+    pub fn advance(&mut self, dt_ms: u32) {
+        let steps = self.clock.advance(dt_ms);
+        let burst_steps = self.burst_clock.advance(dt_ms);
+
+        for i in 0..MAX_PARTICLES {
+            let Some(mut p) = self.particles[i] else {
+                continue;
+            };
+            let steps = if p.burst { burst_steps } else { steps };
+            if steps == 0 {
+                continue;
+            }
+
+            for _ in 0..steps {
+                if p.age >= self.config.fade_in as u32 {
+                    p.frame = (p.frame + 1) % self.config.sheet.frames;
+                }
+                p.pos += p.vel;
+                p.vel += self.config.gravity;
+                p.age = p.age.saturating_add(1);
+            }
+
+            self.particles[i] = Some(p);
+
+            let expired = p.life.is_some_and(|life| p.age >= life);
+            if expired || self.off_screen(&p) {
+                if self.config.respawn {
+                    self.spawn(i);
+                } else {
+                    self.particles[i] = None;
+                }
+            }
+        }
+    }
+
+    /// Past the bottom or either side, or moving up past the top. Particles
+    /// above the screen moving down are still on their way in.
+    fn off_screen(&self, p: &Particle) -> bool {
+        let w = self.config.sheet.width as i32;
+        let h = self.config.sheet.height as i32;
+        p.pos.y > self.screen_height + h
+            || p.pos.x > self.screen_width + w
+            || p.pos.x < -w
+            || (p.vel.y < 0 && p.pos.y < -h)
+    }
+
+    fn spawn(&mut self, index: usize) {
+        let sheet = self.config.sheet;
+
+        let r_x = self.next_rand();
+        let r_y = self.next_rand();
+        let pos = match self.config.spawn {
+            Spawn::Lanes(lanes) => {
+                let lanes = lanes.max(1);
+                let lane_width = self.screen_width / lanes as i32;
+                let lane_x = (r_x % lanes) as i32 * lane_width;
+                let x = lane_x + lane_width / 2 - (sheet.width as i32 / 2);
+
+                let max_offset = self.screen_height + sheet.height as i32;
+                Point::new(x, -((r_y % max_offset as u32) as i32))
+            }
+            Spawn::Area(area) => Point::new(
+                area.top_left.x + (r_x % area.size.width.max(1)) as i32,
+                area.top_left.y + (r_y % area.size.height.max(1)) as i32,
+            ),
+        };
+
+        let vel = self.roll_velocity();
+        let frame = self.next_rand() as usize % sheet.frames;
+        let life = self.roll_lifetime();
+
+        self.particles[index] = Some(Particle {
+            pos,
+            vel,
+            frame,
+            age: 0,
+            life,
+            burst: false,
+        });
+    }
+
+    fn roll_velocity(&mut self) -> Point {
+        let vy = self.config.vy.pick(self.next_rand());
+        let vx = self.config.vx.pick(self.next_rand());
+        Point::new(vx, vy)
+    }
+
+    fn roll_lifetime(&mut self) -> Option<u32> {
+        let lifetime = self.config.lifetime?;
+        Some(lifetime.pick(self.next_rand()).max(1) as u32)
+    }
+
+    fn next_rand(&mut self) -> u32 {
+        self.rng_state = self
+            .rng_state
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        self.rng_state
+    }
+}
+
+/// Cobwebs falling in four lanes behind the standby screen. Those left over
+/// from the halo fall at the sheet's frame rate, new ones at a quarter of it.
+pub fn cobweb_rain(sheet: &'static SpriteSheet) -> ParticleConfig {
+    ParticleConfig {
+        sheet,
+        count: 4,
+        spawn: Spawn::Lanes(4),
+        vx: Spread::stepped(-2, 2, 2),
+        vy: Spread::range(2, 5),
+        gravity: Point::zero(),
+        lifetime: None,
+        fade_in: 0,
+        step_ms: 4 * sheet.frame_ms,
+        burst_step_ms: sheet.frame_ms,
+        respawn: true,
+    }
+}
+
+/// Still particles that flash in around the character during the intro and
+/// then turn in place; placed with `Emitter::burst`.
+pub fn halo(sheet: &'static SpriteSheet) -> ParticleConfig {
+    ParticleConfig {
+        sheet,
+        count: 0,
+        spawn: Spawn::Lanes(1),
+        vx: Spread::fixed(0),
+        vy: Spread::fixed(0),
+        gravity: Point::zero(),
+        lifetime: None,
+        fade_in: 3,
+        step_ms: sheet.frame_ms,
+        burst_step_ms: sheet.frame_ms,
+        respawn: false,
+    }
+}
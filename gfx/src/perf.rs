@@ -0,0 +1,130 @@
+//! Timing statistics and the on-screen performance overlay.
+
+use core::fmt;
+
+use embedded_graphics::prelude::*;
+
+use crate::frame::Gray4Frame;
+use crate::gray4::compose::{compose, Layer, Source};
+use crate::text::{Lines, FONT_HEIGHT, FONT_WIDTH};
+
+/// Characters on an overlay line, e.g. `f 12.3ms`.
+const OVERLAY_COLUMNS: i32 = 8;
+const OVERLAY_LINES: i32 = 3;
+const OVERLAY_WIDTH: usize = (OVERLAY_COLUMNS * FONT_WIDTH) as usize;
+const OVERLAY_HEIGHT: usize = (OVERLAY_LINES * FONT_HEIGHT) as usize;
+
+/// The overlay's text, before it goes on the frame.
+type TextFrame = Gray4Frame<{ OVERLAY_WIDTH * OVERLAY_HEIGHT / 2 }>;
+
+/// Count, min, average and max of a duration, in microseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timing {
+    count: u32,
+    min_us: u32,
+    max_us: u32,
+    sum_us: u64,
+}
+
+impl Timing {
+    pub const fn new() -> Self {
+        Self {
+            count: 0,
+            min_us: u32::MAX,
+            max_us: 0,
+            sum_us: 0,
+        }
+    }
+
+    pub fn add(&mut self, us: u32) {
+        self.count += 1;
+        self.min_us = self.min_us.min(us);
+        self.max_us = self.max_us.max(us);
+        self.sum_us += us as u64;
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn min_us(&self) -> Option<u32> {
+        (self.count > 0).then_some(self.min_us)
+    }
+
+    pub fn max_us(&self) -> Option<u32> {
+        (self.count > 0).then_some(self.max_us)
+    }
+
+    pub fn avg_us(&self) -> Option<u32> {
+        (self.count > 0).then(|| (self.sum_us / self.count as u64) as u32)
+    }
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for Timing {
+    /// `n 10 min 1.0 avg 1.5 max 2.0 ms`, or just `n 0`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "n {}", self.count)?;
+        if let (Some(min), Some(avg), Some(max)) = (self.min_us(), self.avg_us(), self.max_us()) {
+            write!(f, " min {} avg {} max {} ms", Ms(min), Ms(avg), Ms(max))?;
+        }
+        Ok(())
+    }
+}
+
+/// Microseconds shown as milliseconds with one decimal.
+struct Ms(u32);
+
+impl fmt::Display for Ms {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.0 / 1000, self.0 % 1000 / 100)
+    }
+}
+
+/// What the overlay shows, usually averages over the last second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Overlay {
+    pub fps: u32,
+    pub render_us: Option<u32>,
+    pub flush_us: Option<u32>,
+}
+
+impl Overlay {
+    /// Draws frame rate, render and flush time on a black box in the top
+    /// right corner, composed over the scene as two layers: the box and the
+    /// text.
+    pub fn draw<const N: usize>(&self, frame: &mut Gray4Frame<N>) {
+        let mut text = TextFrame::new(OVERLAY_WIDTH, OVERLAY_HEIGHT);
+        let mut lines = Lines::new(&mut text, Point::zero());
+        lines.line(format_args!("{:2} fps", self.fps));
+        for (label, us) in [("r", self.render_us), ("f", self.flush_us)] {
+            match us {
+                Some(us) => lines.line(format_args!("{} {}ms", label, Ms(us))),
+                None => lines.line(format_args!("{} -", label)),
+            }
+        }
+
+        let (w, h) = (OVERLAY_WIDTH as u32, OVERLAY_HEIGHT as u32);
+        let offset = Point::new(frame.width() as i32 - w as i32, 0);
+        let backdrop = Layer {
+            offset,
+            keyed: false,
+            ..Layer::new(Source::Fill { level: 0, w, h })
+        };
+        let text = Layer {
+            offset,
+            z: 1,
+            ..Layer::new(Source::Image {
+                data: text.as_bytes(),
+                w,
+                h,
+            })
+        };
+        compose(frame, &[backdrop, text]);
+    }
+}
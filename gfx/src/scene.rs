@@ -0,0 +1,354 @@
+//! The screens and their animation state, drawn once per frame.
+//!
+//! Nothing in here touches the firmware's globals: the caller passes the
+//! current `ScreenState`, input and the time since the previous frame, and
+//! gets the next state back. That keeps the scenes renderable on the host,
+//! see `gfx/tests/golden.rs`.
+//!
+//! Every screen draws its current state and then moves it on by that time, so
+//! motion and sprite frames run at the same speed whatever the frame rate.
+
+use embedded_graphics::prelude::*;
+
+use crate::anim::{Animation, Stepper};
+use crate::blit::{
+    blit_sheet_frame_fade_dither, blit_sheet_frame_masked, blit_sheet_frame_masked_crt,
+};
+use crate::frame::Gray4Frame;
+use crate::particles::{self, Emitter};
+use crate::sheet::SpriteSheet;
+use crate::theme::{Theme, HALO_PARTICLES};
+use crate::volume_indicator::VolumeIndicator;
+
+/// Frames further apart than this are treated as this far apart, so a stall
+/// does not make everything jump.
+pub const MAX_FRAME_MS: u32 = 250;
+
+/// The pace of the step-based motion: the character's rise and walk, the
+/// outro fade and the CRT glitch pattern.
+const STEP_MS: u32 = 140;
+
+/// Length of the intro, and when the particle halo goes off during it.
+const INTRO_MS: u32 = 26 * STEP_MS;
+const FIREWORK_MS: u32 = 8 * STEP_MS;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScreenState {
+    INTRO = 0,
+    STANDBY = 1,
+    ACTIVE = 2,
+    OUTRO = 3,
+    OFF = 4,
+}
+
+/// The fader shown on the active channel screen.
+#[derive(Clone, Copy)]
+pub struct ActiveChannel {
+    pub channel: usize,
+    /// Normalized fader position, 0..=1023.
+    pub value: u16,
+    pub icon: &'static SpriteSheet,
+    /// A fader is moving right now.
+    pub input: bool,
+    /// The channel is muted, or another one is soloed.
+    pub muted: bool,
+}
+
+/// All screens of the firmware, driven by `ScreenState`.
+pub struct Scenes {
+    halo_origin: Point,
+    halo_offsets: [Point; HALO_PARTICLES],
+    background: Background,
+    intro: IntroScreen,
+    standby: StandbyScreen,
+    active_channel: ActiveChannelScreen,
+    outro: OutroScreen,
+    indicator: VolumeIndicator,
+}
+
+impl Scenes {
+    pub fn new(theme: &Theme, screen_width: i32, screen_height: i32) -> Self {
+        let layout = &theme.layout_for(screen_width, screen_height);
+        Self {
+            halo_origin: layout.halo_origin,
+            halo_offsets: layout.halo_offsets,
+            background: Background::new(theme.particle, screen_width, screen_height),
+            intro: IntroScreen::new(theme.intro, layout.intro, screen_height),
+            standby: StandbyScreen::new(theme.idle, layout.idle, layout.idle_walk),
+            active_channel: ActiveChannelScreen::new(theme.active, layout.active),
+            outro: OutroScreen::new(theme.outro, layout.outro),
+            indicator: VolumeIndicator::new(layout.indicator),
+        }
+    }
+
+    /// Draws one frame of `state` on top of `frame`, moves the animations on
+    /// by `dt_ms` and returns the state for the next frame. `dt_ms` is the time
+    /// since the previous frame; `active` is only used by
+    /// `ScreenState::ACTIVE`.
+    pub fn draw<const N: usize>(
+        &mut self,
+        frame: &mut Gray4Frame<N>,
+        state: ScreenState,
+        active: Option<ActiveChannel>,
+        dt_ms: u32,
+    ) -> ScreenState {
+        let dt_ms = dt_ms.min(MAX_FRAME_MS);
+
+        match state {
+            ScreenState::INTRO => {
+                self.background.draw(frame, true, dt_ms);
+                let finished = self.intro.draw(frame, dt_ms);
+
+                if self.intro.firework_time {
+                    self.background
+                        .start_intro_halo(self.halo_origin, &self.halo_offsets);
+                }
+                if finished {
+                    return ScreenState::STANDBY;
+                }
+            }
+            ScreenState::STANDBY => {
+                self.background.draw(frame, false, dt_ms);
+                self.standby.draw(frame, dt_ms);
+            }
+            ScreenState::ACTIVE => {
+                let input = active.is_some_and(|a| a.input);
+                self.active_channel.draw(frame, input, dt_ms);
+
+                if let Some(a) = active {
+                    self.indicator.draw(frame, a.value, a.icon);
+                    if a.muted {
+                        self.indicator.draw_muted(frame);
+                    }
+                }
+            }
+            ScreenState::OUTRO => {
+                if self.outro.draw(frame, dt_ms) {
+                    return ScreenState::OFF;
+                }
+            }
+            ScreenState::OFF => {}
+        }
+        state
+    }
+}
+
+/// Falling particles behind the standby screen, and the halo around the
+/// character during the intro; see `crate::particles`.
+pub struct Background {
+    particles: Emitter,
+    /// The halo is up; the rain takes over its particles once the intro ends.
+    halo: bool,
+}
+
+impl Background {
+    pub fn new(sheet: &'static SpriteSheet, screen_width: i32, screen_height: i32) -> Self {
+        Self {
+            particles: Emitter::new(particles::halo(sheet), screen_width, screen_height),
+            halo: false,
+        }
+    }
+
+    /// `intro_running` keeps the halo up; once the intro is over the cobwebs
+    /// start falling.
+    pub fn draw<const N: usize>(
+        &mut self,
+        frame: &mut Gray4Frame<N>,
+        intro_running: bool,
+        dt_ms: u32,
+    ) {
+        self.particles.draw(frame);
+
+        if self.halo && !intro_running {
+            let sheet = self.particles.config().sheet;
+            self.particles.hand_over(particles::cobweb_rain(sheet));
+            self.halo = false;
+        } else {
+            self.particles.advance(dt_ms);
+        }
+    }
+
+    pub fn start_intro_halo(&mut self, origin: Point, offsets: &[Point; HALO_PARTICLES]) {
+        let sheet = self.particles.config().sheet;
+        let points = offsets.map(|offset| origin + offset);
+        self.particles.burst(particles::halo(sheet), &points);
+        self.halo = true;
+    }
+}
+
+pub struct IntroScreen {
+    sheet: &'static SpriteSheet,
+    start_coords: Point,
+    rest_y: i32,
+    coords: Point,
+    anim: Animation,
+    rise: Stepper,
+    elapsed_ms: u32,
+    pub firework_time: bool,
+}
+
+impl IntroScreen {
+    /// The character rises from `rise_from` rows below `rest` up to it.
+    pub fn new(sheet: &'static SpriteSheet, rest: Point, rise_from: i32) -> Self {
+        let start_coords = rest + Point::new(0, rise_from);
+        Self {
+            sheet,
+            start_coords,
+            rest_y: rest.y,
+            coords: start_coords,
+            anim: Animation::new(sheet),
+            rise: Stepper::new(8, STEP_MS),
+            elapsed_ms: 0,
+            firework_time: false,
+        }
+    }
+
+    /// Returns true once the intro is over.
+    pub fn draw<const N: usize>(&mut self, frame: &mut Gray4Frame<N>, dt_ms: u32) -> bool {
+        blit_sheet_frame_masked(frame, self.sheet, self.anim.frame(), self.coords);
+
+        self.anim.advance(dt_ms);
+
+        let rise = self.rise.advance(dt_ms) as i32;
+        self.coords = Point::new(self.start_coords.x, (self.coords.y - rise).max(self.rest_y));
+
+        let before = self.elapsed_ms;
+        self.elapsed_ms += dt_ms;
+        self.firework_time = before < FIREWORK_MS && self.elapsed_ms >= FIREWORK_MS;
+
+        if self.elapsed_ms >= INTRO_MS {
+            self.coords = self.start_coords;
+            self.elapsed_ms = 0;
+            self.anim.restart_at(0);
+            self.rise.reset();
+            return true;
+        }
+        false
+    }
+}
+
+pub struct StandbyScreen {
+    sheet: &'static SpriteSheet,
+    width: u32,
+    coords: Point,
+    anim: Animation,
+    walk: Stepper,
+    direction: bool,
+}
+
+impl StandbyScreen {
+    pub fn new(sheet: &'static SpriteSheet, coords: Point, width: u32) -> Self {
+        Self {
+            sheet,
+            coords,
+            width,
+            anim: Animation::new(sheet),
+            walk: Stepper::every(STEP_MS),
+            direction: true,
+        }
+    }
+
+    pub fn draw<const N: usize>(&mut self, frame: &mut Gray4Frame<N>, dt_ms: u32) {
+        blit_sheet_frame_masked(frame, self.sheet, self.anim.frame(), self.coords);
+
+        self.anim.advance(dt_ms);
+
+        for _ in 0..self.walk.advance(dt_ms) {
+            if self.direction {
+                self.coords += Point::new(1, 0);
+            } else {
+                self.coords -= Point::new(1, 0);
+            }
+            if self.coords.x >= self.width as i32 || self.coords.x <= 0 {
+                self.direction = !self.direction;
+            }
+        }
+    }
+}
+
+pub struct ActiveChannelScreen {
+    sheet: &'static SpriteSheet,
+    coords: Point,
+    anim: Animation,
+    glitch: Stepper,
+    glitch_seed: u8,
+}
+
+impl ActiveChannelScreen {
+    pub fn new(sheet: &'static SpriteSheet, coords: Point) -> Self {
+        Self {
+            sheet,
+            coords,
+            anim: Animation::new(sheet),
+            glitch: Stepper::every(STEP_MS),
+            glitch_seed: 0,
+        }
+    }
+
+    /// `glitching` turns on the CRT effect while a fader is moving.
+    pub fn draw<const N: usize>(&mut self, frame: &mut Gray4Frame<N>, glitching: bool, dt_ms: u32) {
+        blit_sheet_frame_masked_crt(
+            frame,
+            self.sheet,
+            self.anim.frame(),
+            self.coords,
+            self.glitch_seed,
+            glitching,
+        );
+        let steps = self.glitch.advance(dt_ms);
+        self.glitch_seed = self.glitch_seed.wrapping_add(steps as u8);
+
+        self.anim.advance(dt_ms);
+    }
+}
+
+pub struct OutroScreen {
+    sheet: &'static SpriteSheet,
+    start_coords: Point,
+    coords: Point,
+    anim: Animation,
+    fade: Stepper,
+    fade_step: u8,
+    fade_steps: u8,
+}
+
+impl OutroScreen {
+    pub fn new(sheet: &'static SpriteSheet, start_coords: Point) -> Self {
+        Self {
+            sheet,
+            start_coords,
+            coords: start_coords,
+            anim: Animation::new(sheet),
+            fade: Stepper::every(STEP_MS),
+            fade_step: 0,
+            fade_steps: 16,
+        }
+    }
+
+    /// Returns true once the character has faded out.
+    pub fn draw<const N: usize>(&mut self, frame: &mut Gray4Frame<N>, dt_ms: u32) -> bool {
+        blit_sheet_frame_fade_dither(
+            frame,
+            self.sheet,
+            self.anim.frame(),
+            self.coords,
+            self.fade_step,
+            self.fade_steps,
+        );
+
+        self.anim.advance(dt_ms);
+
+        if self.fade_step < self.fade_steps {
+            let steps = self.fade.advance(dt_ms).min(u8::MAX as u32) as u8;
+            self.fade_step = self.fade_step.saturating_add(steps).min(self.fade_steps);
+            false
+        } else {
+            self.coords = self.start_coords;
+            self.fade_step = 0;
+            self.fade.reset();
+            self.anim.restart_at(0);
+            true
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{gray4, packbits};
+
+/// Widest row a sheet may have, in bytes (one full 256 px screen row).
+pub const MAX_ROW_BYTES: usize = 128;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SheetEncoding {
+    /// Frames stored back to back, uncompressed.
+    Raw,
+    /// Frames coded with `packbits::encode_sheet`.
+    PackBits,
+}
+
+/// A packed Gray4 sprite sheet: `frames` frames of `width` x `height`
+/// pixels, two pixels per byte with the left one in the high nibble.
+/// Generated into the firmware's `crate::assets`.
+#[derive(Clone, Copy)]
+pub struct SpriteSheet {
+    pub data: &'static [u8],
+    pub width: u32,
+    pub height: u32,
+    pub frames: usize,
+    /// How long each frame is shown when the sheet is played as an
+    /// animation, see `crate::anim::Animation`.
+    pub frame_ms: u32,
+    pub encoding: SheetEncoding,
+}
+
+impl SpriteSheet {
+    #[inline]
+    pub const fn row_bytes(&self) -> usize {
+        gray4::row_bytes(self.width as usize)
+    }
+
+    #[inline]
+    pub const fn frame_bytes(&self) -> usize {
+        self.row_bytes() * self.height as usize
+    }
+
+    /// Streams frame `idx` one row at a time.
+    pub fn rows(&self, idx: usize) -> FrameRows<'static> {
+        let stride = self.row_bytes();
+        debug_assert!(stride <= MAX_ROW_BYTES);
+
+        let source = match self.encoding {
+            SheetEncoding::Raw => {
+                let start = idx * self.frame_bytes();
+                RowSource::Raw(&self.data[start..start + self.frame_bytes()])
+            }
+            SheetEncoding::PackBits => RowSource::PackBits(packbits::Decoder::new(
+                packbits::sheet_frame(self.data, idx),
+            )),
+        };
+
+        FrameRows {
+            source,
+            stride,
+            y: 0,
+            buf: [0; MAX_ROW_BYTES],
+        }
+    }
+
+    /// Decodes the whole of frame `idx` into `out`, which must hold at least
+    /// `frame_bytes()` bytes.
+    pub fn decode_frame(&self, idx: usize, out: &mut [u8]) {
+        let stride = self.row_bytes();
+        let mut rows = self.rows(idx);
+        for row in out[..self.frame_bytes()].chunks_exact_mut(stride) {
+            row.copy_from_slice(rows.next_row());
+        }
+    }
+}
+
+enum RowSource<'a> {
+    Raw(&'a [u8]),
+    PackBits(packbits::Decoder<'a>),
+}
+
+/// Row reader returned by `SpriteSheet::rows`. Raw sheets hand out slices of
+/// flash directly; coded ones are decompressed into a single row buffer.
+pub struct FrameRows<'a> {
+    source: RowSource<'a>,
+    stride: usize,
+    y: usize,
+    buf: [u8; MAX_ROW_BYTES],
+}
+
+impl FrameRows<'_> {
+    /// Returns the next row, `row_bytes()` long.
+    pub fn next_row(&mut self) -> &[u8] {
+        let y = self.y;
+        self.y += 1;
+
+        match &mut self.source {
+            RowSource::Raw(frame) => &frame[y * self.stride..(y + 1) * self.stride],
+            RowSource::PackBits(decoder) => {
+                decoder.read(&mut self.buf[..self.stride]);
+                &self.buf[..self.stride]
+            }
+        }
+    }
+}
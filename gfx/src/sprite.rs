@@ -3,52 +3,37 @@ use embedded_graphics::pixelcolor::Gray4;
 use embedded_graphics::prelude::*;
 use embedded_graphics::Pixel;
 
-use crate::gray4::row_bytes;
-
-#[inline]
-pub fn frame_count(sheet: &[u8], w: u32, h: u32) -> usize {
-    let fsz = row_bytes(w as usize) * h as usize;
-    sheet.len() / fsz
-}
-
-#[inline]
-fn frame_slice(sheet: &[u8], w: u32, h: u32, idx: usize) -> &[u8] {
-    let fsz = row_bytes(w as usize) * h as usize;
-    let start = idx * fsz;
-    &sheet[start..start + fsz]
-}
+use crate::sheet::SpriteSheet;
 
 pub fn draw_sheet_frame<D: DrawTarget<Color = Gray4>>(
     display: &mut D,
-    sheet: &[u8],
-    w: u32,
-    h: u32,
+    sheet: &SpriteSheet,
     idx: usize,
     pos: Point,
 ) -> Result<(), D::Error> {
-    let frm = frame_slice(sheet, w, h, idx);
+    let mut rows = sheet.rows(idx);
 
-    let raw = ImageRawLE::<Gray4>::new(&frm, w);
-    Image::new(&raw, pos).draw(display)
+    for y in 0..sheet.height as i32 {
+        let raw = ImageRawLE::<Gray4>::new(rows.next_row(), sheet.width);
+        Image::new(&raw, pos + Point::new(0, y)).draw(display)?;
+    }
+
+    Ok(())
 }
 
 pub fn draw_sheet_frame_masked<D: DrawTarget<Color = Gray4>>(
     display: &mut D,
-    sheet: &[u8],
-    w: u32,
-    h: u32,
+    sheet: &SpriteSheet,
     idx: usize,
     pos: Point,
 ) -> Result<(), D::Error> {
-    let frm = frame_slice(sheet, w, h, idx);
+    let mut rows = sheet.rows(idx);
 
-    let width = w as usize;
-    let height = h as usize;
-    let stride = row_bytes(width); // bytes per row
+    let width = sheet.width as usize;
+    let height = sheet.height as usize;
 
     for y in 0..height {
-        let row_start = y * stride;
-        let row = &frm[row_start..row_start + stride];
+        let row = rows.next_row();
 
         let mut x = 0usize;
 
@@ -83,24 +68,19 @@ pub fn draw_sheet_frame_masked<D: DrawTarget<Color = Gray4>>(
 
 pub fn draw_sheet_frame_flash<D: DrawTarget<Color = Gray4>>(
     display: &mut D,
-    sheet: &[u8],
-    w: u32,
-    h: u32,
+    sheet: &SpriteSheet,
     idx: usize,
     pos: Point,
     flash_step: u8,  // 0..=flash_steps (0 = biggest flash)
     flash_steps: u8, // duration of flash, e.g. 16
 ) -> Result<(), D::Error> {
-    let frm = frame_slice(sheet, w, h, idx);
-
-    let width = w as usize;
-    let height = h as usize;
-    let stride = row_bytes(width);
+    let width = sheet.width as usize;
+    let height = sheet.height as usize;
 
     // If no flash or we've gone past the flash duration,
     // just draw normally at base brightness.
     if flash_steps == 0 || flash_step >= flash_steps {
-        return draw_sheet_frame_masked(display, sheet, w, h, idx, pos);
+        return draw_sheet_frame_masked(display, sheet, idx, pos);
     }
 
     // Monotonic decay:
@@ -115,9 +95,10 @@ pub fn draw_sheet_frame_flash<D: DrawTarget<Color = Gray4>>(
     // scaled with rounding: boost = MAX_BOOST * remaining / steps
     let boost: u8 = ((remaining * MAX_BOOST as u16 + steps / 2) / steps) as u8;
 
+    let mut rows = sheet.rows(idx);
+
     for y in 0..height {
-        let row_start = y * stride;
-        let row = &frm[row_start..row_start + stride];
+        let row = rows.next_row();
 
         let mut x = 0usize;
 
@@ -166,27 +147,23 @@ pub fn draw_sheet_frame_flash<D: DrawTarget<Color = Gray4>>(
 
 pub fn draw_sheet_frame_masked_crt<D: DrawTarget<Color = Gray4>>(
     display: &mut D,
-    sheet: &[u8],
-    w: u32,
-    h: u32,
+    sheet: &SpriteSheet,
     idx: usize,
     pos: Point,
     frame_tick: u8,
     is_glitching: bool,
 ) -> Result<(), D::Error> {
-    let frm = frame_slice(sheet, w, h, idx);
-
-    let width = w as usize;
-    let height = h as usize;
-    let stride = row_bytes(width);
+    let width = sheet.width as usize;
+    let height = sheet.height as usize;
 
     if !is_glitching {
-        return draw_sheet_frame_masked(display, sheet, w, h, idx, pos);
+        return draw_sheet_frame_masked(display, sheet, idx, pos);
     }
 
+    let mut rows = sheet.rows(idx);
+
     for y in 0..height {
-        let row_start = y * stride;
-        let row = &frm[row_start..row_start + stride];
+        let row = rows.next_row();
 
         let mut x = 0usize;
 
@@ -262,31 +239,27 @@ fn scale_gray4(v: u8, num: u16, den: u16) -> u8 {
 
 pub fn draw_sheet_frame_fade_dither<D: DrawTarget<Color = Gray4>>(
     display: &mut D,
-    sheet: &[u8],
-    w: u32,
-    h: u32,
+    sheet: &SpriteSheet,
     idx: usize,
     pos: Point,
     fade_step: u8,
     fade_steps: u8,
 ) -> Result<(), D::Error> {
     if fade_steps == 0 || fade_step == 0 {
-        return draw_sheet_frame_masked(display, sheet, w, h, idx, pos);
+        return draw_sheet_frame_masked(display, sheet, idx, pos);
     }
 
-    let frm = frame_slice(sheet, w, h, idx);
-
-    let width = w as usize;
-    let height = h as usize;
-    let stride = row_bytes(width);
+    let width = sheet.width as usize;
+    let height = sheet.height as usize;
 
     let clamped_step = core::cmp::min(fade_step, fade_steps) as u16;
     let num = (fade_steps as u16).saturating_sub(clamped_step); // remaining
     let den = fade_steps as u16;
 
+    let mut rows = sheet.rows(idx);
+
     for y in 0..height {
-        let row_start = y * stride;
-        let row = &frm[row_start..row_start + stride];
+        let row = rows.next_row();
 
         let mut x = 0usize;
 
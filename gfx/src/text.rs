@@ -0,0 +1,107 @@
+//! Plain text for the service screens (crash, diagnostics): top to bottom
+//! lines in a 4x6 font, formatted without allocating.
+
+use core::fmt::{self, Write};
+
+use embedded_graphics::mono_font::ascii::FONT_4X6;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+
+use crate::frame::Gray4Frame;
+
+pub const FONT_WIDTH: i32 = 4;
+pub const FONT_HEIGHT: i32 = 6;
+
+/// Longest line drawn.
+const LINE_BYTES: usize = 128;
+
+/// Writes lines down a frame from `origin`; lines below the bottom are
+/// dropped.
+pub(crate) struct Lines<'a, const N: usize> {
+    frame: &'a mut Gray4Frame<N>,
+    style: MonoTextStyle<'static, Gray4>,
+    x: i32,
+    y: i32,
+}
+
+impl<'a, const N: usize> Lines<'a, N> {
+    pub fn new(frame: &'a mut Gray4Frame<N>, origin: Point) -> Self {
+        Self {
+            frame,
+            style: MonoTextStyle::new(&FONT_4X6, Gray4::WHITE),
+            x: origin.x,
+            y: origin.y,
+        }
+    }
+
+    /// Characters that fit on a line.
+    pub fn columns(&self) -> usize {
+        ((self.frame.width() as i32 - self.x) / FONT_WIDTH).max(1) as usize
+    }
+
+    pub fn line(&mut self, args: fmt::Arguments) {
+        if self.y + FONT_HEIGHT > self.frame.height() as i32 {
+            return;
+        }
+        let mut text = FixedStr::<LINE_BYTES>::new();
+        let _ = text.write_fmt(args);
+        let _ = Text::with_baseline(
+            text.as_str(),
+            Point::new(self.x, self.y),
+            self.style,
+            Baseline::Top,
+        )
+        .draw(self.frame);
+        self.y += FONT_HEIGHT;
+    }
+}
+
+/// Fixed-capacity UTF-8 text that drops what does not fit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct FixedStr<const B: usize> {
+    pub buf: [u8; B],
+    pub len: u8,
+}
+
+impl<const B: usize> FixedStr<B> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; B],
+            len: 0,
+        }
+    }
+
+    /// `bytes` as stored by a `FixedStr<B>`; `None` unless the first `len`
+    /// of them are UTF-8.
+    pub fn from_bytes(bytes: &[u8], len: u8) -> Option<Self> {
+        let mut text = Self::new();
+        text.buf.copy_from_slice(bytes);
+        text.len = len;
+        core::str::from_utf8(text.buf.get(..len as usize)?).ok()?;
+        Some(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len as usize]).unwrap_or("")
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        for c in s.chars() {
+            let len = self.len as usize;
+            if len + c.len_utf8() > B {
+                break;
+            }
+            c.encode_utf8(&mut self.buf[len..]);
+            self.len += c.len_utf8() as u8;
+        }
+    }
+}
+
+impl<const B: usize> Write for FixedStr<B> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
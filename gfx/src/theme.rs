@@ -0,0 +1,260 @@
+//! Character themes: which sprite sheets the scenes play, and where.
+//!
+//! A `ThemeDef` names a theme's sheets as `assets/sprites.toml` does, and
+//! `ThemeDef::build` looks them up: the firmware's `themes.rs` builds its
+//! themes from `crate::assets::SHEETS`, the host tests from the converted
+//! manifest, so both render the same ones.
+
+use embedded_graphics::prelude::*;
+
+use crate::sheet::SpriteSheet;
+use crate::volume_indicator::{ICON_HEIGHT, ICON_WIDTH};
+
+/// Particles placed around `Layout::halo_origin` during the intro.
+pub const HALO_PARTICLES: usize = 4;
+
+/// The screen size layouts are written for.
+pub const LAYOUT_WIDTH: i32 = 256;
+pub const LAYOUT_HEIGHT: i32 = 64;
+
+pub struct Theme {
+    /// Name the `theme` USB command selects it by.
+    pub name: &'static str,
+    /// Rises into view during the intro.
+    pub intro: &'static SpriteSheet,
+    /// Walks back and forth on the standby screen.
+    pub idle: &'static SpriteSheet,
+    /// Shown next to the volume indicator while a fader moves.
+    pub active: &'static SpriteSheet,
+    /// Fades out when the host goes away.
+    pub outro: &'static SpriteSheet,
+    /// Background particle: the intro halo and the rain on standby.
+    pub particle: &'static SpriteSheet,
+    pub layout: Layout,
+}
+
+impl Theme {
+    /// `layout` moved onto a `width` x `height` screen. Every sprite keeps its
+    /// place relative to the room it has to move in (left, centred, right
+    /// and so on), the halo follows the intro sprite and spreads with the
+    /// screen, and the idle walk grows or shrinks with the width. Where that
+    /// puts the active sprite over the volume icon, the icon goes under it if
+    /// the screen is tall enough, and otherwise to the right edge with the
+    /// sprite ending where it starts, off the left edge if need be.
+    pub fn layout_for(&self, width: i32, height: i32) -> Layout {
+        let l = &self.layout;
+        if (width, height) == (LAYOUT_WIDTH, LAYOUT_HEIGHT) {
+            return *l;
+        }
+
+        let place = |pos: Point, sheet: &SpriteSheet| {
+            fit(pos, sheet.width as i32, sheet.height as i32, width, height)
+        };
+        let intro = place(l.intro, self.intro);
+        let (active, indicator) = self.clear_of_icon(
+            place(l.active, self.active),
+            fit(
+                l.indicator,
+                ICON_WIDTH as i32,
+                ICON_HEIGHT as i32,
+                width,
+                height,
+            ),
+            width,
+            height,
+        );
+
+        Layout {
+            intro,
+            halo_origin: l.halo_origin + (intro - l.intro),
+            halo_offsets: l
+                .halo_offsets
+                .map(|o| Point::new(o.x * width / LAYOUT_WIDTH, o.y * height / LAYOUT_HEIGHT)),
+            idle: place(l.idle, self.idle),
+            idle_walk: (l.idle_walk as i32 + width - LAYOUT_WIDTH).max(0) as u32,
+            active,
+            indicator,
+            outro: place(l.outro, self.outro),
+        }
+    }
+
+    /// `active` and `indicator` moved apart if the active sprite and the
+    /// volume icon overlap there; see `layout_for`.
+    fn clear_of_icon(
+        &self,
+        active: Point,
+        indicator: Point,
+        width: i32,
+        height: i32,
+    ) -> (Point, Point) {
+        let (aw, ah) = (self.active.width as i32, self.active.height as i32);
+        let (iw, ih) = (ICON_WIDTH as i32, ICON_HEIGHT as i32);
+        let apart = active.x + aw <= indicator.x
+            || indicator.x + iw <= active.x
+            || active.y + ah <= indicator.y
+            || indicator.y + ih <= active.y;
+
+        if apart {
+            (active, indicator)
+        } else if ah + ih <= height {
+            let indicator = Point::new(indicator.x, height - ih);
+            let active = Point::new(active.x, (indicator.y - ah) / 2);
+            (active, indicator)
+        } else {
+            let indicator = Point::new(width - iw, indicator.y);
+            let active = Point::new(active.x.min(indicator.x - aw), active.y);
+            (active, indicator)
+        }
+    }
+}
+
+/// A theme by the names of its sheets in `assets/sprites.toml`.
+pub struct ThemeDef {
+    pub name: &'static str,
+    pub intro: &'static str,
+    pub idle: &'static str,
+    pub active: &'static str,
+    pub outro: &'static str,
+    pub particle: &'static str,
+    pub layout: Layout,
+}
+
+impl ThemeDef {
+    /// The theme, with its sheets found by name in `sheets`. Panics if one is
+    /// missing, which for a `const` means the build fails.
+    pub const fn build(&self, sheets: &[(&str, &'static SpriteSheet)]) -> Theme {
+        Theme {
+            name: self.name,
+            intro: find_sheet(sheets, self.intro),
+            idle: find_sheet(sheets, self.idle),
+            active: find_sheet(sheets, self.active),
+            outro: find_sheet(sheets, self.outro),
+            particle: find_sheet(sheets, self.particle),
+            layout: self.layout,
+        }
+    }
+}
+
+const fn find_sheet(sheets: &[(&str, &'static SpriteSheet)], name: &str) -> &'static SpriteSheet {
+    let mut i = 0;
+    while i < sheets.len() {
+        if str_eq(sheets[i].0, name) {
+            return sheets[i].1;
+        }
+        i += 1;
+    }
+    panic!("theme sheet missing from the assets");
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Every theme; the firmware builds in those with their `theme-*` feature.
+pub const THEME_DEFS: &[ThemeDef] = &[MUFFET, COBWEB];
+
+/// Muffet: a 122 px close-up for the intro, active and outro screens and a
+/// 104 px walking sprite, with cobwebs for particles.
+pub const MUFFET: ThemeDef = ThemeDef {
+    name: "muffet",
+    intro: "muffet_close",
+    idle: "muffet",
+    active: "muffet_close",
+    outro: "muffet_close",
+    particle: "cobweb_rotating",
+    layout: MUFFET_LAYOUT,
+};
+
+/// A single cobweb sprite for everything.
+pub const COBWEB: ThemeDef = ThemeDef {
+    name: "cobweb",
+    intro: "cobweb_rotating",
+    idle: "cobweb_rotating",
+    active: "cobweb_rotating",
+    outro: "cobweb_rotating",
+    particle: "cobweb_rotating",
+    layout: COBWEB_LAYOUT,
+};
+
+/// Moves the top left corner `pos` of a `w` x `h` sprite from the layout
+/// screen onto a `width` x `height` one.
+fn fit(pos: Point, w: i32, h: i32, width: i32, height: i32) -> Point {
+    fn axis(p: i32, size: i32, from: i32, to: i32) -> i32 {
+        if from == to {
+            p
+        } else if from == size {
+            // Filled the screen: no telling where it was anchored.
+            (to - size) / 2
+        } else {
+            p * (to - size) / (from - size)
+        }
+    }
+    Point::new(
+        axis(pos.x, w, LAYOUT_WIDTH, width),
+        axis(pos.y, h, LAYOUT_HEIGHT, height),
+    )
+}
+
+/// Where a theme puts things on a `LAYOUT_WIDTH` x `LAYOUT_HEIGHT` screen;
+/// see `Theme::layout_for` for other panels.
+#[derive(Clone, Copy)]
+pub struct Layout {
+    /// Where the intro character comes to rest; it rises from below the
+    /// screen to get there.
+    pub intro: Point,
+    /// Centre of the intro halo, and the particle positions around it.
+    pub halo_origin: Point,
+    pub halo_offsets: [Point; HALO_PARTICLES],
+    /// Start of the idle walk, and how far right it goes.
+    pub idle: Point,
+    pub idle_walk: u32,
+    pub active: Point,
+    /// Top left of the volume icon.
+    pub indicator: Point,
+    pub outro: Point,
+}
+
+/// Where `MUFFET` puts its sprites.
+pub const MUFFET_LAYOUT: Layout = Layout {
+    intro: Point::new(66, 0),
+    halo_origin: Point::new(108, 20),
+    halo_offsets: [
+        Point::new(-90, -15), // left-up
+        Point::new(90, -15),  // right-up
+        Point::new(-60, 15),  // left-down
+        Point::new(60, 15),   // right-down
+    ],
+    idle: Point::new(0, 0),
+    idle_walk: 151,
+    active: Point::new(15, 0),
+    indicator: Point::new(170, 1),
+    outro: Point::new(66, 0),
+};
+
+/// Where `COBWEB` puts its 40 px sprite: centred vertically.
+pub const COBWEB_LAYOUT: Layout = Layout {
+    intro: Point::new(108, 12),
+    halo_origin: Point::new(108, 12),
+    halo_offsets: [
+        Point::new(-70, -10),
+        Point::new(70, -10),
+        Point::new(-40, 10),
+        Point::new(40, 10),
+    ],
+    idle: Point::new(0, 12),
+    idle_walk: 215,
+    active: Point::new(60, 12),
+    indicator: Point::new(170, 1),
+    outro: Point::new(108, 12),
+};
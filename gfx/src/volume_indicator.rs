@@ -0,0 +1,94 @@
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Line, PrimitiveStyle};
+
+use crate::blit::blit_image;
+use crate::frame::Gray4Frame;
+use crate::gray4::{self, Gray4Img, Gray4ImgMut};
+use crate::gray4_effects::{fill_bottom_to_top, FillParams};
+use crate::sheet::SpriteSheet;
+
+/// Size of the volume icons, see the `logo_*` entries in `assets/sprites.toml`.
+pub const ICON_WIDTH: usize = 62;
+pub const ICON_HEIGHT: usize = 62;
+
+const W: usize = ICON_WIDTH;
+const H: usize = ICON_HEIGHT;
+const BYTES: usize = gray4::size_bytes(W, H);
+
+pub struct VolumeIndicator {
+    coords: Point,
+    // Icons may be compressed, so the current one is decoded once, and again
+    // when the icon changes; this is where its data starts.
+    decoded: Option<usize>,
+    icon_buf: [u8; BYTES],
+    out_buf: [u8; BYTES],
+    scratch_row: [u8; W],
+}
+
+impl VolumeIndicator {
+    pub fn new(coords: Point) -> Self {
+        Self {
+            coords,
+            decoded: None,
+            icon_buf: [0; BYTES],
+            out_buf: [0; BYTES],
+            scratch_row: [0; W],
+        }
+    }
+
+    /// Draws `icon` filled up to `adc_value`.
+    pub fn draw<const N: usize>(
+        &mut self,
+        frame: &mut Gray4Frame<N>,
+        adc_value: u16,
+        icon: &SpriteSheet,
+    ) {
+        debug_assert!(icon.width as usize == W && icon.height as usize == H);
+
+        let data = icon.data.as_ptr() as usize;
+        if self.decoded != Some(data) {
+            icon.decode_frame(0, &mut self.icon_buf);
+            self.decoded = Some(data);
+        }
+
+        let mut dst = Gray4ImgMut {
+            bytes: &mut self.out_buf,
+            w: W,
+            h: H,
+        };
+        let src = Gray4Img {
+            bytes: &self.icon_buf,
+            w: W,
+            h: H,
+        };
+
+        fill_bottom_to_top(
+            &mut dst,
+            &src,
+            adc_value,
+            FillParams {
+                empty_b: 1,
+                full_b: 3,
+            },
+            &mut self.scratch_row,
+        );
+
+        blit_image(frame, &self.out_buf, W as u32, H as u32, self.coords);
+    }
+
+    /// Crosses out the icon of a muted channel: a white slash in a black
+    /// outline, so it shows on any fill level.
+    pub fn draw_muted<const N: usize>(&self, frame: &mut Gray4Frame<N>) {
+        let slash = Line::new(
+            self.coords + Point::new(W as i32 - 4, 3),
+            self.coords + Point::new(3, H as i32 - 4),
+        );
+        let _ = slash
+            .into_styled(PrimitiveStyle::with_stroke(Gray4::BLACK, 6))
+            .draw(frame);
+        let _ = slash
+            .into_styled(PrimitiveStyle::with_stroke(Gray4::WHITE, 2))
+            .draw(frame);
+    }
+}
@@ -0,0 +1,134 @@
+use deej_gfx::blit::{
+    blit_image, blit_sheet_frame_fade_dither, blit_sheet_frame_flash, blit_sheet_frame_masked,
+    blit_sheet_frame_masked_crt,
+};
+use deej_gfx::sheet::SpriteSheet;
+use deej_gfx::sprite::{
+    draw_sheet_frame_fade_dither, draw_sheet_frame_flash, draw_sheet_frame_masked,
+    draw_sheet_frame_masked_crt,
+};
+use embedded_graphics::image::{Image, ImageRawLE};
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+
+mod common;
+
+use common::{sheets, Frame, H, W};
+
+/// A non-blank background so that writes to "transparent" pixels show up.
+fn background() -> Box<Frame> {
+    let mut frame = Box::new(Frame::new(W, H));
+    for (i, b) in frame.as_bytes_mut().iter_mut().enumerate() {
+        *b = (i as u8).wrapping_mul(37);
+    }
+    frame
+}
+
+/// On-screen, odd-aligned and clipped on every edge.
+fn positions(sheet: &SpriteSheet) -> Vec<Point> {
+    let (w, h) = (sheet.width as i32, sheet.height as i32);
+    vec![
+        Point::new(0, 0),
+        Point::new(1, 0),
+        Point::new(67, 3),
+        Point::new(-w / 2 - 1, -h / 3),
+        Point::new(W as i32 - w / 2 + 1, H as i32 - h / 2),
+        Point::new(-w, 0),
+        Point::new(W as i32, 0),
+        Point::new(10, -h),
+        Point::new(10, H as i32),
+    ]
+}
+
+fn assert_same<Draw, Blit>(name: &str, draw: Draw, blit: Blit)
+where
+    Draw: Fn(&mut Frame, &SpriteSheet, usize, Point),
+    Blit: Fn(&mut Frame, &SpriteSheet, usize, Point),
+{
+    for sheet in sheets() {
+        for idx in [0, sheet.frames - 1] {
+            for pos in positions(&sheet) {
+                let mut expected = background();
+                let mut actual = background();
+                draw(&mut expected, &sheet, idx, pos);
+                blit(&mut actual, &sheet, idx, pos);
+                assert!(
+                    expected.as_bytes() == actual.as_bytes(),
+                    "{}: {}x{} frame {} at {:?}",
+                    name,
+                    sheet.width,
+                    sheet.height,
+                    idx,
+                    pos
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn masked_matches_draw_iter() {
+    assert_same(
+        "masked",
+        |f, s, i, p| draw_sheet_frame_masked(f, s, i, p).unwrap(),
+        blit_sheet_frame_masked,
+    );
+}
+
+#[test]
+fn flash_matches_draw_iter() {
+    for step in [0, 1, 2, 3, 4] {
+        assert_same(
+            "flash",
+            |f, s, i, p| draw_sheet_frame_flash(f, s, i, p, step, 3).unwrap(),
+            |f, s, i, p| blit_sheet_frame_flash(f, s, i, p, step, 3),
+        );
+    }
+}
+
+#[test]
+fn crt_matches_draw_iter() {
+    for tick in [0, 1, 5, 200] {
+        for glitch in [false, true] {
+            assert_same(
+                "crt",
+                |f, s, i, p| draw_sheet_frame_masked_crt(f, s, i, p, tick, glitch).unwrap(),
+                |f, s, i, p| blit_sheet_frame_masked_crt(f, s, i, p, tick, glitch),
+            );
+        }
+    }
+}
+
+#[test]
+fn fade_matches_draw_iter() {
+    for step in [0, 1, 5, 6, 15, 16, 17] {
+        assert_same(
+            "fade",
+            |f, s, i, p| draw_sheet_frame_fade_dither(f, s, i, p, step, 16).unwrap(),
+            |f, s, i, p| blit_sheet_frame_fade_dither(f, s, i, p, step, 16),
+        );
+    }
+}
+
+#[test]
+fn image_matches_image_raw() {
+    for sheet in sheets() {
+        let mut data = vec![0u8; sheet.frame_bytes()];
+        sheet.decode_frame(0, &mut data);
+
+        for pos in positions(&sheet) {
+            let mut expected = background();
+            let mut actual = background();
+            let raw = ImageRawLE::<Gray4>::new(&data, sheet.width);
+            Image::new(&raw, pos).draw(&mut *expected).unwrap();
+            blit_image(&mut actual, &data, sheet.width, sheet.height, pos);
+            assert!(
+                expected.as_bytes() == actual.as_bytes(),
+                "image {}x{} at {:?}",
+                sheet.width,
+                sheet.height,
+                pos
+            );
+        }
+    }
+}
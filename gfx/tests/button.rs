@@ -0,0 +1,98 @@
+use deej_gfx::button::{Button, Press, DOUBLE_PRESS_MS, LONG_PRESS_MS};
+
+/// Sampling period of the firmware's `buttons_task`.
+const SAMPLE_MS: u32 = 5;
+
+/// Plays a trace of `(level, ms)` steps, sampling every `SAMPLE_MS`, and
+/// returns every press with the time it was reported.
+fn play(trace: &[(bool, u32)]) -> Vec<(u32, Press)> {
+    let mut button = Button::new();
+    let mut presses = Vec::new();
+    let mut now = 0;
+    for &(down, ms) in trace {
+        for _ in 0..ms / SAMPLE_MS {
+            if let Some(press) = button.update(now, down) {
+                presses.push((now, press));
+            }
+            now += SAMPLE_MS;
+        }
+    }
+    presses
+}
+
+fn kinds(presses: &[(u32, Press)]) -> Vec<Press> {
+    presses.iter().map(|&(_, p)| p).collect()
+}
+
+#[test]
+fn bouncy_tap_is_one_short_press() {
+    let presses = play(&[
+        (false, 50),
+        // Contact bounce on the way down and up.
+        (true, 5),
+        (false, 5),
+        (true, 5),
+        (false, 5),
+        (true, 120),
+        (false, 5),
+        (true, 5),
+        (false, 500),
+    ]);
+    assert_eq!(kinds(&presses), [Press::Short]);
+    // Reported once the window for a second press has closed.
+    let released = 50 + 20 + 120 + 10;
+    assert!(presses[0].0 >= released + DOUBLE_PRESS_MS, "{:?}", presses);
+}
+
+#[test]
+fn glitches_shorter_than_the_debounce_are_ignored() {
+    let presses = play(&[
+        (false, 50),
+        (true, 10),
+        (false, 200),
+        (true, 15),
+        (false, 500),
+    ]);
+    assert_eq!(presses, []);
+}
+
+#[test]
+fn two_quick_taps_are_a_double_press() {
+    let presses = play(&[
+        (false, 50),
+        (true, 100),
+        (false, 150),
+        (true, 100),
+        (false, 500),
+    ]);
+    assert_eq!(kinds(&presses), [Press::Double]);
+}
+
+#[test]
+fn taps_further_apart_are_two_short_presses() {
+    let presses = play(&[
+        (false, 50),
+        (true, 100),
+        (false, DOUBLE_PRESS_MS + 100),
+        (true, 100),
+        (false, 500),
+    ]);
+    assert_eq!(kinds(&presses), [Press::Short, Press::Short]);
+}
+
+#[test]
+fn holding_is_one_long_press_while_still_held() {
+    let presses = play(&[(false, 50), (true, LONG_PRESS_MS + 500), (false, 500)]);
+    assert_eq!(kinds(&presses), [Press::Long]);
+    assert!(presses[0].0 < 50 + LONG_PRESS_MS + 500, "{:?}", presses);
+
+    // A tap followed by a hold is still just the hold.
+    let presses = play(&[
+        (false, 50),
+        (true, 100),
+        (false, 100),
+        (true, LONG_PRESS_MS + 100),
+        (false, 500),
+    ]);
+    assert_eq!(kinds(&presses), [Press::Long]);
+}
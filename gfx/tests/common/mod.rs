@@ -0,0 +1,49 @@
+//! Fixtures shared by the tests and the bench, which each use some of them.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use deej_gfx::assets::{self, Sheet};
+use deej_gfx::frame::Gray4Frame;
+use deej_gfx::sheet::SpriteSheet;
+use deej_gfx::theme::{Theme, THEME_DEFS};
+
+/// The SSD1322 panel the firmware draws on by default.
+pub const W: usize = 256;
+pub const H: usize = 64;
+pub const N: usize = W * H / 2;
+
+pub type Frame = Gray4Frame<N>;
+
+/// The sheets of `assets/sprites.toml`, as `build.rs` converts them.
+pub fn converted() -> Vec<Sheet> {
+    assets::convert_manifest(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets")).unwrap()
+}
+
+pub fn sheets() -> Vec<SpriteSheet> {
+    converted().iter().map(Sheet::leak).collect()
+}
+
+pub fn sheets_by_name() -> HashMap<String, &'static SpriteSheet> {
+    converted()
+        .into_iter()
+        .map(|s| (s.name.clone(), &*Box::leak(Box::new(s.leak()))))
+        .collect()
+}
+
+/// The firmware's themes, built like `src/themes.rs` builds them.
+pub fn themes() -> Vec<Theme> {
+    let sheets: Vec<(&str, &'static SpriteSheet)> = sheets_by_name()
+        .into_iter()
+        .map(|(name, sheet)| (&*name.leak(), sheet))
+        .collect();
+    THEME_DEFS.iter().map(|def| def.build(&sheets)).collect()
+}
+
+pub fn theme(name: &str) -> Theme {
+    themes()
+        .into_iter()
+        .find(|t| t.name == name)
+        .unwrap_or_else(|| panic!("no theme {}", name))
+}
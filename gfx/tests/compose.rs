@@ -0,0 +1,135 @@
+use deej_gfx::blit::blit_sheet_frame_masked;
+use deej_gfx::dirty::Rect;
+use deej_gfx::gray4::compose::{compose, Blend, Layer, Source};
+use embedded_graphics::prelude::*;
+
+mod common;
+
+use common::{Frame, H, W};
+
+fn frame_filled(level: u8) -> Box<Frame> {
+    let mut frame = Box::new(Frame::new(W, H));
+    frame.as_bytes_mut().fill((level << 4) | level);
+    frame
+}
+
+fn fill(level: u8, w: u32, h: u32) -> Source<'static> {
+    Source::Fill { level, w, h }
+}
+
+#[test]
+fn blend_modes() {
+    let cases = [
+        (Blend::Replace, 9, 4, 9),
+        (Blend::Add, 9, 4, 13),
+        (Blend::Add, 9, 10, 15),
+        (Blend::Max, 3, 8, 8),
+        (Blend::Max, 12, 8, 12),
+        (Blend::Multiply, 15, 8, 8),
+        (Blend::Multiply, 0, 8, 0),
+        (Blend::Multiply, 10, 12, 8),
+    ];
+    for (blend, s, d, want) in cases {
+        let mut frame = frame_filled(d);
+        let mut layer = Layer::new(fill(s, 4, 4));
+        layer.blend = blend;
+        layer.keyed = false;
+        compose(&mut frame, &[layer]);
+        assert_eq!(frame.get(2, 2), want, "{:?} {} over {}", blend, s, d);
+        assert_eq!(frame.get(4, 2), d, "outside the layer");
+    }
+}
+
+#[test]
+fn opacity_mixes_with_what_is_under() {
+    let mut frame = frame_filled(0);
+    let mut layer = Layer::new(fill(15, 2, 1));
+    for (opacity, want) in [(0, 0), (5, 5), (8, 8), (15, 15)] {
+        frame.as_bytes_mut().fill(0);
+        layer.opacity = opacity;
+        compose(&mut frame, &[layer]);
+        assert_eq!(frame.get(0, 0), want, "opacity {}", opacity);
+    }
+}
+
+#[test]
+fn nibble_zero_is_transparent_only_when_keyed() {
+    // A 2x1 image, white then black.
+    let data = [0xF0];
+    let image = Source::Image {
+        data: &data,
+        w: 2,
+        h: 1,
+    };
+
+    let mut frame = frame_filled(6);
+    compose(&mut frame, &[Layer::new(image)]);
+    assert_eq!((frame.get(0, 0), frame.get(1, 0)), (15, 6));
+
+    let mut layer = Layer::new(image);
+    layer.keyed = false;
+    compose(&mut frame, &[layer]);
+    assert_eq!((frame.get(0, 0), frame.get(1, 0)), (15, 0));
+}
+
+#[test]
+fn higher_z_goes_on_top_and_ties_keep_their_order() {
+    let mut low = Layer::new(fill(3, 8, 8));
+    low.z = -1;
+    let first = Layer::new(fill(7, 8, 8));
+    let second = Layer::new(fill(9, 8, 8));
+    let mut high = Layer::new(fill(11, 4, 4));
+    high.z = 5;
+
+    let mut frame = frame_filled(0);
+    compose(&mut frame, &[high, second, low, first]);
+    assert_eq!(frame.get(1, 1), 11);
+    // `second` comes before `first` in the slice, so `first` wins.
+    assert_eq!(frame.get(6, 6), 7);
+}
+
+#[test]
+fn offset_and_clip_limit_the_layer() {
+    let mut frame = frame_filled(0);
+    let mut layer = Layer::new(fill(5, 40, 40));
+    layer.offset = Point::new(-10, 30);
+    layer.clip = Some(Rect::new(0, 0, 20, 50));
+    compose(&mut frame, &[layer]);
+
+    for y in 0..H {
+        for x in 0..W {
+            let want = if x < 20 && (30..50).contains(&y) {
+                5
+            } else {
+                0
+            };
+            assert_eq!(frame.get(x, y), want, "({}, {})", x, y);
+        }
+    }
+    assert_eq!(frame.dirty().as_slice(), &[Rect::new(0, 30, 20, 50)]);
+}
+
+#[test]
+fn sheet_layer_matches_the_masked_blitter() {
+    for s in common::converted() {
+        let sheet = s.leak();
+        for pos in [Point::new(15, 0), Point::new(-7, -3), Point::new(230, 41)] {
+            let idx = sheet.frames - 1;
+
+            let mut expected = frame_filled(2);
+            blit_sheet_frame_masked(&mut expected, &sheet, idx, pos);
+
+            let mut actual = frame_filled(2);
+            let mut layer = Layer::new(Source::Sheet { sheet: &sheet, idx });
+            layer.offset = pos;
+            compose(&mut actual, &[layer]);
+
+            assert!(
+                expected.as_bytes() == actual.as_bytes(),
+                "{} at {:?}",
+                s.name,
+                pos
+            );
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use deej_gfx::config::{ChannelConfig, Config, TargetConfig, UsbStrings, MAX_BYTES, SCHEMA};
+use heapless::String;
+
+fn defaults() -> Config {
+    Config {
+        theme: String::try_from("muffet").unwrap(),
+        channels: [ChannelConfig {
+            invert: true,
+            min: 0,
+            max: 1023,
+        }; 5],
+        noise_threshold: 15,
+        active_channel_ttl_ms: 1000,
+        frame_period_ms: 40,
+        usb: UsbStrings {
+            manufacturer: String::try_from("kareraisu.me").unwrap(),
+            product: String::try_from("deej OLED").unwrap(),
+            serial_number: String::try_from("oledassfart").unwrap(),
+        },
+        targets: [
+            target("master", "System", "logo_system"),
+            target("mic", "Mic", "logo_mic"),
+            target("chrome.exe", "Browser", "logo_browser"),
+            target("steam.exe", "Steam", "logo_steam"),
+            target("spotify.exe", "Spotify", "logo_spotify"),
+        ],
+    }
+}
+
+fn target(id: &str, name: &str, icon: &str) -> TargetConfig {
+    TargetConfig {
+        id: String::try_from(id).unwrap(),
+        name: String::try_from(name).unwrap(),
+        icon: String::try_from(icon).unwrap(),
+    }
+}
+
+/// The settings record older builds wrote.
+fn v1_record(theme: &str) -> Vec<u8> {
+    let mut bytes = 0xDEE1_0001u32.to_le_bytes().to_vec();
+    let mut name = [0u8; 16];
+    name[..theme.len()].copy_from_slice(theme.as_bytes());
+    bytes.extend_from_slice(&name);
+    bytes
+}
+
+#[test]
+fn configs_read_back_as_written() {
+    let mut config = defaults();
+    config.theme = String::try_from("cobweb").unwrap();
+    config.channels[3] = ChannelConfig {
+        invert: false,
+        min: 12,
+        max: 1001,
+    };
+    config.noise_threshold = 8;
+
+    let mut buf = [0; MAX_BYTES];
+    let bytes = config.encode(&mut buf);
+    assert_eq!(bytes[0], SCHEMA);
+    assert_eq!(Config::decode(bytes, &defaults()), Some(config));
+}
+
+#[test]
+fn the_checksum_follows_the_contents() {
+    let config = defaults();
+    assert_eq!(config.checksum(), defaults().checksum());
+
+    let mut changed = defaults();
+    changed.frame_period_ms += 1;
+    assert_ne!(changed.checksum(), config.checksum());
+}
+
+#[test]
+fn the_largest_config_fits() {
+    let full = |n: usize| "x".repeat(n);
+    let mut config = defaults();
+    config.theme = String::try_from(full(16).as_str()).unwrap();
+    config.channels = [ChannelConfig {
+        invert: true,
+        min: u16::MAX,
+        max: u16::MAX,
+    }; 5];
+    config.noise_threshold = u16::MAX;
+    config.active_channel_ttl_ms = u32::MAX;
+    config.frame_period_ms = u32::MAX;
+    config.usb = UsbStrings {
+        manufacturer: String::try_from(full(32).as_str()).unwrap(),
+        product: String::try_from(full(32).as_str()).unwrap(),
+        serial_number: String::try_from(full(16).as_str()).unwrap(),
+    };
+    config.targets = core::array::from_fn(|_| target(&full(48), &full(16), &full(16)));
+
+    let mut buf = [0; MAX_BYTES];
+    let bytes = config.encode(&mut buf).to_vec();
+    assert_eq!(Config::decode(&bytes, &defaults()), Some(config));
+}
+
+#[test]
+fn the_old_settings_record_migrates() {
+    let mut expected = defaults();
+    expected.theme = String::try_from("cobweb").unwrap();
+    assert_eq!(
+        Config::decode(&v1_record("cobweb"), &defaults()),
+        Some(expected)
+    );
+
+    // A name filling the whole field has no terminator.
+    let config = Config::decode(&v1_record("sixteen-letters!"), &defaults()).unwrap();
+    assert_eq!(config.theme, "sixteen-letters!");
+}
+
+#[test]
+fn schema_2_configs_get_the_default_targets() {
+    let mut config = defaults();
+    config.theme = String::try_from("cobweb").unwrap();
+    config.noise_threshold = 8;
+
+    // Schema 2 is schema 3 without the targets at the end; empty ones take
+    // a length byte each.
+    let mut stripped = config.clone();
+    stripped.targets = core::array::from_fn(|_| target("", "", ""));
+    let mut buf = [0; MAX_BYTES];
+    let bytes = stripped.encode(&mut buf);
+    let mut v2 = bytes[..bytes.len() - 3 * 5].to_vec();
+    v2[0] = 2;
+
+    assert_eq!(Config::decode(&v2, &defaults()), Some(config));
+}
+
+#[test]
+fn slider_mapping_lists_the_targets_for_deej() {
+    let mut config = defaults();
+    config.targets[2].id = String::try_from("chrome.exe, firefox.exe").unwrap();
+    config.targets[3].id = String::new();
+
+    let mut lines = Vec::new();
+    config.slider_mapping(|line| lines.push(line.to_string()));
+    assert_eq!(
+        lines,
+        [
+            "slider_mapping:",
+            "  0: master",
+            "  1: mic",
+            "  2:",
+            "    - chrome.exe",
+            "    - firefox.exe",
+            "  4: spotify.exe",
+        ]
+    );
+}
+
+#[test]
+fn what_does_not_decode_is_none() {
+    let mut buf = [0; MAX_BYTES];
+    let good = defaults().encode(&mut buf).to_vec();
+
+    // Cut short, from a newer build, empty.
+    assert_eq!(Config::decode(&good[..good.len() - 5], &defaults()), None);
+    let mut newer = good.clone();
+    newer[0] = SCHEMA + 1;
+    assert_eq!(Config::decode(&newer, &defaults()), None);
+    assert_eq!(Config::decode(&[], &defaults()), None);
+
+    // A version 1 record without the rest of its magic, or cut short.
+    let mut record = v1_record("muffet");
+    record[2] = 0;
+    assert_eq!(Config::decode(&record, &defaults()), None);
+    assert_eq!(
+        Config::decode(&v1_record("muffet")[..10], &defaults()),
+        None
+    );
+}
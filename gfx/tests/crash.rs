@@ -0,0 +1,86 @@
+use deej_gfx::crash::{CrashKind, CrashRecord, FILE_BYTES, MESSAGE_BYTES};
+use deej_gfx::frame::Gray4Frame;
+
+const REGISTERS: [u32; 8] = [1, 2, 3, 4, 12, 0x1000_0abd, 0x1000_1234, 0x6100_0000];
+
+/// Text lines with something on them; the font is 6 pixels tall.
+fn lines<const N: usize>(frame: &Gray4Frame<N>) -> usize {
+    (0..frame.height() / 6)
+        .filter(|&l| (l * 6..l * 6 + 6).any(|y| (0..frame.width()).any(|x| frame.get(x, y) != 0)))
+        .count()
+}
+
+#[test]
+fn records_survive_flash() {
+    let panic = CrashRecord::panic("src/adc.rs", 42, format_args!("index {} out of range", 9));
+    let fault = CrashRecord::hard_fault(REGISTERS);
+    let watchdog = CrashRecord::watchdog("adc_task");
+
+    for record in [panic, fault, watchdog] {
+        assert_eq!(CrashRecord::from_bytes(&record.to_bytes()), Some(record));
+    }
+    assert_eq!(panic.message(), "index 9 out of range");
+    assert_eq!(panic.registers, None);
+    assert_eq!(fault.kind, CrashKind::HardFault);
+    assert_eq!(fault.registers, Some(REGISTERS));
+    assert_eq!(watchdog.kind, CrashKind::Watchdog);
+    assert_eq!(watchdog.message(), "adc_task");
+}
+
+#[test]
+fn erased_or_torn_flash_is_no_record() {
+    assert_eq!(CrashRecord::from_bytes(&[0xFF; CrashRecord::BYTES]), None);
+
+    let mut bytes = CrashRecord::panic("src/main.rs", 1, "boom").to_bytes();
+    bytes[7] = 0xFF;
+    assert_eq!(CrashRecord::from_bytes(&bytes), None);
+}
+
+#[test]
+fn long_text_is_cut_on_char_boundaries() {
+    let file = format!("{}/src/graphics.rs", "é".repeat(40));
+    let message = "ü".repeat(MESSAGE_BYTES);
+    let record = CrashRecord::panic(&file, 7, &message);
+
+    assert!(record.file().len() <= FILE_BYTES);
+    assert!(record.file().ends_with("/src/graphics.rs"));
+    assert!(record.message().len() <= MESSAGE_BYTES);
+    assert!(message.starts_with(record.message()));
+    assert_eq!(CrashRecord::from_bytes(&record.to_bytes()), Some(record));
+}
+
+#[test]
+fn log_line_has_location_and_registers() {
+    let panic = CrashRecord::panic("src/adc.rs", 42, "boom");
+    assert_eq!(panic.to_string(), "panic at src/adc.rs:42: boom");
+
+    let watchdog = CrashRecord::watchdog("render_task");
+    assert_eq!(watchdog.to_string(), "watchdog reset: render_task stalled");
+
+    let fault = CrashRecord::hard_fault(REGISTERS).to_string();
+    assert!(fault.starts_with("hard fault r0=0x00000001"), "{}", fault);
+    assert!(fault.contains(" pc=0x10001234 "), "{}", fault);
+}
+
+#[test]
+fn crash_screen_fits_every_panel() {
+    let message = "x".repeat(MESSAGE_BYTES);
+    let panic = CrashRecord::panic("src/screen.rs", 99, &message);
+    let fault = CrashRecord::hard_fault(REGISTERS);
+
+    let mut wide = Box::new(Gray4Frame::<{ 256 * 64 / 2 }>::new(256, 64));
+    panic.draw(&mut wide);
+    // Title, location and 160 characters at 64 a line.
+    assert_eq!(lines(&wide), 5);
+    fault.draw(&mut wide);
+    // Title and two lines of four registers.
+    assert_eq!(lines(&wide), 3);
+
+    let mut small = Box::new(Gray4Frame::<{ 128 * 64 / 2 }>::new(128, 64));
+    fault.draw(&mut small);
+    // Title and four lines of two registers.
+    assert_eq!(lines(&small), 5);
+    // Title, location and 160 characters at 32 a line.
+    panic.draw(&mut small);
+    assert_eq!(lines(&small), 7);
+}
@@ -0,0 +1,90 @@
+use deej_gfx::diagnostics::{draw_readout, draw_test_pattern, NoiseStats};
+use deej_gfx::frame::Gray4Frame;
+
+#[test]
+fn noise_stats_of_a_fader_at_rest() {
+    let mut stats = NoiseStats::new();
+    assert_eq!(stats.last(), None);
+    assert_eq!(stats.std_dev(), None);
+
+    for raw in [510, 512, 514, 512, 510, 514] {
+        stats.add(Some(raw));
+    }
+    stats.add(None);
+
+    assert_eq!(stats.reads(), 6);
+    assert_eq!(stats.errors(), 1);
+    assert_eq!(stats.last(), Some(514));
+    assert_eq!(stats.range(), Some((510, 514)));
+    assert_eq!(stats.mean(), Some(512.0));
+    // Squared deviations 4, 0, 4, 0, 4, 4 over 6.
+    let sd = stats.std_dev().unwrap();
+    assert!((sd - (16.0f32 / 6.0).sqrt()).abs() < 1e-4, "{}", sd);
+
+    assert_eq!(
+        stats.to_string(),
+        "raw 514 min 510 max 514 mean 512.0 sd 1.6, 6 reads, 1 errors"
+    );
+}
+
+#[test]
+fn a_steady_channel_has_no_noise() {
+    let mut stats = NoiseStats::new();
+    for _ in 0..1000 {
+        stats.add(Some(1023));
+    }
+    assert_eq!(stats.std_dev(), Some(0.0));
+
+    let mut failing = NoiseStats::new();
+    failing.add(None);
+    assert_eq!(failing.to_string(), "no readings, 0 reads, 1 errors");
+}
+
+#[test]
+fn test_pattern_shows_every_level_and_the_edges() {
+    let mut frame = Box::new(Gray4Frame::<{ 256 * 64 / 2 }>::new(256, 64));
+    draw_test_pattern(&mut frame);
+
+    let strip: Vec<u8> = (1..255).map(|x| frame.get(x, 10)).collect();
+    for level in 0..16 {
+        assert!(strip.contains(&level), "level {}", level);
+    }
+    for x in 0..256 {
+        assert_eq!((frame.get(x, 0), frame.get(x, 63)), (15, 15));
+    }
+    // Neighbours of the checkerboard differ.
+    assert_ne!(frame.get(10, 40), frame.get(11, 40));
+    assert_ne!(frame.get(10, 40), frame.get(10, 41));
+}
+
+#[test]
+fn readout_has_a_line_per_channel() {
+    let mut channels = [NoiseStats::new(); 5];
+    for (i, stats) in channels.iter_mut().enumerate() {
+        stats.add(Some(200 * i as u16));
+    }
+    channels[4].add(None);
+
+    let mut wide = Box::new(Gray4Frame::<{ 256 * 64 / 2 }>::new(256, 64));
+    draw_readout(&mut wide, format_args!("diagnostics"), &channels);
+    let lit = |f: &Gray4Frame<{ 256 * 64 / 2 }>, x0: usize, y: usize| {
+        (x0..f.width()).any(|x| (y..y + 6).any(|y| f.get(x, y) != 0))
+    };
+    for line in 0..6 {
+        assert!(lit(&wide, 0, line * 6), "line {}", line);
+    }
+    // Bars right of the text, longer for higher readings; none for 0.
+    let bar = |f: &Gray4Frame<{ 256 * 64 / 2 }>, ch: usize| {
+        (112..256)
+            .filter(|&x| f.get(x, (ch + 1) * 6 + 2) != 0)
+            .count()
+    };
+    assert_eq!(bar(&wide, 0), 0);
+    assert!(bar(&wide, 1) > 0);
+    assert!(bar(&wide, 4) > 3 * bar(&wide, 1));
+
+    // No room for bars on a narrow panel, but the text still fits.
+    let mut narrow = Box::new(Gray4Frame::<{ 128 * 64 / 2 }>::new(128, 64));
+    draw_readout(&mut narrow, format_args!("diagnostics"), &channels);
+    assert!((0..128).all(|x| (0..64).all(|y| x < 112 || narrow.get(x, y) == 0)));
+}
@@ -0,0 +1,116 @@
+use deej_gfx::blit::{blit_image, blit_sheet_frame_masked, blit_sheet_frame_masked_crt};
+use deej_gfx::dirty::{DirtyRects, Rect, MAX_DIRTY_RECTS};
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+
+mod common;
+
+use common::{sheets, Frame, H, W};
+
+fn covered(dirty: &DirtyRects, x: usize, y: usize) -> bool {
+    dirty.as_slice().iter().any(|r| {
+        (r.x0 as usize..r.x1 as usize).contains(&x) && (r.y0 as usize..r.y1 as usize).contains(&y)
+    })
+}
+
+/// Copies the `dirty` windows of `frame` into `panel`, 4-pixel aligned like
+/// the SSD1322 column addresses.
+fn apply(panel: &mut Frame, frame: &Frame, dirty: &DirtyRects) {
+    for r in dirty.as_slice() {
+        let r = r.align_x(4, W as u16);
+        for y in r.y0 as usize..r.y1 as usize {
+            for x in r.x0 as usize..r.x1 as usize {
+                panel.set(x, y, frame.get(x, y));
+            }
+        }
+    }
+}
+
+#[test]
+fn merges_touching_and_caps_count() {
+    let mut d = DirtyRects::new();
+    d.add(Rect::new(0, 0, 10, 10));
+    d.add(Rect::new(10, 0, 20, 10));
+    assert_eq!(d.as_slice(), &[Rect::new(0, 0, 20, 10)]);
+
+    d.add(Rect::new(5, 5, 5, 30));
+    assert_eq!(d.as_slice().len(), 1, "empty rects are ignored");
+
+    for i in 0..10u16 {
+        d.add(Rect::new(30 + i * 20, 20, 35 + i * 20, 25));
+        assert!(d.as_slice().len() <= MAX_DIRTY_RECTS);
+    }
+    for (i, a) in d.as_slice().iter().enumerate() {
+        for b in &d.as_slice()[i + 1..] {
+            assert!(!a.touches(b), "{:?} touches {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn clear_resets_dirty_set() {
+    let mut f = Frame::new(W, H);
+    f.mark_dirty(-5, -5, 20, 20);
+    assert_eq!(f.dirty().as_slice(), &[Rect::new(0, 0, 15, 15)]);
+
+    f.clear(Gray4::BLACK).unwrap();
+    assert!(f.dirty().is_empty());
+
+    f.clear(Gray4::WHITE).unwrap();
+    assert_eq!(f.dirty().area(), (W * H) as u32);
+}
+
+#[test]
+fn dirty_set_covers_every_drawn_pixel() {
+    let icon = vec![0xFFu8; 62 * 31];
+
+    for sheet in sheets() {
+        for (i, pos) in [Point::new(3, -7), Point::new(101, 20), Point::new(-9, 40)]
+            .into_iter()
+            .enumerate()
+        {
+            let mut f = Frame::new(W, H);
+            blit_sheet_frame_masked_crt(&mut f, &sheet, 0, pos, i as u8, true);
+            blit_image(&mut f, &icon, 62, 62, Point::new(197, 1));
+            Rectangle::new(Point::new(150, 50), Size::new(3, 2))
+                .into_styled(PrimitiveStyle::with_fill(Gray4::WHITE))
+                .draw(&mut f)
+                .unwrap();
+
+            for y in 0..H {
+                for x in 0..W {
+                    if f.get(x, y) != 0 {
+                        assert!(covered(f.dirty(), x, y), "({}, {}) not dirty", x, y);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn partial_updates_match_full_frames() {
+    let sheets = sheets();
+    let mut panel = Frame::new(W, H);
+    let mut shown = DirtyRects::new();
+
+    for tick in 0..60usize {
+        let mut f = Frame::new(W, H);
+        for (i, sheet) in sheets.iter().enumerate() {
+            let pos = Point::new((tick * 5 + i * 37) as i32 % 300 - 30, (i * 11) as i32 - 10);
+            blit_sheet_frame_masked(&mut f, sheet, tick % sheet.frames, pos);
+        }
+
+        let mut region = shown;
+        region.extend(f.dirty());
+        apply(&mut panel, &f, &region);
+        shown = *f.dirty();
+
+        assert!(
+            panel.as_bytes() == f.as_bytes(),
+            "panel differs at tick {}",
+            tick
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use deej_gfx::dither::{Dither, Gamma, RowDither};
+
+const W: usize = 64;
+const H: usize = 32;
+
+const MODES: [Dither; 3] = [Dither::None, Dither::Ordered, Dither::FloydSteinberg];
+
+/// Converts a `W` x `H` image row by row, back to 8-bit levels.
+fn convert(mode: Dither, gamma: Option<Gamma>, pixel: impl Fn(usize, usize) -> u8) -> Vec<u32> {
+    let mut rows = RowDither::new(mode, W);
+    if let Some(gamma) = gamma {
+        rows = rows.with_gamma(gamma);
+    }
+
+    let mut out = Vec::with_capacity(W * H);
+    let mut levels = [0u8; W];
+    for y in 0..H {
+        let src: Vec<u8> = (0..W).map(|x| pixel(x, y)).collect();
+        rows.next_row(&src, &mut levels);
+        out.extend(levels.iter().map(|&l| {
+            assert!(l <= 15);
+            l as u32 * 17
+        }));
+    }
+    out
+}
+
+/// Worst difference between a flat patch of every grey and its average once
+/// converted.
+fn worst_average_error(mode: Dither) -> f64 {
+    (0..=255u8)
+        .map(|v| {
+            let out = convert(mode, None, |_, _| v);
+            let mean = out.iter().sum::<u32>() as f64 / out.len() as f64;
+            (mean - v as f64).abs()
+        })
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn dithering_keeps_the_average_level() {
+    // Plain rounding is up to half a band (8.5 levels) off; the dithers
+    // get within a level or two.
+    let none = worst_average_error(Dither::None);
+    let ordered = worst_average_error(Dither::Ordered);
+    let diffused = worst_average_error(Dither::FloydSteinberg);
+    assert!((8.0..=8.5).contains(&none), "none: {}", none);
+    assert!(ordered <= 1.5, "ordered: {}", ordered);
+    assert!(diffused <= 1.5, "floyd-steinberg: {}", diffused);
+}
+
+#[test]
+fn dithered_gradient_has_no_bands() {
+    // A shallow horizontal ramp, a level per column, averaged over 4 column
+    // wide strips: rounding turns it into steps 17 levels high, the dithers
+    // follow the ramp.
+    let ramp = |x: usize, _| (96 + x) as u8;
+    let worst_strip_error = |mode| {
+        let out = &convert(mode, None, ramp);
+        (0..W)
+            .step_by(4)
+            .map(|x0| {
+                let strip = x0..x0 + 4;
+                let got = (0..H)
+                    .flat_map(|y| strip.clone().map(move |x| out[y * W + x]))
+                    .sum::<u32>() as f64
+                    / (4 * H) as f64;
+                let want = strip.clone().map(|x| ramp(x, 0) as f64).sum::<f64>() / 4.0;
+                (got - want).abs()
+            })
+            .fold(0.0, f64::max)
+    };
+
+    assert!(worst_strip_error(Dither::None) > 6.0);
+    for mode in [Dither::Ordered, Dither::FloydSteinberg] {
+        let error = worst_strip_error(mode);
+        assert!(error <= 2.5, "{:?}: {}", mode, error);
+    }
+}
+
+#[test]
+fn exact_levels_round_trip() {
+    for mode in MODES {
+        for level in 0..16u8 {
+            let out = convert(mode, None, |_, _| level * 17);
+            assert!(
+                out.iter().all(|&v| v == level as u32 * 17),
+                "{:?} level {}",
+                mode,
+                level
+            );
+        }
+    }
+}
+
+#[test]
+fn gamma_bends_the_midtones_only() {
+    let linear = Gamma::LINEAR;
+    assert!((0..=255u8).all(|v| linear.apply(v) == v));
+
+    let one = Gamma::new(1.0);
+    assert!((0..=255u8).all(|v| one.apply(v).abs_diff(v) <= 1));
+
+    let dark = Gamma::new(2.2);
+    let light = Gamma::new(1.0 / 2.2);
+    for g in [&dark, &light] {
+        assert_eq!((g.apply(0), g.apply(255)), (0, 255));
+        assert!((1..=255u8).all(|v| g.apply(v) >= g.apply(v - 1)));
+    }
+    // 0.5 ^ 2.2 and 0.5 ^ (1 / 2.2) of full scale.
+    assert!(dark.apply(128).abs_diff(56) <= 2, "{}", dark.apply(128));
+    assert!(light.apply(128).abs_diff(186) <= 2, "{}", light.apply(128));
+
+    // Applied before quantizing.
+    let out = convert(Dither::None, Some(Gamma::new(2.2)), |_, _| 128);
+    assert!(out.iter().all(|&v| v == 3 * 17));
+}
+
+#[test]
+fn packed_rows_put_the_left_pixel_high() {
+    let src = [0u8, 255, 136, 17, 255];
+    let mut dst = [0u8; 3];
+    RowDither::new(Dither::None, src.len()).next_row_packed(&src, &mut dst);
+    assert_eq!(dst, [0x0F, 0x81, 0xF0]);
+}
@@ -0,0 +1,87 @@
+use deej_gfx::encoder::{Acceleration, EncoderValue, Quadrature, MAX_VALUE};
+
+/// `(a, b)` through one detent clockwise, from rest to rest.
+const CLOCKWISE: [(bool, bool); 4] = [(false, true), (false, false), (true, false), (true, true)];
+
+fn feed(q: &mut Quadrature, levels: impl IntoIterator<Item = (bool, bool)>) -> Vec<i32> {
+    levels
+        .into_iter()
+        .map(|(a, b)| q.update(a, b))
+        .filter(|&d| d != 0)
+        .collect()
+}
+
+#[test]
+fn full_cycles_are_detents_either_way() {
+    let mut q = Quadrature::new();
+    assert_eq!(feed(&mut q, CLOCKWISE.repeat(3)), [1, 1, 1]);
+
+    let anticlockwise = CLOCKWISE.iter().rev().skip(1).chain([&(true, true)]);
+    assert_eq!(feed(&mut q, anticlockwise.copied()), [-1]);
+}
+
+#[test]
+fn bounce_and_missed_edges() {
+    let mut q = Quadrature::new();
+    // Bouncing on the first edge and falling back to rest: no detent.
+    let bounce = [(false, true), (true, true), (false, true), (true, true)];
+    assert_eq!(feed(&mut q, bounce), []);
+
+    // Chatter mid-way still ends as one detent.
+    let chatter = [
+        (false, true),
+        (false, false),
+        (false, true),
+        (false, false),
+        (true, false),
+        (true, true),
+    ];
+    assert_eq!(feed(&mut q, chatter), [1]);
+
+    // An edge lost between two samples.
+    let skipped = [(false, true), (true, false), (true, true)];
+    assert_eq!(feed(&mut q, skipped), [1]);
+}
+
+#[test]
+fn slow_turns_move_one_step_per_detent() {
+    let accel = Acceleration {
+        window_ms: 100,
+        max_factor: 8,
+    };
+    let mut v = EncoderValue::new(500, 10, accel);
+    for i in 1..=5 {
+        assert_eq!(v.turn(1, i * 200), 500 + 10 * i as u16);
+    }
+    assert_eq!(v.turn(-1, 1200), 540);
+}
+
+#[test]
+fn quick_turns_accelerate_up_to_the_limit() {
+    let accel = Acceleration {
+        window_ms: 100,
+        max_factor: 8,
+    };
+    let mut v = EncoderValue::new(0, 4, accel);
+    v.turn(1, 0);
+    // 50 ms apart: twice the step.
+    assert_eq!(v.turn(1, 50), 4 + 8);
+    // 5 ms apart: capped at eight times.
+    assert_eq!(v.turn(1, 55), 12 + 32);
+    // Turning back starts over.
+    assert_eq!(v.turn(-1, 60), 40);
+
+    let mut plain = EncoderValue::new(0, 4, Acceleration::NONE);
+    plain.turn(1, 0);
+    assert_eq!(plain.turn(1, 1), 8);
+}
+
+#[test]
+fn position_stays_on_the_fader_scale() {
+    let mut v = EncoderValue::new(1000, 64, Acceleration::NONE);
+    assert_eq!(v.turn(1, 0), MAX_VALUE);
+    assert_eq!(v.turn(1, 1000), MAX_VALUE);
+
+    let mut v = EncoderValue::new(10, 64, Acceleration::NONE);
+    assert_eq!(v.turn(-1, 0), 0);
+}
@@ -0,0 +1,76 @@
+use deej_gfx::events::{Event, HostState, ScreenFlow};
+use deej_gfx::scene::ScreenState;
+
+fn flow(events: &[Event]) -> ScreenFlow {
+    let mut flow = ScreenFlow::new();
+    for event in events {
+        flow.handle(event);
+    }
+    flow
+}
+
+fn active(channel: Option<usize>, input: bool) -> Event {
+    Event::ActiveChannelChanged { channel, input }
+}
+
+const ACTIVE: Event = Event::HostStateChanged(HostState::Active);
+const SUSPENDED: Event = Event::HostStateChanged(HostState::Suspended);
+
+#[test]
+fn the_host_starts_the_intro_and_the_outro() {
+    let mut f = flow(&[]);
+    assert_eq!(f.state(), ScreenState::OFF);
+
+    f.handle(&ACTIVE);
+    assert_eq!(f.state(), ScreenState::INTRO);
+    assert_eq!(
+        f.advance(ScreenState::STANDBY),
+        Some(Event::SceneFinished(ScreenState::INTRO))
+    );
+    assert_eq!(f.advance(ScreenState::STANDBY), None);
+
+    f.handle(&SUSPENDED);
+    assert_eq!(f.state(), ScreenState::OUTRO);
+    assert_eq!(
+        f.advance(ScreenState::OFF),
+        Some(Event::SceneFinished(ScreenState::OUTRO))
+    );
+    // Suspending again does not replay the outro.
+    f.handle(&SUSPENDED);
+    assert_eq!(f.state(), ScreenState::OFF);
+}
+
+#[test]
+fn the_active_channel_screen_follows_the_faders() {
+    let mut f = flow(&[ACTIVE]);
+    f.set_faders([0, 0, 300, 0, 0]);
+    f.advance(ScreenState::STANDBY);
+
+    f.handle(&active(Some(2), true));
+    assert_eq!(f.state(), ScreenState::ACTIVE);
+    assert_eq!(f.active(), Some((2, 300, true)));
+
+    f.set_faders([0, 0, 310, 0, 0]);
+    f.handle(&active(Some(2), false));
+    assert_eq!(f.active(), Some((2, 310, false)));
+
+    f.handle(&active(None, false));
+    assert_eq!(f.state(), ScreenState::STANDBY);
+    assert_eq!(f.active(), None);
+}
+
+#[test]
+fn faders_cut_the_intro_short_but_not_the_outro() {
+    let f = flow(&[ACTIVE, active(Some(0), true)]);
+    assert_eq!(f.state(), ScreenState::ACTIVE);
+
+    let f = flow(&[ACTIVE, SUSPENDED, active(Some(0), true)]);
+    assert_eq!(f.state(), ScreenState::OUTRO);
+
+    // A channel still up when the intro ends goes straight to its screen.
+    let mut f = flow(&[ACTIVE]);
+    f.handle(&active(Some(1), false));
+    f.restart();
+    f.advance(ScreenState::STANDBY);
+    assert_eq!(f.state(), ScreenState::ACTIVE);
+}
@@ -0,0 +1,170 @@
+use deej_gfx::gesture::{
+    Gesture, GestureAction, GestureBindings, GestureDetector, DOUBLE_FLICK_MS, HOLD_MAX_MS,
+};
+
+/// Loop period of the firmware's `adc_task`.
+const SAMPLE_MS: u32 = 50;
+
+/// Plays a trace starting at `start`, each step a linear move to a position
+/// over some milliseconds, sampled every `SAMPLE_MS`. Returns every gesture
+/// with the time it was reported.
+fn play(start: u32, trace: &[(u32, u32)]) -> Vec<(u32, Gesture)> {
+    let mut detector = GestureDetector::new();
+    let mut gestures = Vec::new();
+    let mut now = 0;
+    let mut from = start;
+    for &(to, ms) in trace {
+        let samples = ms / SAMPLE_MS;
+        for n in 1..=samples {
+            let value = from as i32 + (to as i32 - from as i32) * n as i32 / samples as i32;
+            if let Some(gesture) = detector.update(now, value as u32) {
+                gestures.push((now, gesture));
+            }
+            now += SAMPLE_MS;
+        }
+        from = to;
+    }
+    gestures
+}
+
+fn kinds(gestures: &[(u32, Gesture)]) -> Vec<Gesture> {
+    gestures.iter().map(|&(_, g)| g).collect()
+}
+
+/// Down to the bottom in 100 ms, a moment there and back up in 150 ms.
+fn flick(at: u32) -> [(u32, u32); 3] {
+    [(0, 100), (0, 50), (at, 150)]
+}
+
+#[test]
+fn quick_slam_and_back_is_a_flick() {
+    let trace = [&[(700, 500)][..], &flick(690), &[(690, 2000)]].concat();
+    let gestures = play(700, &trace);
+    assert_eq!(kinds(&gestures), [Gesture::Flick]);
+    // Reported once the window for a second flick has closed, counted from
+    // the sample that came back.
+    let back = 500 + 300 - SAMPLE_MS;
+    assert_eq!(gestures[0].0, back + DOUBLE_FLICK_MS);
+}
+
+#[test]
+fn two_flicks_in_a_row_are_a_double_flick() {
+    let trace = [
+        &[(600, 500)][..],
+        &flick(600),
+        &[(600, 200)],
+        &flick(620),
+        &[(620, 2000)],
+    ]
+    .concat();
+    assert_eq!(kinds(&play(600, &trace)), [Gesture::DoubleFlick]);
+
+    let apart = [
+        &[(600, 500)][..],
+        &flick(600),
+        &[(600, DOUBLE_FLICK_MS + 500)],
+        &flick(600),
+        &[(600, 2000)],
+    ]
+    .concat();
+    assert_eq!(kinds(&play(600, &apart)), [Gesture::Flick, Gesture::Flick]);
+}
+
+#[test]
+fn volume_changes_are_not_flicks() {
+    // Turned down slowly and back up.
+    assert_eq!(
+        play(700, &[(700, 500), (0, 1000), (700, 1000), (700, 2000)]),
+        []
+    );
+    // Slammed down and left there.
+    assert_eq!(play(700, &[(700, 500), (0, 100), (0, 3000)]), []);
+    // Slammed down and brought back somewhere else.
+    assert_eq!(
+        play(700, &[(700, 500), (0, 100), (300, 150), (300, 2000)]),
+        []
+    );
+    // Too close to the bottom to slam.
+    assert_eq!(
+        play(150, &[(150, 500), (0, 100), (150, 150), (150, 2000)]),
+        []
+    );
+    // Back, but too slowly.
+    assert_eq!(
+        play(
+            700,
+            &[(700, 500), (0, 100), (0, 600), (700, 150), (700, 2000)]
+        ),
+        []
+    );
+}
+
+#[test]
+fn holding_at_max_is_reported_once_per_hold() {
+    let trace = [(1023, 100), (1010, 1000), (1023, 1000), (1015, 3000)];
+    let gestures = play(500, &trace);
+    assert_eq!(kinds(&gestures), [Gesture::HoldMax]);
+    assert!(gestures[0].0 >= HOLD_MAX_MS, "{:?}", gestures);
+    assert!(gestures[0].0 <= 100 + HOLD_MAX_MS, "{:?}", gestures);
+
+    // Let go and held again.
+    let trace = [
+        (1023, 100),
+        (1023, 3500),
+        (600, 500),
+        (1023, 100),
+        (1023, 3500),
+    ];
+    assert_eq!(
+        kinds(&play(500, &trace)),
+        [Gesture::HoldMax, Gesture::HoldMax]
+    );
+
+    // Not long enough.
+    assert_eq!(
+        play(500, &[(1023, 100), (1023, HOLD_MAX_MS - 500), (500, 500)]),
+        []
+    );
+}
+
+#[test]
+fn bindings_fire_for_their_gesture() {
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Button {
+        Mute,
+    }
+
+    let bindings = GestureBindings {
+        flick: Some(GestureAction::Button(Button::Mute)),
+        double_flick: Some(GestureAction::NextTheme),
+        hold_max: Some(GestureAction::Calibrate),
+    };
+    let fired = |trace: &[(u32, u32)]| -> Vec<GestureAction<Button>> {
+        play(600, trace)
+            .into_iter()
+            .filter_map(|(_, g)| bindings.action(g))
+            .collect()
+    };
+
+    let single = [&[(600, 500)][..], &flick(600), &[(600, 2000)]].concat();
+    assert_eq!(fired(&single), [GestureAction::Button(Button::Mute)]);
+
+    let double = [
+        &[(600, 500)][..],
+        &flick(600),
+        &[(600, 200)],
+        &flick(600),
+        &[(600, 2000)],
+    ]
+    .concat();
+    assert_eq!(fired(&double), [GestureAction::NextTheme]);
+
+    let hold = [(1023, 100), (1023, HOLD_MAX_MS + 500)];
+    assert_eq!(fired(&hold), [GestureAction::Calibrate]);
+
+    let unbound = GestureBindings::<Button> {
+        hold_max: None,
+        ..bindings
+    };
+    assert_eq!(unbound.action(Gesture::HoldMax), None);
+}
@@ -0,0 +1,275 @@
+//! Golden-image tests for the scenes.
+//!
+//! Every script renders a sequence of frames on the host and stacks them into
+//! one filmstrip, which must match `tests/golden/<name>.png` exactly. On a
+//! mismatch the actual filmstrip and a diff image (changed pixels in red) are
+//! written next to the test binary's temp dir and their paths are printed.
+//!
+//! After an intended change to the rendering, regenerate the goldens with
+//! `UPDATE_GOLDEN=1 cargo test-host --test golden` and review them in the diff.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use deej_gfx::scene::{ActiveChannel, Scenes, ScreenState};
+use deej_gfx::sheet::SpriteSheet;
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+
+mod common;
+
+use common::{sheets_by_name, theme, Frame, H, W};
+
+/// A fader as seen by `prepare_frame_task`.
+#[derive(Clone, Copy)]
+struct Fader {
+    channel: usize,
+    value: u16,
+    moving: bool,
+}
+
+const ICONS: [&str; 5] = [
+    "logo_system",
+    "logo_mic",
+    "logo_browser",
+    "logo_steam",
+    "logo_spotify",
+];
+
+/// Frame period the goldens are rendered at.
+const FRAME_MS: u32 = 140;
+
+/// Host stand-in for `prepare_frame_task`: same state handling, with a fixed
+/// frame period instead of the ticker.
+struct Renderer {
+    sheets: HashMap<String, &'static SpriteSheet>,
+    scenes: Scenes,
+    state: ScreenState,
+    frame_ms: u32,
+    /// Only every `keep`th frame goes into the filmstrip.
+    keep: usize,
+    rendered: usize,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Renderer {
+    fn new(state: ScreenState) -> Self {
+        Self::with_theme(state, "muffet", FRAME_MS)
+    }
+
+    /// Renders the theme called `theme_name` at `frame_ms`, a whole fraction of
+    /// `FRAME_MS`, keeping the frames that line up with the goldens.
+    fn with_theme(state: ScreenState, theme_name: &str, frame_ms: u32) -> Self {
+        assert_eq!(FRAME_MS % frame_ms, 0);
+        let sheets = sheets_by_name();
+        let scenes = Scenes::new(&theme(theme_name), W as i32, H as i32);
+        Self {
+            sheets,
+            scenes,
+            state,
+            frame_ms,
+            keep: (FRAME_MS / frame_ms) as usize,
+            rendered: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    fn run(&mut self, frames: usize, fader: impl Fn(usize) -> Option<Fader>) {
+        for i in 0..frames {
+            let fader = fader(i);
+
+            if fader.is_none() && self.state == ScreenState::ACTIVE {
+                self.state = ScreenState::STANDBY;
+            } else if fader.is_some() && self.state == ScreenState::STANDBY {
+                self.state = ScreenState::ACTIVE;
+            }
+
+            let active = fader.map(|f| ActiveChannel {
+                channel: f.channel,
+                value: f.value,
+                icon: self.sheets[ICONS[f.channel]],
+                input: f.moving,
+                muted: false,
+            });
+
+            let mut frame = Frame::new(W, H);
+            frame.clear(Gray4::BLACK).unwrap();
+            self.state = self
+                .scenes
+                .draw(&mut frame, self.state, active, self.frame_ms);
+            if self.rendered.is_multiple_of(self.keep) {
+                self.frames.push(frame.as_bytes().to_vec());
+            }
+            self.rendered += 1;
+        }
+    }
+
+    /// All frames stacked top to bottom, one byte per pixel (0..=15).
+    fn filmstrip(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.frames.len() * W * H);
+        for frame in &self.frames {
+            for b in frame {
+                out.push(b >> 4);
+                out.push(b & 0x0F);
+            }
+        }
+        out
+    }
+}
+
+fn write_png(path: &Path, width: usize, height: usize, color: png::ColorType, data: &[u8]) {
+    let w = BufWriter::new(File::create(path).unwrap());
+    let mut enc = png::Encoder::new(w, width as u32, height as u32);
+    enc.set_color(color);
+    enc.set_depth(png::BitDepth::Eight);
+    enc.write_header().unwrap().write_image_data(data).unwrap();
+}
+
+fn read_png(path: &Path) -> Option<(usize, usize, Vec<u8>)> {
+    let mut reader = png::Decoder::new(File::open(path).ok()?)
+        .read_info()
+        .unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Grayscale, "{:?}", path);
+    buf.truncate(info.buffer_size());
+    Some((info.width as usize, info.height as usize, buf))
+}
+
+fn check_golden(name: &str, renderer: &Renderer) {
+    let nibbles = renderer.filmstrip();
+    let height = nibbles.len() / W;
+    let actual: Vec<u8> = nibbles.iter().map(|v| v * 17).collect();
+
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(golden.parent().unwrap()).unwrap();
+        write_png(&golden, W, height, png::ColorType::Grayscale, &actual);
+        return;
+    }
+
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    fs::create_dir_all(&out_dir).unwrap();
+    let actual_path = out_dir.join(format!("{}.actual.png", name));
+
+    let Some((gw, gh, expected)) = read_png(&golden) else {
+        write_png(&actual_path, W, height, png::ColorType::Grayscale, &actual);
+        panic!(
+            "{}: no golden image at {}, rendered {} (set UPDATE_GOLDEN=1 to create it)",
+            name,
+            golden.display(),
+            actual_path.display()
+        );
+    };
+
+    if (gw, gh) == (W, height) && expected == actual {
+        return;
+    }
+
+    write_png(&actual_path, W, height, png::ColorType::Grayscale, &actual);
+
+    // Unchanged pixels dimmed, changed ones red; rows missing on either side
+    // count as changed.
+    let diff_h = height.max(gh);
+    let mut diff = Vec::with_capacity(W * diff_h * 3);
+    let mut changed = 0usize;
+    for y in 0..diff_h {
+        for x in 0..W {
+            let a = (y < height).then(|| actual[y * W + x]);
+            let e = (gw == W && y < gh).then(|| expected[y * W + x]);
+            match a {
+                Some(v) if a == e => diff.extend_from_slice(&[v / 3; 3]),
+                _ => {
+                    changed += 1;
+                    diff.extend_from_slice(&[255, 0, 0]);
+                }
+            }
+        }
+    }
+    let diff_path = out_dir.join(format!("{}.diff.png", name));
+    write_png(&diff_path, W, diff_h, png::ColorType::Rgb, &diff);
+
+    panic!(
+        "{}: {} pixels differ from {} ({}x{} vs {}x{})\n  actual: {}\n  diff:   {}",
+        name,
+        changed,
+        golden.display(),
+        W,
+        height,
+        gw,
+        gh,
+        actual_path.display(),
+        diff_path.display()
+    );
+}
+
+#[test]
+fn intro_into_standby() {
+    let mut r = Renderer::new(ScreenState::INTRO);
+    r.run(40, |_| None);
+    assert_eq!(r.state, ScreenState::STANDBY);
+    check_golden("intro_into_standby", &r);
+}
+
+#[test]
+fn active_channel_fill_and_glitch() {
+    let mut r = Renderer::new(ScreenState::STANDBY);
+    r.run(12, |i| {
+        Some(Fader {
+            channel: 0,
+            value: (i * 93) as u16,
+            moving: true,
+        })
+    });
+    r.run(4, |_| {
+        Some(Fader {
+            channel: 3,
+            value: 512,
+            moving: false,
+        })
+    });
+    r.run(3, |_| None);
+    assert_eq!(r.state, ScreenState::STANDBY);
+    check_golden("active_channel_fill_and_glitch", &r);
+}
+
+#[test]
+fn outro_fade() {
+    let mut r = Renderer::new(ScreenState::OUTRO);
+    r.run(18, |_| None);
+    assert_eq!(r.state, ScreenState::OFF);
+    check_golden("outro_fade", &r);
+}
+
+#[test]
+fn cobweb_theme() {
+    let mut r = Renderer::with_theme(ScreenState::INTRO, "cobweb", FRAME_MS);
+    r.run(34, |_| None);
+    r.run(3, |i| {
+        Some(Fader {
+            channel: 1,
+            value: 300 + (i * 200) as u16,
+            moving: i == 0,
+        })
+    });
+    r.state = ScreenState::OUTRO;
+    r.run(18, |_| None);
+    assert_eq!(r.state, ScreenState::OFF);
+    check_golden("cobweb_theme", &r);
+}
+
+#[test]
+fn higher_frame_rates_keep_the_pace() {
+    let mut r = Renderer::with_theme(ScreenState::INTRO, "muffet", 35);
+    r.run(40 * 4, |_| None);
+    check_golden("intro_into_standby", &r);
+
+    let mut r = Renderer::with_theme(ScreenState::OUTRO, "muffet", 20);
+    r.run(18 * 7, |_| None);
+    check_golden("outro_fade", &r);
+}
@@ -0,0 +1,209 @@
+use deej_gfx::kv::{Error, Store, MAX_KEYS, MAX_VALUE};
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
+
+const SECTOR: usize = 256;
+const SECTORS: u32 = 4;
+
+/// NOR flash in RAM: writes only clear bits, erases count per sector, and
+/// power can be cut after a number of bytes written.
+struct RamFlash {
+    bytes: Vec<u8>,
+    erases: Vec<u32>,
+    /// Bytes that can still be written before the power goes.
+    power: Option<usize>,
+}
+
+impl RamFlash {
+    fn new() -> Self {
+        Self {
+            bytes: vec![0xFF; SECTOR * SECTORS as usize],
+            erases: vec![0; SECTORS as usize],
+            power: None,
+        }
+    }
+}
+
+impl ErrorType for RamFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for RamFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let at = offset as usize;
+        bytes.copy_from_slice(&self.bytes[at..at + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl NorFlash for RamFlash {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = SECTOR;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if self.power == Some(0) {
+            return Err(NorFlashErrorKind::Other);
+        }
+        self.bytes[from as usize..to as usize].fill(0xFF);
+        for sector in from as usize / SECTOR..to as usize / SECTOR {
+            self.erases[sector] += 1;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        for (i, &b) in bytes.iter().enumerate() {
+            match &mut self.power {
+                Some(0) => return Err(NorFlashErrorKind::Other),
+                Some(left) => *left -= 1,
+                None => {}
+            }
+            self.bytes[offset as usize + i] &= b;
+        }
+        Ok(())
+    }
+}
+
+fn open(flash: &mut RamFlash) -> Store {
+    Store::open(flash, 0, SECTORS).unwrap()
+}
+
+fn read(store: &Store, flash: &mut RamFlash, key: u8) -> Option<Vec<u8>> {
+    let mut buf = [0u8; MAX_VALUE];
+    let len = store.read(flash, key, &mut buf).unwrap()?;
+    Some(buf[..len].to_vec())
+}
+
+#[test]
+fn the_last_write_of_a_key_wins() {
+    let mut flash = RamFlash::new();
+    let mut store = open(&mut flash);
+    assert_eq!(read(&store, &mut flash, 1), None);
+
+    store.write(&mut flash, 1, b"first").unwrap();
+    store.write(&mut flash, 2, b"other").unwrap();
+    store.write(&mut flash, 1, b"second").unwrap();
+    assert_eq!(read(&store, &mut flash, 1).unwrap(), b"second");
+
+    let store = open(&mut flash);
+    assert_eq!(read(&store, &mut flash, 1).unwrap(), b"second");
+    assert_eq!(read(&store, &mut flash, 2).unwrap(), b"other");
+    assert_eq!(read(&store, &mut flash, 3), None);
+}
+
+#[test]
+fn rewriting_the_same_value_writes_nothing() {
+    let mut flash = RamFlash::new();
+    let mut store = open(&mut flash);
+    store.write(&mut flash, 1, b"value").unwrap();
+    let before = flash.bytes.clone();
+    store.write(&mut flash, 1, b"value").unwrap();
+    assert_eq!(flash.bytes, before);
+}
+
+#[test]
+fn full_sectors_hand_over_in_turn_and_keep_every_key() {
+    let mut flash = RamFlash::new();
+    let mut store = open(&mut flash);
+    store.write(&mut flash, 0, b"kept all along").unwrap();
+    for i in 0..500u32 {
+        store.write(&mut flash, 5, &i.to_le_bytes()).unwrap();
+    }
+
+    let store = open(&mut flash);
+    assert_eq!(read(&store, &mut flash, 0).unwrap(), b"kept all along");
+    assert_eq!(read(&store, &mut flash, 5).unwrap(), 499u32.to_le_bytes());
+
+    let (least, most) = (flash.erases.iter().min(), flash.erases.iter().max());
+    assert!(least.unwrap() + 1 >= *most.unwrap(), "{:?}", flash.erases);
+    assert!(*least.unwrap() > 5, "{:?}", flash.erases);
+}
+
+#[test]
+fn losing_power_keeps_the_old_or_the_new_value() {
+    // Every cut point of a run of writes, some of which move to the next
+    // sector, over a store that already holds another key.
+    for (at, cut) in (24..36).flat_map(|at| (0..100).map(move |cut| (at, cut))) {
+        let mut flash = RamFlash::new();
+        let mut store = open(&mut flash);
+        store.write(&mut flash, 0, b"bystander").unwrap();
+        let mut last = None;
+        for i in 0..40u32 {
+            let value = [i as u8; 20];
+            if i == at {
+                flash.power = Some(cut);
+            }
+            if store.write(&mut flash, 1, &value).is_err() {
+                break;
+            }
+            last = Some(value);
+        }
+        flash.power = None;
+
+        let store = open(&mut flash);
+        let got = read(&store, &mut flash, 1).unwrap();
+        let old = last.unwrap();
+        let new = [old[0] + 1; 20];
+        assert!(
+            got == old || got == new,
+            "write {} cut {}: {:?}",
+            at,
+            cut,
+            got
+        );
+        let bystander = read(&store, &mut flash, 0).unwrap();
+        assert_eq!(bystander, b"bystander", "write {} cut {}", at, cut);
+    }
+}
+
+#[test]
+fn a_corrupt_copy_falls_back_to_the_one_before() {
+    let mut flash = RamFlash::new();
+    let mut store = open(&mut flash);
+    store.write(&mut flash, 1, b"older").unwrap();
+    store.write(&mut flash, 1, b"newer").unwrap();
+
+    let at = flash.bytes.windows(5).position(|w| w == b"newer").unwrap();
+    flash.bytes[at + 2] ^= 0x10;
+    let mut store = open(&mut flash);
+    assert_eq!(read(&store, &mut flash, 1).unwrap(), b"older");
+
+    // And writing carries on past the bad record.
+    store.write(&mut flash, 1, b"newest").unwrap();
+    let store = open(&mut flash);
+    assert_eq!(read(&store, &mut flash, 1).unwrap(), b"newest");
+}
+
+#[test]
+fn garbage_in_flash_is_formatted_away() {
+    let mut flash = RamFlash::new();
+    for (i, b) in flash.bytes.iter_mut().enumerate() {
+        *b = (i * 7 + 3) as u8;
+    }
+    let mut store = open(&mut flash);
+    assert_eq!(read(&store, &mut flash, 1), None);
+    store.write(&mut flash, 1, b"fresh").unwrap();
+    assert_eq!(read(&open(&mut flash), &mut flash, 1).unwrap(), b"fresh");
+}
+
+#[test]
+fn bad_keys_and_oversized_values_are_refused() {
+    let mut flash = RamFlash::new();
+    let mut store = open(&mut flash);
+    assert_eq!(
+        store.write(&mut flash, MAX_KEYS as u8, b"x"),
+        Err(Error::Invalid)
+    );
+    assert_eq!(
+        store.write(&mut flash, 1, &[0; MAX_VALUE + 1]),
+        Err(Error::Invalid)
+    );
+    // Allowed, but more than a sector holds.
+    assert_eq!(store.write(&mut flash, 1, &[0; SECTOR]), Err(Error::Full));
+    assert_eq!(read(&store, &mut flash, 1), None);
+}
@@ -0,0 +1,178 @@
+use deej_gfx::frame::Gray4Frame;
+use deej_gfx::scene::{ActiveChannel, Scenes, ScreenState};
+use deej_gfx::sheet::SpriteSheet;
+use deej_gfx::theme::{Layout, Theme, LAYOUT_HEIGHT, LAYOUT_WIDTH};
+use deej_gfx::volume_indicator::{ICON_HEIGHT, ICON_WIDTH};
+use embedded_graphics::pixelcolor::Gray4;
+use embedded_graphics::prelude::*;
+
+mod common;
+
+use common::{sheets_by_name, themes};
+
+fn points(l: &Layout) -> Vec<Point> {
+    let mut points = vec![
+        l.intro,
+        l.halo_origin,
+        l.idle,
+        l.active,
+        l.indicator,
+        l.outro,
+    ];
+    points.extend(l.halo_offsets);
+    points.push(Point::new(l.idle_walk as i32, 0));
+    points
+}
+
+#[test]
+fn reference_screen_keeps_the_layout() {
+    for t in themes() {
+        let l = t.layout_for(LAYOUT_WIDTH, LAYOUT_HEIGHT);
+        assert_eq!(points(&l), points(&t.layout), "{}", t.name);
+    }
+}
+
+#[test]
+fn sprites_stay_in_the_room_they_have() {
+    for t in themes() {
+        for (w, h) in [(128, 64), (128, 128), (320, 240)] {
+            let l = t.layout_for(w, h);
+            let inside = |p: Point, sheet: &SpriteSheet, what: &str| {
+                let (sw, sh) = (sheet.width as i32, sheet.height as i32);
+                for (pos, size, screen) in [(p.x, sw, w), (p.y, sh, h)] {
+                    let ok = if size <= screen {
+                        (0..=screen - size).contains(&pos)
+                    } else {
+                        (screen - size..=0).contains(&pos)
+                    };
+                    assert!(ok, "{} {} on {}x{}: {:?}", t.name, what, w, h, p);
+                }
+            };
+            inside(l.intro, t.intro, "intro");
+            inside(l.idle, t.idle, "idle");
+            inside(l.outro, t.outro, "outro");
+            // The active sprite may give way to the volume icon, see
+            // `active_sprite_clears_the_volume_icon`.
+            if l.active.x + t.active.width as i32 != l.indicator.x {
+                inside(l.active, t.active, "active");
+            }
+
+            let walk_end = l.idle.x + l.idle_walk as i32 + t.idle.width as i32;
+            assert!(walk_end <= w.max(t.idle.width as i32), "{} walk", t.name);
+            assert!(l.indicator.x + ICON_WIDTH as i32 <= w, "{} icon", t.name);
+        }
+    }
+}
+
+#[test]
+fn active_sprite_clears_the_volume_icon() {
+    for t in themes() {
+        for (w, h) in [(256, 64), (128, 64), (128, 128), (320, 240)] {
+            let l = t.layout_for(w, h);
+            let (aw, ah) = (t.active.width as i32, t.active.height as i32);
+            let (iw, ih) = (ICON_WIDTH as i32, ICON_HEIGHT as i32);
+            let apart = l.active.x + aw <= l.indicator.x
+                || l.indicator.x + iw <= l.active.x
+                || l.active.y + ah <= l.indicator.y
+                || l.indicator.y + ih <= l.active.y;
+            assert!(apart, "{} on {}x{}", t.name, w, h);
+
+            // Both on screen, the icon whole and at least half the sprite.
+            assert!((0..=w - iw).contains(&l.indicator.x), "{} icon", t.name);
+            assert!((0..=h - ih).contains(&l.indicator.y), "{} icon", t.name);
+            let shown = (l.active.x + aw).min(w) - l.active.x.max(0);
+            assert!(shown * 2 >= aw, "{} on {}x{}: {} px", t.name, w, h, shown);
+            assert!((0..=h - ah).contains(&l.active.y), "{} active", t.name);
+        }
+    }
+}
+
+/// Runs every screen on a `W` x `H` frame and returns how many frames had
+/// something on them.
+fn run_scenes<const N: usize>(t: &Theme, w: usize, h: usize) -> usize {
+    let mut scenes = Scenes::new(t, w as i32, h as i32);
+    let mut frame = Box::new(Gray4Frame::<N>::new(w, h));
+    let icon = sheets_by_name()["logo_system"];
+
+    let mut lit = 0;
+    let mut state = ScreenState::INTRO;
+    for i in 0..120 {
+        if i == 60 {
+            state = ScreenState::ACTIVE;
+        } else if i == 80 {
+            state = ScreenState::OUTRO;
+        }
+        let active = ActiveChannel {
+            channel: 0,
+            value: 600,
+            icon,
+            input: i % 2 == 0,
+            muted: i % 3 == 0,
+        };
+
+        frame.clear(Gray4::BLACK).unwrap();
+        state = scenes.draw(&mut frame, state, Some(active), 140);
+        lit += frame.as_bytes().iter().any(|&b| b != 0) as usize;
+    }
+    assert_eq!(state, ScreenState::OFF, "{} on {}x{}", t.name, w, h);
+    lit
+}
+
+#[test]
+fn scenes_play_on_other_panels() {
+    for t in themes() {
+        assert!(
+            run_scenes::<{ 128 * 64 / 2 }>(&t, 128, 64) > 60,
+            "{}",
+            t.name
+        );
+        assert!(
+            run_scenes::<{ 128 * 128 / 2 }>(&t, 128, 128) > 60,
+            "{}",
+            t.name
+        );
+    }
+}
+
+#[test]
+fn muted_channel_is_crossed_out_on_its_icon() {
+    let t = &themes()[0];
+    let l = t.layout;
+    let icon = sheets_by_name()["logo_system"];
+
+    let draw = |muted| {
+        let mut scenes = Scenes::new(t, LAYOUT_WIDTH, LAYOUT_HEIGHT);
+        let mut frame = Box::new(Gray4Frame::<{ 256 * 64 / 2 }>::new(256, 64));
+        let active = ActiveChannel {
+            channel: 0,
+            value: 1023,
+            icon,
+            input: false,
+            muted,
+        };
+        scenes.draw(&mut frame, ScreenState::ACTIVE, Some(active), 40);
+        frame
+    };
+    let (plain, muted) = (draw(false), draw(true));
+
+    let mut changed = 0;
+    for y in 0..64 {
+        for x in 0..256 {
+            if plain.get(x, y) == muted.get(x, y) {
+                continue;
+            }
+            changed += 1;
+            let (x, y) = (x as i32, y as i32);
+            assert!(
+                x >= l.indicator.x
+                    && x < l.indicator.x + ICON_WIDTH as i32
+                    && y >= l.indicator.y
+                    && y < l.indicator.y + ICON_HEIGHT as i32,
+                "({}, {}) outside the icon",
+                x,
+                y
+            );
+        }
+    }
+    assert!(changed > 100, "{} pixels", changed);
+}
@@ -0,0 +1,70 @@
+use deej_gfx::dirty::Rect;
+use deej_gfx::frame::Gray4Frame;
+use deej_gfx::mono::{lit, pack_pages, page_bytes};
+
+const W: usize = 128;
+const H: usize = 64;
+const N: usize = W * H / 2;
+
+type Frame = Gray4Frame<N>;
+
+const ALL: Rect = Rect::new(0, 0, W as u16, H as u16);
+
+fn unpack(pages: &[u8], x: usize, y: usize) -> bool {
+    pages[y / 8 * W + x] & (1 << (y % 8)) != 0
+}
+
+#[test]
+fn grey_levels_keep_their_brightness() {
+    for level in 0..16u8 {
+        let mut frame = Box::new(Frame::new(W, H));
+        frame.as_bytes_mut().fill(level << 4 | level);
+        let mut pages = [0u8; page_bytes(W, H)];
+        pack_pages(&frame, &ALL, &mut pages);
+
+        let on: u32 = pages.iter().map(|b| b.count_ones()).sum();
+        let share = on as f64 / (W * H) as f64;
+        assert!(
+            (share - level as f64 / 15.0).abs() <= 1.0 / 30.0,
+            "level {}: {}",
+            level,
+            share
+        );
+    }
+}
+
+#[test]
+fn black_and_white_are_solid() {
+    for (x, y) in [(0, 0), (1, 2), (3, 3), (17, 42)] {
+        assert!(!lit(0, x, y));
+        assert!(lit(15, x, y));
+    }
+}
+
+#[test]
+fn pages_hold_eight_rows_top_bit_first() {
+    let mut frame = Box::new(Frame::new(W, H));
+    frame.set(5, 9, 15);
+    frame.set(127, 63, 15);
+    let mut pages = [0u8; page_bytes(W, H)];
+    pack_pages(&frame, &ALL, &mut pages);
+
+    assert_eq!(pages[W + 5], 0b10);
+    assert_eq!(pages[7 * W + 127], 0b1000_0000);
+    assert_eq!(pages.iter().map(|b| b.count_ones()).sum::<u32>(), 2);
+}
+
+#[test]
+fn only_touched_pages_are_packed() {
+    let mut frame = Box::new(Frame::new(W, H));
+    frame.as_bytes_mut().fill(0xFF);
+    let mut pages = [0u8; page_bytes(W, H)];
+    pack_pages(&frame, &Rect::new(10, 12, 20, 17), &mut pages);
+
+    for y in 0..H {
+        for x in 0..W {
+            let inside = (10..20).contains(&x) && (8..24).contains(&y);
+            assert_eq!(unpack(&pages, x, y), inside, "({}, {})", x, y);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use deej_gfx::packbits;
+use deej_gfx::sheet::SheetEncoding;
+
+mod common;
+
+fn roundtrip(src: &[u8]) -> Vec<u8> {
+    let mut coded = Vec::new();
+    packbits::encode(src, &mut coded);
+
+    let mut out = vec![0xAA; src.len()];
+    packbits::Decoder::new(&coded).read(&mut out);
+    out
+}
+
+#[test]
+fn roundtrips_edge_cases() {
+    let mut alternating = vec![0u8; 300];
+    for (i, b) in alternating.iter_mut().enumerate() {
+        *b = (i % 2) as u8;
+    }
+
+    let cases: Vec<Vec<u8>> = vec![
+        vec![],
+        vec![7],
+        vec![7, 7],
+        vec![1, 2, 2, 3],
+        vec![0; 127],
+        vec![0; 128],
+        vec![0; 129],
+        vec![0; 1000],
+        (0..=255).collect(),
+        alternating,
+        [
+            vec![5; 3],
+            (0..200).map(|i| i as u8).collect(),
+            vec![9; 130],
+        ]
+        .concat(),
+    ];
+
+    for case in cases {
+        assert_eq!(roundtrip(&case), case, "case of {} bytes", case.len());
+    }
+}
+
+#[test]
+fn truncated_stream_pads_with_transparent() {
+    let mut coded = Vec::new();
+    packbits::encode(&[3, 1, 4, 1, 5], &mut coded);
+    coded.truncate(3);
+
+    let mut out = [0xFF; 8];
+    packbits::Decoder::new(&coded).read(&mut out);
+    assert_eq!(out, [3, 1, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn asset_sheets_decode_to_originals() {
+    let sheets = common::converted();
+    assert!(!sheets.is_empty());
+
+    for sheet in &sheets {
+        let fb = sheet.frame_bytes();
+        let handle = sheet.leak();
+        let mut frame = vec![0u8; fb];
+
+        for idx in 0..sheet.frames {
+            handle.decode_frame(idx, &mut frame);
+            assert_eq!(
+                frame,
+                &sheet.raw[idx * fb..(idx + 1) * fb],
+                "{} frame {}",
+                sheet.name,
+                idx
+            );
+        }
+    }
+}
+
+#[test]
+fn compressed_sheets_are_smaller() {
+    for sheet in common::converted() {
+        if sheet.encoding == SheetEncoding::PackBits {
+            assert!(
+                sheet.encoded().len() < sheet.raw.len(),
+                "{} grew when compressed",
+                sheet.name
+            );
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use deej_gfx::particles::{Emitter, ParticleConfig, Spawn, Spread, MAX_PARTICLES};
+use deej_gfx::sheet::{SheetEncoding, SpriteSheet};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+mod common;
+
+use common::{Frame, H, W};
+
+const STEP_MS: u32 = 100;
+
+/// A 2x2 dot, level 8 and then level 4.
+static DOT: SpriteSheet = SpriteSheet {
+    data: &[0x88, 0x88, 0x44, 0x44],
+    width: 2,
+    height: 2,
+    frames: 2,
+    frame_ms: STEP_MS,
+    encoding: SheetEncoding::Raw,
+};
+
+/// One still dot at (10, 10) that lives forever.
+fn config() -> ParticleConfig {
+    ParticleConfig {
+        sheet: &DOT,
+        count: 1,
+        spawn: Spawn::Area(Rectangle::new(Point::new(10, 10), Size::new(1, 1))),
+        vx: Spread::fixed(0),
+        vy: Spread::fixed(0),
+        gravity: Point::zero(),
+        lifetime: None,
+        fade_in: 0,
+        step_ms: STEP_MS,
+        burst_step_ms: STEP_MS,
+        respawn: false,
+    }
+}
+
+/// The top left lit pixel and its level.
+fn render(emitter: &Emitter) -> Option<(Point, u8)> {
+    let mut frame = Box::new(Frame::new(W, H));
+    emitter.draw(&mut frame);
+    (0..H)
+        .flat_map(|y| (0..W).map(move |x| (x, y)))
+        .find(|&(x, y)| frame.get(x, y) != 0)
+        .map(|(x, y)| (Point::new(x as i32, y as i32), frame.get(x, y)))
+}
+
+#[test]
+fn gravity_speeds_particles_up() {
+    let mut emitter = Emitter::new(
+        ParticleConfig {
+            gravity: Point::new(0, 1),
+            ..config()
+        },
+        W as i32,
+        H as i32,
+    );
+    emitter.fill();
+
+    let mut ys = Vec::new();
+    for _ in 0..5 {
+        ys.push(render(&emitter).unwrap().0.y);
+        emitter.advance(STEP_MS);
+    }
+    assert_eq!(ys, [10, 10, 11, 13, 16]);
+}
+
+#[test]
+fn time_between_frames_is_carried_over() {
+    let mut emitter = Emitter::new(
+        ParticleConfig {
+            vx: Spread::fixed(3),
+            ..config()
+        },
+        W as i32,
+        H as i32,
+    );
+    emitter.fill();
+
+    for _ in 0..10 {
+        emitter.advance(STEP_MS / 4);
+    }
+    // 2.5 steps: two whole ones and half of the next carried.
+    assert_eq!(render(&emitter).unwrap().0, Point::new(16, 10));
+    emitter.advance(STEP_MS / 2);
+    assert_eq!(render(&emitter).unwrap().0, Point::new(19, 10));
+}
+
+#[test]
+fn particles_die_of_old_age_without_respawn() {
+    let mut emitter = Emitter::new(
+        ParticleConfig {
+            count: 3,
+            lifetime: Some(Spread::fixed(3)),
+            ..config()
+        },
+        W as i32,
+        H as i32,
+    );
+    emitter.fill();
+    assert_eq!(emitter.len(), 3);
+
+    emitter.advance(2 * STEP_MS);
+    assert_eq!(emitter.len(), 3);
+    emitter.advance(STEP_MS);
+    assert!(emitter.is_empty());
+}
+
+#[test]
+fn respawn_keeps_the_count_up() {
+    let mut emitter = Emitter::new(
+        ParticleConfig {
+            count: 100,
+            spawn: Spawn::Lanes(5),
+            vx: Spread::range(-3, 3),
+            vy: Spread::range(1, 6),
+            lifetime: Some(Spread::range(1, 20)),
+            respawn: true,
+            ..config()
+        },
+        W as i32,
+        H as i32,
+    );
+    emitter.fill();
+    for _ in 0..200 {
+        assert_eq!(emitter.len(), MAX_PARTICLES);
+        emitter.advance(STEP_MS);
+    }
+}
+
+#[test]
+fn rising_particles_leave_through_the_top() {
+    let mut emitter = Emitter::new(
+        ParticleConfig {
+            spawn: Spawn::Area(Rectangle::new(Point::new(0, 60), Size::new(256, 4))),
+            vy: Spread::range(-3, -1),
+            count: 8,
+            ..config()
+        },
+        W as i32,
+        H as i32,
+    );
+    emitter.fill();
+    emitter.advance(20 * STEP_MS);
+    assert_eq!(emitter.len(), 8, "still on screen");
+    emitter.advance(50 * STEP_MS);
+    assert!(emitter.is_empty());
+}
+
+#[test]
+fn bursts_flash_in_and_then_animate() {
+    let mut emitter = Emitter::new(config(), W as i32, H as i32);
+    emitter.burst(
+        ParticleConfig {
+            fade_in: 2,
+            ..config()
+        },
+        &[Point::new(30, 5)],
+    );
+
+    let mut seen = Vec::new();
+    for _ in 0..5 {
+        seen.push(render(&emitter).unwrap());
+        emitter.advance(STEP_MS);
+    }
+    let at = Point::new(30, 5);
+    assert_eq!(seen, [(at, 14), (at, 11), (at, 8), (at, 4), (at, 8)]);
+}
+
+#[test]
+fn hand_over_sets_bursts_moving() {
+    let mut emitter = Emitter::new(config(), W as i32, H as i32);
+    emitter.burst(config(), &[Point::new(30, 5)]);
+    emitter.advance(3 * STEP_MS);
+    assert_eq!(render(&emitter).unwrap().0, Point::new(30, 5));
+
+    emitter.hand_over(ParticleConfig {
+        vy: Spread::fixed(2),
+        burst_step_ms: 2 * STEP_MS,
+        ..config()
+    });
+    emitter.advance(3 * STEP_MS);
+    assert_eq!(render(&emitter).unwrap().0, Point::new(30, 7));
+}
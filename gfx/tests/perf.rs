@@ -0,0 +1,57 @@
+use deej_gfx::frame::Gray4Frame;
+use deej_gfx::perf::{Overlay, Timing};
+
+type Frame = Gray4Frame<{ 256 * 64 / 2 }>;
+
+#[test]
+fn timing_keeps_min_avg_max() {
+    let mut t = Timing::new();
+    assert_eq!((t.min_us(), t.avg_us(), t.max_us()), (None, None, None));
+    assert_eq!(t.to_string(), "n 0");
+
+    for us in [3_000, 1_200, 4_850] {
+        t.add(us);
+    }
+    assert_eq!(t.count(), 3);
+    assert_eq!(
+        (t.min_us(), t.avg_us(), t.max_us()),
+        (Some(1_200), Some(3_016), Some(4_850))
+    );
+    assert_eq!(t.to_string(), "n 3 min 1.2 avg 3.0 max 4.8 ms");
+}
+
+#[test]
+fn timing_does_not_overflow_on_long_runs() {
+    let mut t = Timing::new();
+    for _ in 0..100_000 {
+        t.add(200_000);
+    }
+    assert_eq!(t.avg_us(), Some(200_000));
+}
+
+#[test]
+fn overlay_stays_in_its_corner() {
+    let mut frame = Box::new(Frame::new(256, 64));
+    frame.as_bytes_mut().fill(0x77);
+    let overlay = Overlay {
+        fps: 25,
+        render_us: Some(3_100),
+        flush_us: None,
+    };
+    overlay.draw(&mut frame);
+
+    // 8 characters by 3 lines of the 4x6 font, blacked out under the text.
+    for y in 0..64 {
+        for x in 0..256 {
+            let inside = x >= 256 - 32 && y < 18;
+            let v = frame.get(x, y);
+            if inside {
+                assert!(v == 0 || v == 15, "({}, {}) = {}", x, y, v);
+            } else {
+                assert_eq!(v, 7, "({}, {})", x, y);
+            }
+        }
+    }
+    let lit = (224..256).flat_map(|x| (0..18).map(move |y| (x, y)));
+    assert!(lit.filter(|&(x, y)| frame.get(x, y) == 15).count() > 20);
+}
@@ -1,5 +0,0 @@
-MEMORY {
-    BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 16M - 0x100
-    RAM   : ORIGIN = 0x20000000, LENGTH = 256K
-}
@@ -1,73 +1,73 @@
 use adc_mcp3008::{self, Channels8, Mcp3008};
-use core::sync::atomic::{AtomicBool, AtomicI8, AtomicU32, Ordering};
+use core::sync::atomic::{AtomicU32, Ordering};
+use deej_gfx::gesture::GestureDetector;
 use embassy_rp::{gpio, peripherals, spi};
 use embassy_time::Timer;
 use embassy_time::{Duration, Instant};
 
-use crate::graphics::{get_screen_state, ScreenState, ACTIVE_INPUT, SCREEN_STATE};
-use crate::{deej_usb, AdcResources};
+use crate::config::{self, ChannelConfig};
+use crate::events::{self, Event, Events, HostState};
+use crate::perf::{self, Metric};
+use crate::watchdog::{self, Task};
+use crate::{buttons, deej_usb, diagnostics, encoder, gestures, AdcResources};
 
 #[derive(Clone, Copy)]
 pub struct AdcChanCfg {
+    /// Default calibration; the one in use is in `config`.
     pub invert: bool,
     pub min: u16,
     pub max: u16,
     pub chan: Channels8,
-    pub target: AdcTarget,
-}
-
-#[derive(Clone, Copy)]
-pub enum AdcTarget {
-    System,
-    Mic,
-    Browser,
-    Steam,
-    // Discord,
-    Spotify,
 }
 
+/// Default of `Config::noise_threshold`.
 pub const NOISE_THRESHOLD: u32 = 15;
 
+/// Loop period while the host is active; a flick lasts a few of them.
+const SAMPLE_PERIOD: Duration = Duration::from_millis(50);
+
+/// Polling period while the host is suspended.
+const SUSPEND_POLL: Duration = Duration::from_secs(1);
+
+/// Index into `ADC_CHANNELS` of the fader that starts diagnostics when held
+/// at full volume during boot.
+pub const DIAGNOSTICS_FADER: usize = 0;
+
 pub const ADC_CHANNELS: [AdcChanCfg; 5] = [
     AdcChanCfg {
         invert: true,
         min: 0,
         max: 1023,
         chan: Channels8::CH0,
-        target: AdcTarget::System,
     },
     AdcChanCfg {
         invert: true,
         min: 0,
         max: 1023,
         chan: Channels8::CH1,
-        target: AdcTarget::Mic,
     },
     AdcChanCfg {
         invert: true,
         min: 0,
         max: 1023,
         chan: Channels8::CH2,
-        target: AdcTarget::Browser,
     },
     AdcChanCfg {
         invert: true,
         min: 0,
         max: 1023,
         chan: Channels8::CH3,
-        target: AdcTarget::Steam,
     },
     AdcChanCfg {
         invert: true,
         min: 0,
         max: 1023,
         chan: Channels8::CH4,
-        target: AdcTarget::Spotify,
     },
 ];
 
+/// Default of `Config::active_channel_ttl_ms`.
 pub const ACTIVE_CHANNEL_TTL: u32 = 1000;
-pub static ACTIVE_CHANNEL: AtomicI8 = AtomicI8::new(-1);
 
 pub static ADC_VALUES: [AtomicU32; 5] = [
     AtomicU32::new(0),
@@ -77,8 +77,6 @@ pub static ADC_VALUES: [AtomicU32; 5] = [
     AtomicU32::new(0),
 ];
 
-pub static ADC_FORCE_PUSH: AtomicBool = AtomicBool::new(false);
-
 pub struct AdcMcp<'d> {
     adc: Mcp3008<spi::Spi<'d, peripherals::SPI0, spi::Async>, gpio::Output<'d>>,
 }
@@ -103,16 +101,107 @@ impl<'d> AdcMcp<'d> {
             adc: Mcp3008::new(adc_spi, cs).unwrap(),
         }
     }
+
+    /// Channel `i` on the 0..=1023 scale, from its encoder if it has one.
+    fn read(&mut self, i: usize) -> Option<u32> {
+        if let Some(v) = encoder::value(i) {
+            return Some(v);
+        }
+        let calibration = config::get(|c| c.channels[i]);
+        self.adc
+            .read_channel(ADC_CHANNELS[i].chan)
+            .ok()
+            .map(|raw| normalize_value(raw, &calibration))
+    }
+}
+
+/// Smallest change of channel `i` passed on. An encoder does not drift, so
+/// every detent counts.
+fn threshold(i: usize) -> u32 {
+    if encoder::value(i).is_some() {
+        1
+    } else {
+        config::get(|c| c.noise_threshold as u32)
+    }
 }
 
 #[embassy_executor::task]
-pub async fn adc_task(adc: AdcResources) {
+pub async fn adc_task(adc: AdcResources, mut events: Events) {
     let mut adc_mcp = AdcMcp::new(adc);
 
     let mut active_deadline = Instant::now();
-    ACTIVE_CHANNEL.store(-1, Ordering::Relaxed);
+    // What the last `ActiveChannelChanged` said.
+    let mut active: Option<usize> = None;
+    let mut input = false;
+
+    let fader = ADC_CHANNELS[DIAGNOSTICS_FADER];
+    let calibration = config::get(|c| c.channels[DIAGNOSTICS_FADER]);
+    if let Ok(raw) = adc_mcp.adc.read_channel(fader.chan) {
+        if normalize_value(raw, &calibration) >= 1023 - threshold(DIAGNOSTICS_FADER) {
+            diagnostics::start();
+        }
+    }
+
+    let mut last_loop: Option<Instant> = None;
+    let mut detectors = [GestureDetector::new(); ADC_CHANNELS.len()];
+    let mut diagnosing = false;
+    // The values go out whether they changed or not: the host came back,
+    // a channel was muted, or diagnostics held them up.
+    let mut force_push = false;
+    // A channel to bring up on the active screen as if its fader had moved.
+    let mut show: Option<usize> = None;
 
     loop {
+        watchdog::check_in(Task::Adc);
+
+        while let Some(event) = events.try_next_message_pure() {
+            match event {
+                Event::HostStateChanged(HostState::Active) => force_push = true,
+                Event::MuteChanged(channel) => {
+                    force_push = true;
+                    show = Some(channel);
+                }
+                _ => {}
+            }
+        }
+
+        force_push |= diagnosing && !diagnostics::running();
+        diagnosing = diagnostics::running();
+        if diagnosing {
+            for (i, conf) in ADC_CHANNELS.iter().enumerate() {
+                let raw = adc_mcp.adc.read_channel(conf.chan).ok();
+                if raw.is_none() {
+                    perf::adc_error();
+                }
+                diagnostics::record(i, raw);
+            }
+            // Sampling runs at its own pace, not the loop's.
+            last_loop = None;
+            Timer::after(diagnostics::SAMPLE_PERIOD).await;
+            continue;
+        }
+
+        if !deej_usb::host_active() {
+            // Suspended: only look for a fader being moved, to wake the host.
+            let moved = (0..ADC_CHANNELS.len()).any(|i| {
+                adc_mcp.read(i).is_some_and(|norm| {
+                    norm.abs_diff(ADC_VALUES[i].load(Ordering::Relaxed)) >= threshold(i)
+                })
+            });
+            if moved {
+                deej_usb::request_wakeup();
+            }
+            last_loop = None;
+            watchdog::idle(Task::Adc, Timer::after(SUSPEND_POLL)).await;
+            continue;
+        }
+
+        let started = Instant::now();
+        if let Some(last) = last_loop {
+            perf::record(Metric::AdcLoop, started - last);
+        }
+        last_loop = Some(started);
+
         let mut snapshot: [u32; ADC_VALUES.len()] = [0; ADC_VALUES.len()];
         for (i, s) in snapshot.iter_mut().enumerate() {
             *s = ADC_VALUES[i].load(Ordering::Relaxed);
@@ -122,13 +211,17 @@ pub async fn adc_task(adc: AdcResources) {
         let mut best_idx: Option<usize> = None;
         let mut best_diff: u32 = 0;
 
-        for (i, conf) in ADC_CHANNELS.iter().enumerate() {
-            if let Ok(raw) = adc_mcp.adc.read_channel(conf.chan) {
-                let norm = normalize_value(raw, *conf);
+        let now_ms = started.as_millis() as u32;
+        for i in 0..ADC_CHANNELS.len() {
+            if let Some(norm) = adc_mcp.read(i) {
+                if let Some(gesture) = detectors[i].update(now_ms, norm) {
+                    gestures::run(gesture, i);
+                }
+
                 let curr = snapshot[i];
                 let diff = core::cmp::max(curr, norm) - core::cmp::min(curr, norm);
 
-                if diff >= NOISE_THRESHOLD {
+                if diff >= threshold(i) {
                     ADC_VALUES[i].store(norm, Ordering::Relaxed);
                     snapshot[i] = norm;
                     any_updated = true;
@@ -139,39 +232,46 @@ pub async fn adc_task(adc: AdcResources) {
                     }
                 }
             } else {
+                perf::adc_error();
                 log::warn!("Failed to read channel {}", i);
             }
         }
 
-        let now = Instant::now();
+        best_idx = best_idx.or(show.take());
 
-        if let Some(idx) = best_idx {
-            set_active_channel(Some(idx));
-            active_deadline = now + Duration::from_millis(ACTIVE_CHANNEL_TTL as u64);
+        let now = Instant::now();
 
-            ACTIVE_INPUT.store(true, Ordering::Relaxed);
-            SCREEN_STATE.store(ScreenState::ACTIVE as u8, Ordering::Relaxed);
+        let (next_active, next_input) = if let Some(idx) = best_idx {
+            let ttl = config::get(|c| c.active_channel_ttl_ms);
+            active_deadline = now + Duration::from_millis(ttl as u64);
+            (Some(idx), true)
         } else if now >= active_deadline {
-            set_active_channel(None);
-
-            if get_screen_state() == ScreenState::ACTIVE {
-                SCREEN_STATE.store(ScreenState::STANDBY as u8, Ordering::Relaxed);
-            }
+            (None, false)
         } else {
-            ACTIVE_INPUT.store(false, Ordering::Relaxed);
+            (active, false)
+        };
+        if (next_active, next_input) != (active, input) {
+            (active, input) = (next_active, next_input);
+            events::publish(Event::ActiveChannelChanged {
+                channel: active,
+                input,
+            });
         }
 
-        if any_updated || ADC_FORCE_PUSH.load(Ordering::Relaxed) {
-            deej_usb::write_adc_values(snapshot);
-            ADC_FORCE_PUSH.store(false, Ordering::Relaxed);
+        if any_updated {
+            events::FADERS.sender().send(snapshot.map(|v| v as u16));
+        }
+        if any_updated || force_push {
+            deej_usb::write_adc_values(buttons::apply(snapshot));
+            force_push = false;
         }
 
-        Timer::after_millis(200).await;
+        Timer::after(SAMPLE_PERIOD).await;
     }
 }
 
 #[inline]
-pub fn normalize_value(raw: u16, cfg: AdcChanCfg) -> u32 {
+pub fn normalize_value(raw: u16, cfg: &ChannelConfig) -> u32 {
     let mut v = if cfg.invert {
         cfg.min.saturating_add(cfg.max).saturating_sub(raw)
     } else {
@@ -193,30 +293,3 @@ pub fn normalize_value(raw: u16, cfg: AdcChanCfg) -> u32 {
     let v0 = (v - cfg.min) as u32;
     ((v0 * 1023) + (span as u32 / 2)) / (span as u32)
 }
-
-pub fn read_adc_value(channel: usize) -> u32 {
-    if channel < 8 {
-        ADC_VALUES[channel].load(Ordering::Relaxed)
-    } else {
-        0
-    }
-}
-
-fn set_active_channel(channel: Option<usize>) {
-    let idx = match channel {
-        Some(c) if c < ADC_CHANNELS.len() => c as i8,
-        _ => -1,
-    };
-
-    ACTIVE_CHANNEL.store(idx, Ordering::Relaxed);
-}
-
-pub fn get_active_channel() -> Option<usize> {
-    let active_channel = ACTIVE_CHANNEL.load(Ordering::Relaxed);
-
-    if active_channel >= 0 {
-        Some(active_channel as usize)
-    } else {
-        None
-    }
-}
@@ -0,0 +1,7 @@
+//! Sprite sheets converted at build time from `assets/sprites.toml`.
+//! See `gfx/src/assets.rs` for the conversion rules.
+#![allow(dead_code)]
+
+use deej_gfx::sheet::{SheetEncoding, SpriteSheet};
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
@@ -0,0 +1,41 @@
+//! The board the firmware runs on, picked by one `board-*` cargo feature.
+//!
+//! Each profile in `board/` assigns the pins with `assign_resources!`, has
+//! a `split` that hands them out, and gives the size of the board's flash;
+//! `build.rs` puts the same size into `memory.x`. What a board has besides
+//! the faders, a screen or a status LED, comes from the `screen` and
+//! `status-led` features its `board-*` feature turns on.
+//!
+//! | feature                   | board                    | flash | screen | status LED |
+//! |---------------------------|--------------------------|-------|--------|------------|
+//! | `board-tenstar-pro-micro` | Tenstar RP2040 Pro Micro | 16M   | yes    | no         |
+//! | `board-pico`              | Raspberry Pi Pico        | 2M    | yes    | GP25       |
+//! | `board-rp2040-zero`       | Waveshare RP2040-Zero    | 2M    | no     | no         |
+
+#[cfg(feature = "board-pico")]
+mod pico;
+#[cfg(feature = "board-rp2040-zero")]
+mod rp2040_zero;
+#[cfg(feature = "board-tenstar-pro-micro")]
+mod tenstar_pro_micro;
+
+#[cfg(feature = "board-pico")]
+pub use pico::*;
+#[cfg(feature = "board-rp2040-zero")]
+pub use rp2040_zero::*;
+#[cfg(feature = "board-tenstar-pro-micro")]
+pub use tenstar_pro_micro::*;
+
+#[cfg(not(any(
+    feature = "board-pico",
+    feature = "board-rp2040-zero",
+    feature = "board-tenstar-pro-micro"
+)))]
+compile_error!("enable one board-* feature");
+
+#[cfg(any(
+    all(feature = "board-pico", feature = "board-rp2040-zero"),
+    all(feature = "board-pico", feature = "board-tenstar-pro-micro"),
+    all(feature = "board-rp2040-zero", feature = "board-tenstar-pro-micro"),
+))]
+compile_error!("enable only one board-* feature");
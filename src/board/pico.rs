@@ -0,0 +1,70 @@
+//! Raspberry Pi Pico: 2M of flash, the panel on SPI1 and the green LED on
+//! GP25 as the status LED. Every other GPIO on the header is in use.
+
+use assign_resources::assign_resources;
+use embassy_rp::{peripherals, Peri, Peripherals};
+
+/// Size of the flash chip; `build.rs` gives `memory.x` the same.
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+assign_resources! {
+    screen: ScreenResources {
+        spi: SPI1,
+        sck: PIN_10,
+        mosi: PIN_11,
+        miso: PIN_12,
+        cs: PIN_13,
+        reset: PIN_14,
+        pwr: PIN_15,
+        dc: PIN_9,
+        dma_tx: DMA_CH0,
+    },
+    adc: AdcResources {
+        spi: SPI0,
+        sck: PIN_2,
+        mosi: PIN_3,
+        miso: PIN_4,
+        cs: PIN_5,
+        dma_tx: DMA_CH1,
+        dma_rx: DMA_CH2,
+    },
+    buttons: ButtonResources {
+        button0: PIN_16,
+        button1: PIN_17,
+        button2: PIN_18,
+        button3: PIN_19,
+        button4: PIN_20,
+    },
+    encoders: EncoderResources {
+        enc0_a: PIN_0,
+        enc0_b: PIN_1,
+        enc1_a: PIN_6,
+        enc1_b: PIN_7,
+        enc2_a: PIN_8,
+        enc2_b: PIN_21,
+        enc3_a: PIN_22,
+        enc3_b: PIN_26,
+        enc4_a: PIN_27,
+        enc4_b: PIN_28,
+    },
+    led: LedResources {
+        led: PIN_25
+    },
+    usb: UsbResources {
+        usb: USB
+    },
+    flash: FlashResources {
+        flash: FLASH
+    },
+    watchdog: WatchdogResources {
+        watchdog: WATCHDOG
+    },
+    core1: Core1Resources {
+        core1: CORE1
+    }
+}
+
+/// Hands the peripherals out in the groups above.
+pub fn split(p: Peripherals) -> AssignedResources {
+    split_resources!(p)
+}
@@ -0,0 +1,54 @@
+//! Waveshare RP2040-Zero: 2M of flash and no screen, for a plain fader
+//! box. Only GP0-GP15 and GP26-GP29 are broken out; the RGB LED on GP16 is
+//! a WS2812, which needs PIO, so there is no status LED either.
+
+use assign_resources::assign_resources;
+use embassy_rp::{peripherals, Peri, Peripherals};
+
+/// Size of the flash chip; `build.rs` gives `memory.x` the same.
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+assign_resources! {
+    adc: AdcResources {
+        spi: SPI0,
+        sck: PIN_2,
+        mosi: PIN_3,
+        miso: PIN_4,
+        cs: PIN_5,
+        dma_tx: DMA_CH1,
+        dma_rx: DMA_CH2,
+    },
+    buttons: ButtonResources {
+        button0: PIN_6,
+        button1: PIN_7,
+        button2: PIN_8,
+        button3: PIN_9,
+        button4: PIN_10,
+    },
+    encoders: EncoderResources {
+        enc0_a: PIN_0,
+        enc0_b: PIN_1,
+        enc1_a: PIN_11,
+        enc1_b: PIN_12,
+        enc2_a: PIN_13,
+        enc2_b: PIN_14,
+        enc3_a: PIN_15,
+        enc3_b: PIN_26,
+        enc4_a: PIN_27,
+        enc4_b: PIN_28,
+    },
+    usb: UsbResources {
+        usb: USB
+    },
+    flash: FlashResources {
+        flash: FLASH
+    },
+    watchdog: WatchdogResources {
+        watchdog: WATCHDOG
+    }
+}
+
+/// Hands the peripherals out in the groups above.
+pub fn split(p: Peripherals) -> AssignedResources {
+    split_resources!(p)
+}
@@ -0,0 +1,66 @@
+//! Tenstar RP2040 Pro Micro: 16M of flash and the panel on SPI1.
+
+use assign_resources::assign_resources;
+use embassy_rp::{peripherals, Peri, Peripherals};
+
+/// Size of the flash chip; `build.rs` gives `memory.x` the same.
+pub const FLASH_SIZE: usize = 16 * 1024 * 1024;
+
+assign_resources! {
+    screen: ScreenResources {
+        spi: SPI1,
+        sck: PIN_14,
+        mosi: PIN_15,
+        miso: PIN_12,
+        cs: PIN_13,
+        reset: PIN_6,
+        pwr: PIN_9,
+        dc: PIN_16,
+        dma_tx: DMA_CH0,
+    },
+    adc: AdcResources {
+        spi: SPI0,
+        sck: PIN_2,
+        mosi: PIN_7,
+        miso: PIN_4,
+        cs: PIN_5,
+        dma_tx: DMA_CH1,
+        dma_rx: DMA_CH2,
+    },
+    buttons: ButtonResources {
+        button0: PIN_18,
+        button1: PIN_19,
+        button2: PIN_20,
+        button3: PIN_21,
+        button4: PIN_22,
+    },
+    encoders: EncoderResources {
+        enc0_a: PIN_0,
+        enc0_b: PIN_1,
+        enc1_a: PIN_3,
+        enc1_b: PIN_8,
+        enc2_a: PIN_10,
+        enc2_b: PIN_11,
+        enc3_a: PIN_17,
+        enc3_b: PIN_26,
+        enc4_a: PIN_27,
+        enc4_b: PIN_28,
+    },
+    usb: UsbResources {
+        usb: USB
+    },
+    flash: FlashResources {
+        flash: FLASH
+    },
+    watchdog: WatchdogResources {
+        watchdog: WATCHDOG
+    },
+    core1: Core1Resources {
+        core1: CORE1
+    }
+}
+
+/// Hands the peripherals out in the groups above.
+pub fn split(p: Peripherals) -> AssignedResources {
+    split_resources!(p)
+}
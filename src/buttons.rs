@@ -0,0 +1,155 @@
+//! Buttons next to the faders.
+//!
+//! `buttons_task` samples the pins of `crate::ButtonResources` (active low,
+//! on the internal pull-ups) and hands them to `deej_gfx::button::Button`,
+//! which tells short, double and long presses apart. What each press does
+//! is set per button in `BUTTONS`.
+//!
+//! Muting or soloing only changes what deej is sent: `apply` zeroes the
+//! silenced channels and the faders keep their position, so unmuting brings
+//! the volume back where it was. The state lives in RAM and so survives the
+//! host suspending; on resume the values go out with it applied.
+
+use deej_gfx::button::{Button, Press};
+use embassy_rp::gpio::{Input, Pull};
+use embassy_time::{Duration, Instant, Ticker};
+use portable_atomic::{AtomicI8, AtomicU8, Ordering};
+
+use crate::adc::{ADC_CHANNELS, ADC_VALUES};
+use crate::events::{self, Event};
+use crate::graphics;
+use crate::watchdog::{self, Task};
+use crate::ButtonResources;
+
+const SAMPLE_PERIOD: Duration = Duration::from_millis(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonAction {
+    /// Toggles sending 0 for the button's channel.
+    Mute,
+    /// Toggles sending 0 for every channel but the button's.
+    Solo,
+    /// Switches to the next of `THEMES`. What each fader controls comes
+    /// from `Config::targets` and stays as it is.
+    NextTheme,
+}
+
+#[derive(Clone, Copy)]
+pub struct ButtonCfg {
+    /// Index into `ADC_CHANNELS`.
+    pub channel: usize,
+    pub press: Option<ButtonAction>,
+    pub double_press: Option<ButtonAction>,
+    pub long_press: Option<ButtonAction>,
+}
+
+impl ButtonCfg {
+    const fn mute(channel: usize) -> Self {
+        Self {
+            channel,
+            press: Some(ButtonAction::Mute),
+            double_press: Some(ButtonAction::Solo),
+            long_press: Some(ButtonAction::NextTheme),
+        }
+    }
+
+    fn action(&self, press: Press) -> Option<ButtonAction> {
+        match press {
+            Press::Short => self.press,
+            Press::Double => self.double_press,
+            Press::Long => self.long_press,
+        }
+    }
+}
+
+/// One button per fader, in the order of the pins in `ButtonResources`.
+pub const BUTTONS: [ButtonCfg; 5] = [
+    ButtonCfg::mute(0),
+    ButtonCfg::mute(1),
+    ButtonCfg::mute(2),
+    ButtonCfg::mute(3),
+    ButtonCfg::mute(4),
+];
+
+const _: () = assert!(ADC_CHANNELS.len() <= 8, "MUTED is a u8 bit set");
+
+/// Bit set of muted channels. `run` toggles it, for `buttons_task` and for
+/// the fader gestures in `adc_task`.
+static MUTED: AtomicU8 = AtomicU8::new(0);
+/// The soloed channel, or -1.
+static SOLO: AtomicI8 = AtomicI8::new(-1);
+
+pub fn muted(channel: usize) -> bool {
+    MUTED.load(Ordering::Relaxed) & (1 << channel) != 0
+}
+
+/// Whether deej gets 0 for `channel`: it is muted or another one is soloed.
+pub fn silenced(channel: usize) -> bool {
+    let solo = SOLO.load(Ordering::Relaxed);
+    muted(channel) || (solo >= 0 && solo as usize != channel)
+}
+
+/// The values to send deej for the fader positions `values`.
+pub fn apply(mut values: [u32; ADC_VALUES.len()]) -> [u32; ADC_VALUES.len()] {
+    for (i, v) in values.iter_mut().enumerate() {
+        if silenced(i) {
+            *v = 0;
+        }
+    }
+    values
+}
+
+/// Does `action` for `channel`; also used by `gestures`.
+pub fn run(action: ButtonAction, channel: usize) {
+    match action {
+        ButtonAction::Mute => {
+            MUTED.fetch_xor(1 << channel, Ordering::Relaxed);
+            let state = if muted(channel) { "muted" } else { "unmuted" };
+            log::info!("ch{} {}", channel, state);
+        }
+        ButtonAction::Solo => {
+            let solo = if SOLO.load(Ordering::Relaxed) == channel as i8 {
+                log::info!("solo off");
+                -1
+            } else {
+                log::info!("ch{} solo", channel);
+                channel as i8
+            };
+            SOLO.store(solo, Ordering::Relaxed);
+        }
+        ButtonAction::NextTheme => {
+            graphics::next_theme();
+            return;
+        }
+    }
+    events::publish(Event::MuteChanged(channel));
+}
+
+#[embassy_executor::task]
+pub async fn buttons_task(res: ButtonResources) {
+    let pins = [
+        Input::new(res.button0, Pull::Up),
+        Input::new(res.button1, Pull::Up),
+        Input::new(res.button2, Pull::Up),
+        Input::new(res.button3, Pull::Up),
+        Input::new(res.button4, Pull::Up),
+    ];
+    let mut buttons = [Button::new(); BUTTONS.len()];
+
+    let mut ticker = Ticker::every(SAMPLE_PERIOD);
+    loop {
+        watchdog::check_in(Task::Buttons);
+
+        let now = Instant::now().as_millis() as u32;
+        for ((pin, button), cfg) in pins.iter().zip(&mut buttons).zip(&BUTTONS) {
+            let action = button
+                .update(now, pin.is_low())
+                .and_then(|press| cfg.action(press));
+            if let Some(action) = action {
+                run(action, cfg.channel);
+            }
+        }
+
+        ticker.next().await;
+    }
+}
@@ -0,0 +1,138 @@
+//! Text commands from the host, one per line on the CDC-ACM port:
+//!
+//! ```text
+//! theme            list the built-in themes, the active one starred
+//! theme <name>     switch themes; the choice survives power cycles
+//! crash            show the record the last crash left
+//! clear crash      forget it
+//! diag             run diagnostics: test pattern, raw ADC readout, noise
+//! diag stop        end them early
+//! perf             render, flush and ADC loop timings, dropped frames
+//! perf reset       start counting afresh
+//! perf overlay     switch the FPS and timing overlay on or off
+//! targets          list what each fader controls: id, name and icon
+//! target <n> id|name|icon <value>
+//!                  change it for fader n; the id is deej's (`master`,
+//!                  `mic`, `chrome.exe`, several separated by commas), the
+//!                  icon a 62x62 sprite from `assets/sprites.toml`
+//! mapping          the `slider_mapping` block for deej's `config.yaml`
+//! ```
+//!
+//! Replies go out as log lines on the same port. deej skips lines that are
+//! not fader values, so they do not get in its way.
+
+use core::cell::RefCell;
+use core::sync::atomic::Ordering;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use heapless::String;
+
+use crate::config::{self, CHANNELS};
+use crate::graphics::{self, THEME};
+use crate::themes::{self, THEMES};
+use crate::{crash, diagnostics, perf};
+
+const MAX_LINE: usize = 96;
+
+static LINE: Mutex<CriticalSectionRawMutex, RefCell<String<MAX_LINE>>> =
+    Mutex::new(RefCell::new(String::new()));
+
+/// Collects `data` into lines and runs every complete one.
+pub fn feed(data: &[u8]) {
+    for &b in data {
+        let line = LINE.lock(|line| {
+            let mut line = line.borrow_mut();
+            match b {
+                b'\r' | b'\n' => {
+                    let done = line.clone();
+                    line.clear();
+                    Some(done)
+                }
+                // Overlong lines are cut short and then rejected as unknown.
+                _ => {
+                    let _ = line.push(b as char);
+                    None
+                }
+            }
+        });
+        if let Some(line) = line {
+            run(line.trim());
+        }
+    }
+}
+
+fn run(line: &str) {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next()) {
+        (None, _) => {}
+        (Some("theme"), None) => {
+            let active = THEME.load(Ordering::Relaxed) as usize;
+            for (i, theme) in THEMES.iter().enumerate() {
+                let mark = if i == active { "*" } else { " " };
+                log::info!("{} {}", mark, theme.name);
+            }
+        }
+        (Some("theme"), Some(name)) => match themes::find(name) {
+            Some(index) => {
+                graphics::set_theme(index);
+                log::info!("theme {}", name);
+            }
+            None => log::info!("no theme {}", name),
+        },
+        (Some("crash"), None) => crash::report(),
+        (Some("clear"), Some("crash")) => crash::clear(),
+        (Some("diag"), None) => diagnostics::start(),
+        (Some("diag"), Some("stop")) => diagnostics::stop(),
+        (Some("perf"), None) => perf::report(),
+        (Some("perf"), Some("reset")) => perf::reset(),
+        (Some("perf"), Some("overlay")) => perf::toggle_overlay(),
+        (Some("targets"), None) => {
+            let targets = config::get(|c| c.targets.clone());
+            for (i, t) in targets.iter().enumerate() {
+                log::info!("{} {} {} {}", i, t.id, t.name, t.icon);
+            }
+        }
+        (Some("target"), Some(n)) => set_target(n, words.next(), words.next()),
+        (Some("mapping"), None) => {
+            // Logged outside the lock.
+            let config = config::get(|c| c.clone());
+            config.slider_mapping(|line| log::info!("{}", line));
+        }
+        (Some(cmd), _) => log::info!("unknown command {}", cmd),
+    }
+}
+
+fn set_target(n: &str, field: Option<&str>, value: Option<&str>) {
+    let Some(i) = n.parse::<usize>().ok().filter(|&i| i < CHANNELS) else {
+        log::info!("no fader {}", n);
+        return;
+    };
+    let (Some(field @ ("id" | "name" | "icon")), Some(value)) = (field, value) else {
+        log::info!("usage: target <n> id|name|icon <value>");
+        return;
+    };
+    if field == "icon" && graphics::find_icon(value).is_none() {
+        log::info!("no icon {}", value);
+        return;
+    }
+    let mut fits = false;
+    config::update(|c| {
+        let t = &mut c.targets[i];
+        fits = match field {
+            "id" => set(&mut t.id, value),
+            "name" => set(&mut t.name, value),
+            _ => set(&mut t.icon, value),
+        };
+    });
+    if fits {
+        log::info!("target {} {} {}", i, field, value);
+    } else {
+        log::info!("too long: {}", value);
+    }
+}
+
+/// Replaces `s` with `value` if it fits.
+fn set<const N: usize>(s: &mut String<N>, value: &str) -> bool {
+    String::try_from(value).map(|v| *s = v).is_ok()
+}
@@ -0,0 +1,95 @@
+//! The running configuration.
+//!
+//! The tunables (`ADC_CHANNELS` calibration, `NOISE_THRESHOLD`,
+//! `ACTIVE_CHANNEL_TTL`, `FRAME_PERIOD`, the USB strings, the theme and what
+//! each fader controls) live in a `deej_gfx::config::Config`, with the
+//! constants as its defaults. `settings` loads it from flash before the
+//! tasks start and saves it after every change.
+//!
+//! Read it with `get`, change it with `update`; tasks that need to act on a
+//! change look out for `Event::ConfigChanged`.
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use heapless::String;
+
+pub use deej_gfx::config::{ChannelConfig, Config, TargetConfig, UsbStrings, CHANNELS};
+
+use crate::adc::{ACTIVE_CHANNEL_TTL, ADC_CHANNELS, NOISE_THRESHOLD};
+use crate::events::{self, Event};
+use crate::graphics::FRAME_PERIOD;
+use crate::themes::THEMES;
+
+const _: () = assert!(ADC_CHANNELS.len() == CHANNELS);
+
+pub const USB_MANUFACTURER: &str = "kareraisu.me";
+pub const USB_PRODUCT: &str = "deej OLED";
+pub const USB_SERIAL_NUMBER: &str = "oledassfart";
+
+/// What each fader controls out of the box: deej target, name and icon.
+pub const TARGETS: [(&str, &str, &str); CHANNELS] = [
+    ("master", "System", "logo_system"),
+    ("mic", "Mic", "logo_mic"),
+    ("chrome.exe", "Browser", "logo_browser"),
+    ("steam.exe", "Steam", "logo_steam"),
+    ("spotify.exe", "Spotify", "logo_spotify"),
+];
+
+static CONFIG: Mutex<CriticalSectionRawMutex, RefCell<Option<Config>>> =
+    Mutex::new(RefCell::new(None));
+
+/// The configuration of a board nothing has been stored on.
+pub fn defaults() -> Config {
+    Config {
+        theme: string(THEMES[0].name),
+        channels: ADC_CHANNELS.map(|c| ChannelConfig {
+            invert: c.invert,
+            min: c.min,
+            max: c.max,
+        }),
+        noise_threshold: NOISE_THRESHOLD as u16,
+        active_channel_ttl_ms: ACTIVE_CHANNEL_TTL,
+        frame_period_ms: FRAME_PERIOD.as_millis() as u32,
+        usb: UsbStrings {
+            manufacturer: string(USB_MANUFACTURER),
+            product: string(USB_PRODUCT),
+            serial_number: string(USB_SERIAL_NUMBER),
+        },
+        targets: TARGETS.map(|(id, name, icon)| TargetConfig {
+            id: string(id),
+            name: string(name),
+            icon: string(icon),
+        }),
+    }
+}
+
+fn string<const N: usize>(s: &str) -> String<N> {
+    s.try_into().unwrap_or_default()
+}
+
+/// Sets the configuration at boot, before anything reads it.
+pub fn init(config: Config) {
+    CONFIG.lock(|c| *c.borrow_mut() = Some(config));
+}
+
+/// Reads the configuration through `f`.
+pub fn get<R>(f: impl FnOnce(&Config) -> R) -> R {
+    CONFIG.lock(|c| f(c.borrow().as_ref().expect("config::init runs first")))
+}
+
+/// Changes the configuration through `f` and publishes
+/// `Event::ConfigChanged`, if anything changed.
+pub fn update(f: impl FnOnce(&mut Config)) {
+    let changed = CONFIG.lock(|c| {
+        let mut c = c.borrow_mut();
+        let config = c.as_mut().expect("config::init runs first");
+        let before = config.clone();
+        f(config);
+        *config != before
+    });
+    if changed {
+        events::publish(Event::ConfigChanged);
+    }
+}
@@ -0,0 +1,209 @@
+//! What happens when the firmware goes down.
+//!
+//! The panic and HardFault handlers put a `deej_gfx::crash::CrashRecord` on
+//! the panel, on boards with a screen, keep it in the flash sector below the
+//! settings (left out of the firmware image by `memory.x`) and reboot. On
+//! the next boot the record is reported over the USB log; it stays in flash,
+//! and can be shown again with `crash`, until the host sends `clear crash`.
+//!
+//! The watchdog is stopped first, as all that takes longer than its
+//! timeout. A reset by the watchdog (see `watchdog`) leaves no time for any
+//! of it, so its record is written on the way back up instead.
+//!
+//! Only core0 may write the flash, as embassy pauses core1 while it does. A
+//! crash on core1, where the frames are drawn, leaves its record in RAM that
+//! the reset does not clear, and `init` saves it on the way back up like a
+//! watchdog record. A crash on core0 holds core1 in reset once the record is
+//! saved, so `render_task` cannot flush over the crash screen.
+
+use core::cell::Cell;
+use core::mem::MaybeUninit;
+use core::panic::PanicInfo;
+use core::ptr::addr_of_mut;
+
+use cortex_m::peripheral::SCB;
+use cortex_m_rt::{exception, ExceptionFrame};
+use deej_gfx::crash::CrashRecord;
+use embassy_rp::flash::{Flash, ERASE_SIZE};
+use embassy_rp::pac;
+use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, ThreadModeRawMutex};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+use portable_atomic::{AtomicU8, Ordering};
+
+use crate::settings::{SettingsFlash, STORE_OFFSET};
+use crate::FlashResources;
+use crate::{power, watchdog};
+
+#[cfg(feature = "screen")]
+mod panel;
+
+const CRASH_OFFSET: u32 = STORE_OFFSET - ERASE_SIZE as u32;
+
+/// Time after boot before the record is logged, for the host to open the
+/// port.
+const REPORT_DELAY: Duration = Duration::from_secs(5);
+
+/// The record found at boot, until it is cleared.
+static LAST_CRASH: Mutex<CriticalSectionRawMutex, Cell<Option<CrashRecord>>> =
+    Mutex::new(Cell::new(None));
+
+/// Raised by `clear`; `settings::settings_task` owns the flash and erases
+/// the record.
+pub static CLEAR_CRASH: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// The core whose handler ran first, plus one, or 0. A crash while handling
+/// one goes straight to the reboot, and a crash on the other core waits for
+/// it.
+static CRASHING: AtomicU8 = AtomicU8::new(0);
+
+/// A crash on core1, for `init` to save after the reset. `.uninit` is left
+/// as it is at boot, so this holds whatever the RAM did: `from_bytes` tells.
+#[link_section = ".uninit.CORE1_CRASH"]
+static mut CORE1_CRASH: MaybeUninit<[u8; CrashRecord::BYTES]> = MaybeUninit::uninit();
+
+/// Whether a crash handler is running. Core1's tasks stop while it shows
+/// its crash, which `watchdog_task` must not take for a missed deadline.
+pub fn crashing() -> bool {
+    CRASHING.load(Ordering::Relaxed) != 0
+}
+
+/// Reads the record the last crash left, if any, or records the core1 crash
+/// kept in RAM or the watchdog reset caused by `late`.
+pub fn init(flash: &mut SettingsFlash, late: Option<watchdog::Task>) {
+    let kept = take_core1_crash().or_else(|| late.map(|task| CrashRecord::watchdog(task.name())));
+    let record = match kept {
+        Some(record) => {
+            write(flash, &record);
+            Some(record)
+        }
+        None => {
+            let mut bytes = [0u8; CrashRecord::BYTES];
+            flash
+                .blocking_read(CRASH_OFFSET, &mut bytes)
+                .ok()
+                .and_then(|_| CrashRecord::from_bytes(&bytes))
+        }
+    };
+    LAST_CRASH.lock(|c| c.set(record));
+}
+
+/// The record core1 left in `CORE1_CRASH`, which is cleared so a later
+/// reset does not find it again.
+fn take_core1_crash() -> Option<CrashRecord> {
+    let slot = addr_of_mut!(CORE1_CRASH).cast::<[u8; CrashRecord::BYTES]>();
+    // SAFETY: core1 is not started yet, and any bytes are a valid array.
+    let bytes = unsafe { slot.read_volatile() };
+    unsafe { slot.write_volatile([0; CrashRecord::BYTES]) };
+    CrashRecord::from_bytes(&bytes)
+}
+
+/// Logs the record found at boot once the host has had time to connect.
+#[embassy_executor::task]
+pub async fn report_task() {
+    Timer::after(REPORT_DELAY).await;
+    if LAST_CRASH.lock(|c| c.get()).is_some() {
+        report();
+    }
+}
+
+/// Logs the stored record.
+pub fn report() {
+    match LAST_CRASH.lock(|c| c.get()) {
+        Some(record) => log::warn!("last crash: {}", record),
+        None => log::info!("no crash recorded"),
+    }
+}
+
+/// Forgets the stored record and has it erased from flash.
+pub fn clear() {
+    LAST_CRASH.lock(|c| c.set(None));
+    CLEAR_CRASH.signal(());
+}
+
+/// Erases the record sector, unless it is erased already.
+pub fn erase(flash: &mut SettingsFlash) -> Result<(), embassy_rp::flash::Error> {
+    let mut bytes = [0u8; CrashRecord::BYTES];
+    flash.blocking_read(CRASH_OFFSET, &mut bytes)?;
+    if bytes.iter().all(|&b| b == 0xFF) {
+        return Ok(());
+    }
+    flash.blocking_erase(CRASH_OFFSET, CRASH_OFFSET + ERASE_SIZE as u32)
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    let (file, line) = info.location().map_or(("", 0), |l| (l.file(), l.line()));
+    go_down(&CrashRecord::panic(file, line, info.message()))
+}
+
+#[exception]
+unsafe fn HardFault(ef: &ExceptionFrame) -> ! {
+    go_down(&CrashRecord::hard_fault([
+        ef.r0(),
+        ef.r1(),
+        ef.r2(),
+        ef.r3(),
+        ef.r12(),
+        ef.lr(),
+        ef.pc(),
+        ef.xpsr(),
+    ]))
+}
+
+fn go_down(record: &CrashRecord) -> ! {
+    cortex_m::interrupt::disable();
+    // The flash write and the panel take longer than the watchdog's timeout,
+    // which would cut the crash screen short.
+    watchdog::stop();
+    let core = pac::SIO.cpuid().read() as u8;
+    match CRASHING.compare_exchange(0, core + 1, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => {}
+        Err(first) if first == core + 1 => SCB::sys_reset(),
+        Err(_) => loop {
+            cortex_m::asm::wfe();
+        },
+    }
+
+    power::restore_clocks();
+    defmt::error!("{}", defmt::Display2Format(record));
+
+    // SAFETY: nothing runs after this but the reboot, so whatever the tasks
+    // did with these peripherals no longer matters.
+    let r = unsafe { crate::steal_crash_resources() };
+    if core == 0 {
+        save(record, r.flash);
+        #[cfg(feature = "screen")]
+        stop_core1();
+    } else {
+        let slot = addr_of_mut!(CORE1_CRASH).cast::<[u8; CrashRecord::BYTES]>();
+        // SAFETY: only the core that claimed `CRASHING` gets here, and `init`
+        // reads the slot before core1 starts.
+        unsafe { slot.write_volatile(record.to_bytes()) };
+    }
+    #[cfg(feature = "screen")]
+    panel::show(record, r.screen);
+    SCB::sys_reset()
+}
+
+/// Holds core1 in reset, for the panel to be drawn without `render_task`.
+#[cfg(feature = "screen")]
+fn stop_core1() {
+    pac::PSM.frce_off().modify(|w| w.set_proc1(true));
+    while !pac::PSM.frce_off().read().proc1() {
+        cortex_m::asm::nop();
+    }
+    // Core1 may have been inside a critical section, whose spinlock nothing
+    // will release now.
+    pac::SIO.spinlock(31).write_value(1);
+}
+
+fn save(record: &CrashRecord, res: FlashResources) {
+    write(&mut Flash::new_blocking(res.flash), record);
+}
+
+fn write(flash: &mut SettingsFlash, record: &CrashRecord) {
+    let _ = flash.blocking_erase(CRASH_OFFSET, CRASH_OFFSET + ERASE_SIZE as u32);
+    let _ = flash.blocking_write(CRASH_OFFSET, &record.to_bytes());
+}
@@ -0,0 +1,86 @@
+//! The crash screen, drawn without the executor.
+
+use deej_gfx::crash::CrashRecord;
+use embassy_futures::block_on;
+use embassy_rp::gpio::{Level, Output};
+use embassy_rp::spi::Spi;
+use embedded_hal_bus::spi::ExclusiveDevice;
+
+use crate::display::{Display, Panel};
+use crate::screen::{self, Frame, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::ScreenResources;
+
+/// How long the crash screen stays up before the reboot.
+const SHOW_MS: u32 = 5_000;
+
+/// Draws the record with blocking SPI, since the executor is gone, and
+/// leaves it up for `SHOW_MS`.
+pub fn show(record: &CrashRecord, res: ScreenResources) {
+    let spi = Spi::new_blocking_txonly(res.spi, res.sck, res.mosi, screen::spi_config());
+    let cs = Output::new(res.cs, Level::Low);
+    let Ok(spi_dev) = ExclusiveDevice::new_no_delay(BlockingBus(spi), cs) else {
+        return;
+    };
+    let dc = Output::new(res.dc, Level::Low);
+    let reset = Output::new(res.reset, Level::Low);
+    let pwr = Output::new(res.pwr, Level::Low);
+    let mut panel = Panel::new(spi_dev, dc, reset, pwr);
+
+    let mut frame = Frame::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+    record.draw(&mut frame);
+
+    // The driver is async, but over a blocking bus its futures finish on the
+    // first poll.
+    let _ = block_on(async {
+        panel.init(&mut SpinDelay).await?;
+        panel.flush(&frame).await
+    });
+    SpinDelay::wait_us(SHOW_MS * 1000);
+}
+
+/// A blocking SPI bus behind the async interface the display drivers use.
+struct BlockingBus<B>(B);
+
+impl<B: embedded_hal::spi::ErrorType> embedded_hal::spi::ErrorType for BlockingBus<B> {
+    type Error = B::Error;
+}
+
+impl<B: embedded_hal::spi::SpiBus> embedded_hal_async::spi::SpiBus for BlockingBus<B> {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.read(words)
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.write(words)
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.0.transfer(read, write)
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.transfer_in_place(words)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.0.flush()
+    }
+}
+
+/// Busy-waits, as the time driver's interrupt may be off.
+struct SpinDelay;
+
+impl SpinDelay {
+    fn wait_us(us: u32) {
+        let cycles_per_us = embassy_rp::clocks::clk_sys_freq() / 1_000_000;
+        for _ in 0..us {
+            cortex_m::asm::delay(cycles_per_us);
+        }
+    }
+}
+
+impl embedded_hal_async::delay::DelayNs for SpinDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        Self::wait_us(ns.div_ceil(1000));
+    }
+}
@@ -1,17 +1,20 @@
 use core::fmt::Write as _;
-use core::sync::atomic::Ordering;
+use core::sync::atomic::{AtomicBool, Ordering};
 use heapless::String;
 use static_cell::StaticCell;
 
+use embassy_futures::select::{select, Either};
 use embassy_rp::peripherals::USB;
 use embassy_rp::usb::Driver;
 use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
-use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
 use embassy_usb::{class::cdc_acm, Builder, Config as UsbConfig, UsbDevice};
-use embassy_usb_logger::MAX_PACKET_SIZE;
+use embassy_usb_logger::{ReceiverHandler, UsbLogger, MAX_PACKET_SIZE};
 
-use crate::adc::{ADC_FORCE_PUSH, ADC_VALUES};
-use crate::graphics::{ScreenState, SCREEN_STATE};
+use crate::adc::ADC_VALUES;
+use crate::commands;
+use crate::config::{self, UsbStrings};
+use crate::events::{self, Event, HostState};
 use crate::{Irqs, UsbResources};
 
 static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
@@ -19,36 +22,76 @@ static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
 static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
 static LOG_STATE: StaticCell<cdc_acm::State> = StaticCell::new();
 static USB_DEVICE: StaticCell<UsbDevice<'static, Driver<'static, USB>>> = StaticCell::new();
+static LOGGER: StaticCell<UsbLogger<1024, CommandHandler>> = StaticCell::new();
+static USB_STRINGS: StaticCell<UsbStrings> = StaticCell::new();
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum HostState {
-    Active,
-    Suspended,
+static HOST_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Raised by `adc_task` when a fader moves while the host is suspended.
+static WAKE_HOST: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// Whether the host is there, for diagnostics and the ADC poll rate; the
+/// tasks that act on it going away follow `Event::HostStateChanged`.
+pub fn host_active() -> bool {
+    HOST_ACTIVE.load(Ordering::Relaxed)
 }
 
-pub static HOST_STATE_CH: Channel<ThreadModeRawMutex, HostState, 1> = Channel::new();
+/// Asks a suspended host to resume, if it lets the device do that.
+pub fn request_wakeup() {
+    WAKE_HOST.signal(());
+}
 
 #[embassy_executor::task]
 pub async fn usb_task(dev: &'static mut UsbDevice<'static, Driver<'static, USB>>) -> ! {
-    let tx = HOST_STATE_CH.sender();
-
     loop {
-        tx.send(HostState::Active).await;
-        ADC_FORCE_PUSH.store(true, Ordering::Relaxed);
-        SCREEN_STATE.store(ScreenState::INTRO as u8, Ordering::Relaxed);
+        HOST_ACTIVE.store(true, Ordering::Relaxed);
+        events::publish(Event::HostStateChanged(HostState::Active));
 
         dev.run_until_suspend().await;
 
-        tx.send(HostState::Suspended).await;
+        HOST_ACTIVE.store(false, Ordering::Relaxed);
+        events::publish(Event::HostStateChanged(HostState::Suspended));
+
+        loop {
+            match select(dev.wait_resume(), WAKE_HOST.wait()).await {
+                Either::First(()) => break,
+                Either::Second(()) => {
+                    if dev.remote_wakeup().await.is_err() {
+                        log::info!("host does not allow remote wakeup");
+                    }
+                }
+            }
+        }
+    }
+}
 
-        dev.wait_resume().await;
+/// Hands whatever the host writes to the port to `commands`.
+struct CommandHandler;
+
+impl ReceiverHandler for CommandHandler {
+    async fn handle_data(&self, data: &[u8]) {
+        commands::feed(data);
+    }
+
+    fn new() -> Self {
+        Self
     }
 }
 
+/// What `embassy_usb_logger::with_class!` does, plus the command handler.
 #[embassy_executor::task]
 pub async fn logger_task(class: cdc_acm::CdcAcmClass<'static, Driver<'static, USB>>) {
-    let fut = embassy_usb_logger::with_class!(1024, log::LevelFilter::Info, class);
-    fut.await;
+    let logger = LOGGER.init(UsbLogger::new());
+    logger.with_handler(CommandHandler);
+    let logger: &'static UsbLogger<1024, CommandHandler> = logger;
+
+    // SAFETY: nothing else sets a logger, and this runs once.
+    unsafe {
+        let _ =
+            log::set_logger_racy(logger).map(|()| log::set_max_level_racy(log::LevelFilter::Info));
+    }
+
+    logger.create_future_from_class(class).await;
 }
 
 pub fn init(
@@ -59,11 +102,14 @@ pub fn init(
 ) {
     let driver = Driver::new(res.usb, Irqs);
 
+    let strings: &'static UsbStrings = USB_STRINGS.init(config::get(|c| c.usb.clone()));
+
     let mut config = UsbConfig::new(0xc0de, 0xcafe);
-    config.manufacturer = Some("kareraisu.me");
-    config.product = Some("deej OLED");
-    config.serial_number = Some("oledassfart");
+    config.manufacturer = Some(strings.manufacturer.as_str());
+    config.product = Some(strings.product.as_str());
+    config.serial_number = Some(strings.serial_number.as_str());
     config.max_power = 100;
+    config.supports_remote_wakeup = true;
     config.max_packet_size_0 = 64;
 
     let mut builder = Builder::new(
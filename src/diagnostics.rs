@@ -0,0 +1,166 @@
+//! Diagnostics mode, for telling a bad fader, ribbon cable or panel from a
+//! software problem.
+//!
+//! Started by holding fader `adc::DIAGNOSTICS_FADER` at full volume while the
+//! board boots, or by the `diag` command; `diag stop` ends it early. For
+//! `DURATION` the screen shows the test pattern and then a live readout of
+//! every channel, `adc_task` samples the channels every `SAMPLE_PERIOD` and
+//! sends deej nothing, and every `REPORT_PERIOD` the noise statistics, the
+//! MCP3008's health and the USB state are logged.
+
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "screen")]
+use deej_gfx::diagnostics;
+use deej_gfx::diagnostics::NoiseStats;
+use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, ThreadModeRawMutex};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Ticker};
+
+use crate::adc::ADC_CHANNELS;
+use crate::deej_usb;
+#[cfg(feature = "screen")]
+use crate::screen::Frame;
+
+/// How long diagnostics run unless stopped.
+const DURATION: Duration = Duration::from_secs(60);
+
+/// How long the test pattern is shown before the readout.
+#[cfg(feature = "screen")]
+const TEST_PATTERN_TIME: Duration = Duration::from_secs(3);
+
+/// Channel sampling period while running; the statistics window is
+/// `REPORT_PERIOD`.
+pub const SAMPLE_PERIOD: Duration = Duration::from_millis(10);
+const REPORT_PERIOD: Duration = Duration::from_secs(1);
+
+const CHANNELS: usize = ADC_CHANNELS.len();
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static START: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+struct State {
+    started: Instant,
+    /// Readings since the last report.
+    stats: [NoiseStats; CHANNELS],
+}
+
+static STATE: Mutex<CriticalSectionRawMutex, RefCell<State>> = Mutex::new(RefCell::new(State {
+    started: Instant::from_ticks(0),
+    stats: [NoiseStats::new(); CHANNELS],
+}));
+
+pub fn running() -> bool {
+    RUNNING.load(Ordering::Relaxed)
+}
+
+pub fn start() {
+    if running() {
+        log::info!("diagnostics already running");
+    } else {
+        START.signal(());
+    }
+}
+
+pub fn stop() {
+    RUNNING.store(false, Ordering::Relaxed);
+}
+
+/// Adds a reading of `channel`, `None` for a failed read.
+pub fn record(channel: usize, raw: Option<u16>) {
+    STATE.lock(|s| s.borrow_mut().stats[channel].add(raw));
+}
+
+/// Draws the test pattern or the readout, depending on how long
+/// diagnostics have been running.
+#[cfg(feature = "screen")]
+pub fn draw(frame: &mut Frame) {
+    let (elapsed, stats) = STATE.lock(|s| {
+        let s = s.borrow();
+        (s.started.elapsed(), s.stats)
+    });
+
+    if elapsed < TEST_PATTERN_TIME {
+        diagnostics::draw_test_pattern(frame);
+    } else {
+        let status = format_args!(
+            "DIAG {:2}s mcp3008 {} usb {}",
+            elapsed.as_secs(),
+            mcp_health(&stats),
+            usb_state()
+        );
+        diagnostics::draw_readout(frame, status, &stats);
+    }
+}
+
+/// What the readings say about the MCP3008 and its cable. With MISO open or
+/// shorted every channel reads the same rail.
+fn mcp_health(stats: &[NoiseStats]) -> &'static str {
+    let reads: u32 = stats.iter().map(|s| s.reads()).sum();
+    let errors: u32 = stats.iter().map(|s| s.errors()).sum();
+    let all_at = |rail| stats.iter().all(|s| s.range() == Some((rail, rail)));
+
+    if reads == 0 && errors > 0 {
+        "not answering"
+    } else if errors > 0 {
+        "read errors"
+    } else if reads > 0 && (all_at(0) || all_at(1023)) {
+        "stuck"
+    } else {
+        "ok"
+    }
+}
+
+fn usb_state() -> &'static str {
+    if deej_usb::host_active() {
+        "active"
+    } else {
+        "suspended"
+    }
+}
+
+#[embassy_executor::task]
+pub async fn diagnostics_task() {
+    loop {
+        START.wait().await;
+
+        STATE.lock(|s| {
+            let mut s = s.borrow_mut();
+            s.started = Instant::now();
+            s.stats = [NoiseStats::new(); CHANNELS];
+        });
+        RUNNING.store(true, Ordering::Relaxed);
+        log::info!(
+            "diagnostics for {} s, `diag stop` ends them",
+            DURATION.as_secs()
+        );
+
+        let mut ticker = Ticker::every(REPORT_PERIOD);
+        loop {
+            ticker.next().await;
+
+            let (elapsed, stats) = STATE.lock(|s| {
+                let mut s = s.borrow_mut();
+                let stats = s.stats;
+                s.stats = [NoiseStats::new(); CHANNELS];
+                (s.started.elapsed(), stats)
+            });
+            for (i, s) in stats.iter().enumerate() {
+                log::info!("diag ch{} {}", i, s);
+            }
+            log::info!("diag mcp3008 {}", mcp_health(&stats));
+            log::info!("diag usb {}", usb_state());
+
+            if !running() || elapsed >= DURATION {
+                break;
+            }
+        }
+
+        // `prepare_frame_task` and `adc_task` see this and go back to the
+        // usual screen and fresh values for deej.
+        RUNNING.store(false, Ordering::Relaxed);
+        log::info!("diagnostics done");
+    }
+}
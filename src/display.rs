@@ -0,0 +1,57 @@
+//! The panel the frames go to, picked by one `display-*` cargo feature.
+//!
+//! The scenes always draw into a Gray4 frame of the panel's size; each
+//! driver turns that into whatever its controller wants and keeps track of
+//! what is already on the glass.
+//!
+//! | feature           | controller | pixels  | colour          |
+//! |-------------------|------------|---------|-----------------|
+//! | `display-ssd1322` | SSD1322    | 256x64  | Gray4           |
+//! | `display-ssd1327` | SSD1327    | 128x128 | Gray4           |
+//! | `display-ssd1306` | SSD1306    | 128x64  | 1bpp, dithered  |
+
+use deej_gfx::frame::Gray4Frame;
+use embedded_hal_async::delay::DelayNs;
+
+#[cfg(feature = "display-ssd1306")]
+pub use crate::ssd1306::{Ssd1306 as Panel, HEIGHT, WIDTH};
+#[cfg(feature = "display-ssd1322")]
+pub use crate::ssd1322::{Ssd1322 as Panel, HEIGHT, WIDTH};
+#[cfg(feature = "display-ssd1327")]
+pub use crate::ssd1327::{Ssd1327 as Panel, HEIGHT, WIDTH};
+
+#[cfg(not(any(
+    feature = "display-ssd1306",
+    feature = "display-ssd1322",
+    feature = "display-ssd1327"
+)))]
+compile_error!("enable one display-* feature");
+
+#[cfg(any(
+    all(feature = "display-ssd1306", feature = "display-ssd1322"),
+    all(feature = "display-ssd1306", feature = "display-ssd1327"),
+    all(feature = "display-ssd1322", feature = "display-ssd1327"),
+))]
+compile_error!("enable only one display-* feature");
+
+#[derive(Debug)]
+pub enum DisplayError {
+    Spi,
+    Pin,
+}
+
+/// A panel driver. Every driver module also has `WIDTH` and `HEIGHT`
+/// consts, its visible pixels, and the driver a `new(spi, dc, reset, pwr)`
+/// constructor taking the pins of `crate::ScreenResources`.
+pub trait Display {
+    /// Resets and sets up the controller and turns the panel on.
+    async fn init(&mut self, delay: &mut impl DelayNs) -> Result<(), DisplayError>;
+
+    /// Brings the panel up to date with `frame`, sending only what changed
+    /// where the driver can tell.
+    async fn flush<const N: usize>(&mut self, frame: &Gray4Frame<N>) -> Result<(), DisplayError>;
+
+    /// Puts the controller to sleep and cuts the panel supply; `init` brings
+    /// it back, and the next flush sends the whole frame.
+    async fn sleep(&mut self) -> Result<(), DisplayError>;
+}
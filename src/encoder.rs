@@ -0,0 +1,129 @@
+//! Rotary encoders in place of faders, with the `encoders` feature.
+//!
+//! Each encoder in `ENCODERS` stands in for one channel. Its `encoder_task`
+//! wakes on the edges of the A and B pins (GPIO interrupts), decodes them
+//! with `deej_gfx::encoder` and keeps a 0..=1023 position, which `adc_task`
+//! reads in place of the MCP3008; from there on the channel is handled like
+//! a fader. The encoder's push switch goes on the channel's button pin in
+//! `crate::ButtonResources`, where it toggles mute.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use deej_gfx::encoder::Acceleration;
+
+use crate::adc::ADC_VALUES;
+
+#[derive(Clone, Copy)]
+#[cfg_attr(not(feature = "encoders"), allow(dead_code))]
+pub struct EncoderCfg {
+    /// Index into `ADC_CHANNELS`.
+    pub channel: usize,
+    /// Position change per detent when turned slowly.
+    pub step: u16,
+    pub acceleration: Acceleration,
+}
+
+#[cfg_attr(not(feature = "encoders"), allow(dead_code))]
+impl EncoderCfg {
+    /// 64 slow detents from silent to full, four times as few when spun.
+    const fn for_channel(channel: usize) -> Self {
+        Self {
+            channel,
+            step: 16,
+            acceleration: Acceleration {
+                window_ms: 120,
+                max_factor: 4,
+            },
+        }
+    }
+}
+
+/// The encoders, in the order of the pin pairs in `EncoderResources`.
+#[cfg(feature = "encoders")]
+pub const ENCODERS: &[EncoderCfg] = &[
+    EncoderCfg::for_channel(0),
+    EncoderCfg::for_channel(1),
+    EncoderCfg::for_channel(2),
+    EncoderCfg::for_channel(3),
+    EncoderCfg::for_channel(4),
+];
+#[cfg(not(feature = "encoders"))]
+pub const ENCODERS: &[EncoderCfg] = &[];
+
+/// Where the encoders start after a boot.
+const INITIAL_VALUE: u16 = 512;
+
+static POSITIONS: [AtomicU32; ADC_VALUES.len()] =
+    [const { AtomicU32::new(INITIAL_VALUE as u32) }; ADC_VALUES.len()];
+
+/// The position of the encoder standing in for `channel`, if one does.
+pub fn value(channel: usize) -> Option<u32> {
+    ENCODERS
+        .iter()
+        .any(|e| e.channel == channel)
+        .then(|| POSITIONS[channel].load(Ordering::Relaxed))
+}
+
+/// Starts an `encoder_task` for each of `ENCODERS`.
+#[cfg(feature = "encoders")]
+pub fn spawn(spawner: embassy_executor::Spawner, res: crate::EncoderResources) {
+    use embassy_rp::gpio::{Input, Pull};
+
+    use crate::watchdog::Task;
+
+    let pins = [
+        (
+            Input::new(res.enc0_a, Pull::Up),
+            Input::new(res.enc0_b, Pull::Up),
+        ),
+        (
+            Input::new(res.enc1_a, Pull::Up),
+            Input::new(res.enc1_b, Pull::Up),
+        ),
+        (
+            Input::new(res.enc2_a, Pull::Up),
+            Input::new(res.enc2_b, Pull::Up),
+        ),
+        (
+            Input::new(res.enc3_a, Pull::Up),
+            Input::new(res.enc3_b, Pull::Up),
+        ),
+        (
+            Input::new(res.enc4_a, Pull::Up),
+            Input::new(res.enc4_b, Pull::Up),
+        ),
+    ];
+    for (i, ((a, b), cfg)) in pins.into_iter().zip(ENCODERS).enumerate() {
+        spawner.spawn(encoder_task(Task::encoder(i), *cfg, a, b).unwrap());
+    }
+}
+
+#[cfg(feature = "encoders")]
+#[embassy_executor::task(pool_size = 5)]
+pub async fn encoder_task(
+    task: crate::watchdog::Task,
+    cfg: EncoderCfg,
+    mut a: embassy_rp::gpio::Input<'static>,
+    mut b: embassy_rp::gpio::Input<'static>,
+) {
+    use deej_gfx::encoder::{EncoderValue, Quadrature};
+    use embassy_futures::select::select;
+    use embassy_time::Instant;
+
+    use crate::watchdog;
+
+    let mut quadrature = Quadrature::new();
+    let mut position = EncoderValue::new(INITIAL_VALUE, cfg.step, cfg.acceleration);
+
+    loop {
+        let detents = quadrature.update(a.is_high(), b.is_high());
+        if detents != 0 {
+            let now = Instant::now().as_millis() as u32;
+            let v = position.turn(detents, now);
+            POSITIONS[cfg.channel].store(v as u32, Ordering::Relaxed);
+        }
+
+        // A knob left alone waits here for good.
+        watchdog::idle(task, select(a.wait_for_any_edge(), b.wait_for_any_edge())).await;
+    }
+}
@@ -0,0 +1,63 @@
+//! The event bus between tasks.
+//!
+//! State that one task changes and others act on goes out as a
+//! `deej_gfx::events::Event` on `BUS`, rather than through globals each
+//! task reads and rewrites:
+//!
+//! - `adc_task` publishes `ActiveChannelChanged`;
+//! - `usb_task` publishes `HostStateChanged`;
+//! - `config::update` publishes `ConfigChanged`;
+//! - `prepare_frame_task` publishes `SceneFinished`;
+//! - `buttons::run` publishes `MuteChanged`.
+//!
+//! Boards without a screen have no `render_task` or `prepare_frame_task`,
+//! and only boards with a status LED have `status_led_task`; their
+//! subscriber slots are left empty.
+//!
+//! Subscribers are made in `main` before any task starts, so none misses
+//! the first host state, and handed to the task that reads them. Publishing
+//! never waits: a subscriber that falls more than `CAPACITY` events behind
+//! loses the oldest ones and is told how many, and reads what it needs from
+//! `deej_usb` and `SCREEN_STATE` instead.
+//!
+//! The fader positions change every ADC loop while a fader moves, which on
+//! the bus would push the rare events out of the queues of the tasks that
+//! ignore them; they go in `FADERS` instead.
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pubsub::{PubSubChannel, Subscriber};
+use embassy_sync::watch::Watch;
+
+use deej_gfx::config::CHANNELS;
+pub use deej_gfx::events::{Event, HostState};
+use deej_gfx::scene::ScreenState;
+
+/// Events kept for the slowest subscriber.
+const CAPACITY: usize = 24;
+/// `adc_task`, `settings_task`, `power_task`, `render_task`,
+/// `prepare_frame_task` and `status_led_task`.
+const SUBSCRIBERS: usize = 6;
+
+// Publishers are all immediate, so none are counted.
+static BUS: PubSubChannel<CriticalSectionRawMutex, Event, CAPACITY, SUBSCRIBERS, 0> =
+    PubSubChannel::new();
+
+pub type Events = Subscriber<'static, CriticalSectionRawMutex, Event, CAPACITY, SUBSCRIBERS, 0>;
+
+/// The fader positions, 0..=1023, as of the last one to move past the noise
+/// threshold. Written by `adc_task`, read by `prepare_frame_task`.
+pub static FADERS: Watch<CriticalSectionRawMutex, [u16; CHANNELS], 0> = Watch::new();
+
+/// The screen state as of `prepare_frame_task`'s last frame, for the tasks
+/// that follow it through `SceneFinished` to catch up after losing events.
+pub static SCREEN_STATE: Watch<CriticalSectionRawMutex, ScreenState, 0> = Watch::new();
+
+pub fn publish(event: Event) {
+    BUS.immediate_publisher().publish_immediate(event);
+}
+
+/// A new subscriber, seeing the events published from now on. There are
+/// `SUBSCRIBERS` of them.
+pub fn subscribe() -> Events {
+    BUS.subscriber().expect("event subscribers")
+}
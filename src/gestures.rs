@@ -0,0 +1,48 @@
+//! Gestures on the faders.
+//!
+//! `adc_task` feeds every reading to a `deej_gfx::gesture::GestureDetector`
+//! per channel and hands what it reports to `run`. What each gesture does is
+//! set per channel in `GESTURES`: a button action, diagnostics for
+//! calibrating, the next theme or a line for the host.
+//!
+//! A flick passes through the bottom, so deej briefly gets a low value for
+//! the channel before the fader is back; a mute bound to it follows once the
+//! window for a double flick has closed.
+
+use deej_gfx::gesture::{Gesture, GestureBindings};
+
+use crate::buttons::{self, ButtonAction};
+use crate::{diagnostics, graphics};
+
+pub type GestureAction = deej_gfx::gesture::GestureAction<ButtonAction>;
+pub type GestureCfg = GestureBindings<ButtonAction>;
+
+/// Flick to mute, everything else to the host.
+const DEFAULT: GestureCfg = GestureCfg {
+    flick: Some(GestureAction::Button(ButtonAction::Mute)),
+    double_flick: Some(GestureAction::Host),
+    hold_max: Some(GestureAction::Host),
+};
+
+/// Per channel, in the order of `ADC_CHANNELS`.
+pub const GESTURES: [GestureCfg; 5] = [DEFAULT; 5];
+
+fn name(gesture: Gesture) -> &'static str {
+    match gesture {
+        Gesture::Flick => "flick",
+        Gesture::DoubleFlick => "double-flick",
+        Gesture::HoldMax => "hold-max",
+    }
+}
+
+/// Does what `gesture` on `channel` is bound to.
+pub fn run(gesture: Gesture, channel: usize) {
+    match GESTURES[channel].action(gesture) {
+        Some(GestureAction::Button(action)) => buttons::run(action, channel),
+        // `adc_task` sees it running from its next loop on.
+        Some(GestureAction::Calibrate) => diagnostics::start(),
+        Some(GestureAction::NextTheme) => graphics::next_theme(),
+        Some(GestureAction::Host) => log::info!("gesture ch{} {}", channel, name(gesture)),
+        None => {}
+    }
+}
@@ -1,47 +1,67 @@
 #![no_std]
 #![no_main]
 
-use assign_resources::assign_resources;
 use embassy_executor::Spawner;
+#[cfg(feature = "screen")]
+use embassy_executor::Executor;
+#[cfg(feature = "screen")]
+use embassy_rp::multicore::{spawn_core1, Stack};
 use embassy_rp::peripherals::USB;
 use embassy_rp::usb::InterruptHandler;
-use embassy_rp::Peri;
-use embassy_rp::{bind_interrupts, peripherals};
-use {defmt_rtt as _, panic_probe as _};
+use embassy_rp::bind_interrupts;
+use defmt_rtt as _;
+#[cfg(feature = "screen")]
+use static_cell::{ConstStaticCell, StaticCell};
+
+// The board's resource groups, `crate::AdcResources` and the rest.
+use board::*;
 
 mod adc;
+mod assets;
+mod board;
+mod buttons;
+mod commands;
+mod config;
+mod crash;
 mod deej_usb;
+mod diagnostics;
+#[cfg(feature = "screen")]
+mod display;
+mod encoder;
+mod events;
+mod gestures;
 mod graphics;
-mod gray4;
-mod gray4_effects;
+mod perf;
+mod power;
+#[cfg(feature = "screen")]
 mod screen;
-mod sprite;
-mod volume_indicator;
-
-assign_resources! {
-    screen: ScreenResources {
-        spi: SPI1,
-        sck: PIN_14,
-        mosi: PIN_15,
-        miso: PIN_12,
-        cs: PIN_13,
-        reset: PIN_6,
-        pwr: PIN_9,
-        dc: PIN_16,
-        dma_tx: DMA_CH0,
-    },
-    adc: AdcResources {
-        spi: SPI0,
-        sck: PIN_2,
-        mosi: PIN_7,
-        miso: PIN_4,
-        cs: PIN_5,
-        dma_tx: DMA_CH1,
-        dma_rx: DMA_CH2,
-    },
-    usb: UsbResources {
-        usb: USB
-    }
+mod settings;
+#[cfg(all(feature = "screen", feature = "display-ssd1306"))]
+mod ssd1306;
+#[cfg(all(feature = "screen", feature = "display-ssd1322"))]
+mod ssd1322;
+#[cfg(all(feature = "screen", feature = "display-ssd1327"))]
+mod ssd1327;
+#[cfg(feature = "status-led")]
+mod status_led;
+mod themes;
+mod watchdog;
+
+/// Stack of core1, which draws and flushes the frames. Scenes and frames live
+/// in statics and task futures, so this only holds call frames.
+#[cfg(feature = "screen")]
+static CORE1_STACK: ConstStaticCell<Stack<16384>> = ConstStaticCell::new(Stack::new());
+#[cfg(feature = "screen")]
+static CORE1_EXECUTOR: StaticCell<Executor> = StaticCell::new();
+
+/// The panel and flash, among the rest, for the panic and HardFault
+/// handlers, which cannot wait for the tasks to give them back.
+///
+/// # Safety
+///
+/// Only for code that never returns to the tasks; see `crash::go_down`.
+unsafe fn steal_crash_resources() -> AssignedResources {
+    board::split(embassy_rp::Peripherals::steal())
 }
 
 bind_interrupts!(struct Irqs {
@@ -52,16 +72,54 @@ bind_interrupts!(struct Irqs {
 async fn main(spawner: Spawner) {
     let p = embassy_rp::init(Default::default());
 
-    let r = split_resources!(p);
+    let r = board::split(p);
+
+    let (watchdog, late) = watchdog::init(r.watchdog);
+    let mut settings = settings::init(r.flash);
+    crash::init(&mut settings.flash, late);
+
+    // Every subscriber exists before anything can publish.
+    let settings_events = events::subscribe();
+    let adc_events = events::subscribe();
+    #[cfg(feature = "screen")]
+    let render_events = events::subscribe();
+    #[cfg(feature = "screen")]
+    let frame_events = events::subscribe();
+    let power_events = events::subscribe();
+    #[cfg(feature = "status-led")]
+    let led_events = events::subscribe();
+
+    spawner.spawn(settings::settings_task(settings, settings_events).unwrap());
+    spawner.spawn(crash::report_task().unwrap());
 
     let (usb_dev, log_class) = deej_usb::init(r.usb);
 
     spawner.spawn(deej_usb::usb_task(usb_dev).unwrap());
     spawner.spawn(deej_usb::logger_task(log_class).unwrap());
 
-    spawner.spawn(adc::adc_task(r.adc).unwrap());
+    spawner.spawn(adc::adc_task(r.adc, adc_events).unwrap());
+    spawner.spawn(buttons::buttons_task(r.buttons).unwrap());
+    spawner.spawn(power::power_task(power_events).unwrap());
+    #[cfg(feature = "encoders")]
+    encoder::spawn(spawner, r.encoders);
+    #[cfg(feature = "status-led")]
+    spawner.spawn(status_led::status_led_task(r.led, led_events).unwrap());
+
+    // Drawing and flushing frames takes milliseconds at a time; on core1 it
+    // cannot hold up sampling or USB.
+    #[cfg(feature = "screen")]
+    {
+        screen::init_display_buffers();
+        let screen_res = r.screen;
+        spawn_core1(r.core1.core1, CORE1_STACK.take(), move || {
+            CORE1_EXECUTOR.init(Executor::new()).run(|spawner| {
+                spawner.spawn(screen::render_task(screen_res, render_events).unwrap());
+                spawner.spawn(graphics::prepare_frame_task(frame_events).unwrap());
+            })
+        });
+    }
+
+    spawner.spawn(diagnostics::diagnostics_task().unwrap());
 
-    screen::init_display_buffers();
-    spawner.spawn(screen::render_task(r.screen).unwrap());
-    spawner.spawn(graphics::prepare_frame_task().unwrap());
+    spawner.spawn(watchdog::watchdog_task(watchdog).unwrap());
 }
@@ -0,0 +1,171 @@
+//! Timings of the render path and the ADC loop, for the `perf` command and
+//! the on-screen overlay.
+//!
+//! `prepare_frame_task` records how long a frame takes to draw,
+//! `render_task` how long the panel takes to flush and how far apart the
+//! flushes are, and `adc_task` the period of its loop. A flush later than
+//! `graphics::frame_period()` allows counts the frames it missed as dropped.
+
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use deej_gfx::perf::{Overlay, Timing};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant};
+
+#[cfg(feature = "screen")]
+use crate::graphics;
+#[cfg(feature = "screen")]
+use crate::screen::Frame;
+
+/// How often the overlay's figures are updated; they cover this window.
+#[cfg(feature = "screen")]
+const OVERLAY_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    /// Drawing a frame in `prepare_frame_task`.
+    Render,
+    /// Sending a frame to the panel.
+    Flush,
+    /// Time between two flushes.
+    Frame,
+    /// Period of the `adc_task` loop.
+    AdcLoop,
+}
+
+impl Metric {
+    const ALL: [Metric; 4] = [
+        Metric::Render,
+        Metric::Flush,
+        Metric::Frame,
+        Metric::AdcLoop,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Metric::Render => "render",
+            Metric::Flush => "flush",
+            Metric::Frame => "frame",
+            Metric::AdcLoop => "adc loop",
+        }
+    }
+}
+
+const METRICS: usize = Metric::ALL.len();
+
+#[cfg_attr(not(feature = "screen"), allow(dead_code))]
+struct Stats {
+    since: Instant,
+    timings: [Timing; METRICS],
+    dropped_frames: u32,
+    adc_errors: u32,
+    /// Timings since the overlay was last updated.
+    window: [Timing; METRICS],
+    window_start: Instant,
+    overlay: Overlay,
+}
+
+static STATS: Mutex<CriticalSectionRawMutex, RefCell<Stats>> = Mutex::new(RefCell::new(Stats {
+    since: Instant::from_ticks(0),
+    timings: [Timing::new(); METRICS],
+    dropped_frames: 0,
+    adc_errors: 0,
+    window: [Timing::new(); METRICS],
+    window_start: Instant::from_ticks(0),
+    overlay: Overlay {
+        fps: 0,
+        render_us: None,
+        flush_us: None,
+    },
+}));
+
+static OVERLAY: AtomicBool = AtomicBool::new(false);
+
+fn micros(d: Duration) -> u32 {
+    d.as_micros().min(u32::MAX as u64) as u32
+}
+
+pub fn record(metric: Metric, d: Duration) {
+    let us = micros(d);
+    STATS.lock(|s| {
+        let mut s = s.borrow_mut();
+        s.timings[metric as usize].add(us);
+        s.window[metric as usize].add(us);
+    });
+}
+
+/// Records the time between two flushes, and the frames it skipped.
+#[cfg(feature = "screen")]
+pub fn frame_interval(d: Duration) {
+    record(Metric::Frame, d);
+    let period = micros(graphics::frame_period());
+    let missed = (micros(d) + period / 2) / period;
+    if missed > 1 {
+        STATS.lock(|s| s.borrow_mut().dropped_frames += missed - 1);
+    }
+}
+
+pub fn adc_error() {
+    STATS.lock(|s| s.borrow_mut().adc_errors += 1);
+}
+
+/// Logs everything recorded since boot or the last `reset`.
+pub fn report() {
+    let (since, timings, dropped, errors) = STATS.lock(|s| {
+        let s = s.borrow();
+        (s.since, s.timings, s.dropped_frames, s.adc_errors)
+    });
+    log::info!("perf over {} s", since.elapsed().as_secs());
+    for metric in Metric::ALL {
+        log::info!("perf {} {}", metric.name(), timings[metric as usize]);
+    }
+    log::info!(
+        "perf {} dropped frames, {} adc read failures",
+        dropped,
+        errors
+    );
+}
+
+pub fn reset() {
+    STATS.lock(|s| {
+        let mut s = s.borrow_mut();
+        s.since = Instant::now();
+        s.timings = [Timing::new(); METRICS];
+        s.dropped_frames = 0;
+        s.adc_errors = 0;
+    });
+    log::info!("perf reset");
+}
+
+pub fn toggle_overlay() {
+    let on = !OVERLAY.load(Ordering::Relaxed);
+    OVERLAY.store(on, Ordering::Relaxed);
+    log::info!("perf overlay {}", if on { "on" } else { "off" });
+}
+
+/// Draws the overlay over `frame` if it is switched on.
+#[cfg(feature = "screen")]
+pub fn draw_overlay(frame: &mut Frame) {
+    if !OVERLAY.load(Ordering::Relaxed) {
+        return;
+    }
+    let overlay = STATS.lock(|s| {
+        let mut s = s.borrow_mut();
+        if s.window_start.elapsed() >= OVERLAY_PERIOD {
+            let window = s.window;
+            s.overlay = Overlay {
+                fps: window[Metric::Frame as usize]
+                    .avg_us()
+                    .map_or(0, |us| 1_000_000 / us.max(1)),
+                render_us: window[Metric::Render as usize].avg_us(),
+                flush_us: window[Metric::Flush as usize].avg_us(),
+            };
+            s.window = [Timing::new(); METRICS];
+            s.window_start = Instant::now();
+        }
+        s.overlay
+    });
+    overlay.draw(frame);
+}
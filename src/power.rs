@@ -0,0 +1,70 @@
+//! Clock scaling for USB suspend.
+//!
+//! A suspended host leaves the device 2.5 mA. `power_task`, on core0,
+//! divides clk_sys (and clk_peri, which runs off it) down until the host
+//! resumes; on boards with a screen it first waits for the outro to finish,
+//! so that plays at full speed. USB has its own PLL and the timer its own
+//! 1 MHz tick, so neither notices; the ADC's SPI clock drops with clk_peri,
+//! which the MCP3008 does not mind, and so does the panel's, which is asleep
+//! by then.
+
+use deej_gfx::scene::ScreenState;
+use embassy_rp::pac;
+use embassy_sync::pubsub::WaitResult;
+
+use crate::deej_usb;
+use crate::events::{self, Event, Events, HostState};
+
+/// clk_sys divider while suspended: 125 MHz down to about 16 MHz.
+const SUSPEND_DIVIDER: u32 = 8;
+
+fn suspend_clocks() {
+    set_sys_divider(SUSPEND_DIVIDER);
+}
+
+/// Back to full speed; also called by `crash`, which times its delays by
+/// clk_sys.
+pub fn restore_clocks() {
+    set_sys_divider(1);
+}
+
+fn set_sys_divider(div: u32) {
+    // The integer divider switches without glitches.
+    pac::CLOCKS.clk_sys_div().write(|w| w.set_int(div));
+}
+
+#[embassy_executor::task]
+pub async fn power_task(mut events: Events) {
+    let mut suspended = false;
+
+    loop {
+        match events.next_message().await {
+            WaitResult::Message(Event::HostStateChanged(HostState::Suspended)) => {
+                suspended = true;
+                if !cfg!(feature = "screen") {
+                    suspend_clocks();
+                }
+            }
+            WaitResult::Message(Event::SceneFinished(ScreenState::OUTRO)) if suspended => {
+                suspend_clocks();
+            }
+            WaitResult::Message(Event::HostStateChanged(HostState::Active)) => {
+                suspended = false;
+                restore_clocks();
+            }
+            WaitResult::Message(_) => {}
+            WaitResult::Lagged(n) => {
+                log::warn!("power_task lost {} events", n);
+                suspended = !deej_usb::host_active();
+                let outro_done = !cfg!(feature = "screen")
+                    || events::SCREEN_STATE.try_get() == Some(ScreenState::OFF);
+                match (suspended, outro_done) {
+                    (false, _) => restore_clocks(),
+                    (true, true) => suspend_clocks(),
+                    // `SceneFinished(OUTRO)` is still to come.
+                    (true, false) => {}
+                }
+            }
+        }
+    }
+}
@@ -1,135 +1,125 @@
-use core::sync::atomic::Ordering;
+use deej_gfx::frame::Gray4Frame;
+use deej_gfx::gray4;
+use deej_gfx::scene::ScreenState;
 use embassy_rp::gpio::{Level, Output};
 use embassy_rp::spi;
-use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pubsub::WaitResult;
 use embassy_sync::signal::Signal;
-use embassy_time::Delay;
-use embedded_graphics::pixelcolor::Gray4;
-use embedded_graphics::prelude::GrayColor;
-use embedded_graphics::prelude::*;
+use embassy_time::{Delay, Instant};
 use embedded_hal_bus::spi::ExclusiveDevice;
-use ssd1322_rs::{self, calculate_buffer_size, Frame, Orientation, SSD1322};
 use static_cell::StaticCell;
 
 use crate::deej_usb;
-use crate::graphics::{get_screen_state, ScreenState, SCREEN_STATE};
+use crate::display::{self, Display, Panel};
+use crate::events::{self, Event, Events, HostState};
+use crate::perf::{self, Metric};
+use crate::watchdog::{self, Task};
 use crate::ScreenResources;
 
-pub const SCREEN_WIDTH: usize = 256;
-pub const SCREEN_HEIGHT: usize = 64;
-const BUF_SIZE: usize = calculate_buffer_size(SCREEN_WIDTH, SCREEN_HEIGHT);
+pub const SCREEN_WIDTH: usize = display::WIDTH;
+pub const SCREEN_HEIGHT: usize = display::HEIGHT;
+const BUF_SIZE: usize = gray4::size_bytes(SCREEN_WIDTH, SCREEN_HEIGHT);
 
-static FRAME_A: StaticCell<Frame<BUF_SIZE>> = StaticCell::new();
-static FRAME_B: StaticCell<Frame<BUF_SIZE>> = StaticCell::new();
+pub type Frame = Gray4Frame<BUF_SIZE>;
 
-pub static NEXT_FRAME: Signal<ThreadModeRawMutex, &'static mut Frame<BUF_SIZE>> = Signal::new();
-pub static READY_FRAME: Signal<ThreadModeRawMutex, &'static mut Frame<BUF_SIZE>> = Signal::new();
+static FRAME_A: StaticCell<Frame> = StaticCell::new();
+static FRAME_B: StaticCell<Frame> = StaticCell::new();
+
+// Both ends run on core1 (see `main`), but the first frames are handed in
+// from core0 by `init_display_buffers`, so these lock across cores.
+pub static NEXT_FRAME: Signal<CriticalSectionRawMutex, &'static mut Frame> = Signal::new();
+pub static READY_FRAME: Signal<CriticalSectionRawMutex, &'static mut Frame> = Signal::new();
 
 pub fn init_display_buffers() {
-    let frame_a = FRAME_A.init(Default::default());
+    let frame_a = FRAME_A.init(Frame::new(SCREEN_WIDTH, SCREEN_HEIGHT));
     NEXT_FRAME.signal(frame_a);
 
-    let frame_b = FRAME_B.init(Default::default());
+    let frame_b = FRAME_B.init(Frame::new(SCREEN_WIDTH, SCREEN_HEIGHT));
     READY_FRAME.signal(frame_b);
 }
 
-#[embassy_executor::task]
-pub async fn render_task(screen: ScreenResources) {
+/// SPI settings of the panel, also used by `crash` to draw without DMA.
+pub fn spi_config() -> spi::Config {
     let mut spi_config = spi::Config::default();
     spi_config.frequency = 10_000_000;
+    spi_config
+}
 
+#[embassy_executor::task]
+pub async fn render_task(screen: ScreenResources, mut events: Events) {
     let spi_p = spi::Spi::new_txonly(
         screen.spi,
         screen.sck,
         screen.mosi,
         screen.dma_tx,
-        spi_config,
+        spi_config(),
     );
     let reset = Output::new(screen.reset, Level::Low);
     let scr_power = Output::new(screen.pwr, Level::Low);
     let data_command_pin = Output::new(screen.dc, Level::Low);
     let cs_pin = Output::new(screen.cs, Level::Low);
 
-    let spi_dev = ExclusiveDevice::new_no_delay(spi_p, cs_pin);
+    let spi_dev = ExclusiveDevice::new_no_delay(spi_p, cs_pin).unwrap();
 
-    let mut display = SSD1322::new(
-        spi_dev,
-        data_command_pin,
-        reset,
-        scr_power,
-        Default::default(),
-    );
-    display.init_default(&mut Delay).await.unwrap();
-    display
-        .set_orientation(Orientation::Standard)
-        .await
-        .unwrap();
+    let mut display = Panel::new(spi_dev, data_command_pin, reset, scr_power);
+    display.init(&mut Delay).await.unwrap();
 
     let mut frame = READY_FRAME.wait().await;
 
-    let rx = deej_usb::HOST_STATE_CH.receiver();
-    let mut host_state = deej_usb::HostState::Active; // assume we start active
-
-    // loop {
-    //     while let Ok(new_state) = rx.try_receive() {
-    //         host_state = new_state;
-    //     }
-
-    //     if host_state == deej_usb::HostState::Suspended {
-    //         frame.clear(Gray4::BLACK).unwrap();
-    //         let _ = display.flush_frame(frame).await;
-
-    //         loop {
-    //             let new_state = rx.receive().await;
-    //             host_state = new_state;
-    //             if host_state == deej_usb::HostState::Active {
-    //                 break;
-    //             }
-    //         }
-    //     }
-
-    //     NEXT_FRAME.signal(frame);
-    //     frame = READY_FRAME.wait().await;
-
-    //     match display.flush_frame(frame).await {
-    //         Ok(_) => {}
-    //         Err(_e) => {}
-    //     }
-    // }
+    let mut host_state = HostState::Active; // assume we start active
+    let mut outro_done = false;
+    let mut last_flush: Option<Instant> = None;
 
     loop {
-        // 1) Pull in any pending USB state changes
-        while let Ok(new_state) = rx.try_receive() {
-            // If we *just* got Suspended, trigger outro once
-            if new_state == deej_usb::HostState::Suspended {
-                let current = get_screen_state();
-                if current != ScreenState::OUTRO && current != ScreenState::OFF {
-                    SCREEN_STATE.store(ScreenState::OUTRO as u8, Ordering::Relaxed);
+        watchdog::check_in(Task::Render);
+
+        // 1) Pull in any pending host state changes; `prepare_frame_task`
+        //    plays the outro when the host goes away.
+        while let Some(event) = events.try_next_message() {
+            match event {
+                WaitResult::Message(Event::HostStateChanged(new_state)) => {
+                    host_state = new_state;
+                    outro_done = false;
+                }
+                WaitResult::Message(Event::SceneFinished(ScreenState::OUTRO)) => {
+                    outro_done = true;
+                }
+                WaitResult::Message(_) => {}
+                WaitResult::Lagged(n) => {
+                    log::warn!("render_task lost {} events", n);
+                    host_state = if deej_usb::host_active() {
+                        HostState::Active
+                    } else {
+                        HostState::Suspended
+                    };
+                    outro_done = events::SCREEN_STATE.try_get() == Some(ScreenState::OFF);
                 }
             }
-
-            host_state = new_state;
         }
 
-        // 2) If host is suspended *and* outro has finished (OFF),
-        //    go black and sleep until Active again.
-        if host_state == deej_usb::HostState::Suspended && get_screen_state() == ScreenState::OFF {
-            frame.clear(Gray4::BLACK).unwrap();
-            let _ = display.flush_frame(frame).await;
+        // 2) If host is suspended *and* outro has finished,
+        //    put the panel to sleep and wait until Active.
+        if host_state == HostState::Suspended && outro_done {
+            let _ = display.sleep().await;
 
-            // Wait here until host wakes up
+            // Wait here until host wakes up; the intro starts again then.
             loop {
-                let new_state = rx.receive().await;
-                host_state = new_state;
-
-                if host_state == deej_usb::HostState::Active {
-                    // Optionally restart intro on wake
-                    SCREEN_STATE.store(ScreenState::INTRO as u8, Ordering::Relaxed);
-                    break;
+                match watchdog::idle(Task::Render, events.next_message()).await {
+                    WaitResult::Message(Event::HostStateChanged(HostState::Active)) => break,
+                    WaitResult::Lagged(_) if deej_usb::host_active() => break,
+                    _ => {}
                 }
             }
+            host_state = HostState::Active;
+            outro_done = false;
+
+            if display.init(&mut Delay).await.is_err() {
+                log::warn!("display did not come back from sleep");
+            }
 
-            // Go back to top of loop after wake
+            // Go back to top of loop after wake; the pause is no dropped frame.
+            last_flush = None;
             continue;
         }
 
@@ -137,6 +127,12 @@ pub async fn render_task(screen: ScreenResources) {
         NEXT_FRAME.signal(frame);
         frame = READY_FRAME.wait().await;
 
-        let _ = display.flush_frame(frame).await;
+        let started = Instant::now();
+        let _ = display.flush(frame).await;
+        perf::record(Metric::Flush, started.elapsed());
+        if let Some(last) = last_flush {
+            perf::frame_interval(started - last);
+        }
+        last_flush = Some(started);
     }
 }
@@ -0,0 +1,145 @@
+//! Settings kept across power cycles: the `config::Config`, in a
+//! `deej_gfx::kv::Store` over the last `STORE_SECTORS` sectors of the flash
+//! (kept out of the firmware image by `memory.x`).
+//!
+//! The store takes the sectors in turn and checks every record with a CRC.
+//! A config that is missing or does not decode means defaults, except on
+//! the first boot after an upgrade: earlier builds kept just the theme name
+//! in the top sector, and that record is migrated.
+//!
+//! The task writing it owns the flash, so it also erases the crash record in
+//! the sector below when asked to (see `crash`).
+
+use deej_gfx::config::{Config, MAX_BYTES};
+use deej_gfx::kv::Store;
+use embassy_futures::select::{select, Either};
+use embassy_rp::flash::{Blocking, Flash, ERASE_SIZE};
+use embassy_rp::peripherals::FLASH;
+use embassy_sync::pubsub::WaitResult;
+use embassy_time::{Duration, Timer};
+
+use crate::board::FLASH_SIZE;
+use crate::crash::{self, CLEAR_CRASH};
+use crate::events::{Event, Events};
+use crate::{config, graphics, FlashResources};
+
+const STORE_SECTORS: u32 = 4;
+pub const STORE_OFFSET: u32 = (FLASH_SIZE - STORE_SECTORS as usize * ERASE_SIZE) as u32;
+
+/// The settings record of builds before the store, in its top sector.
+const OLD_RECORD_OFFSET: u32 = (FLASH_SIZE - ERASE_SIZE) as u32;
+const OLD_RECORD_BYTES: usize = 20;
+
+/// Key of the config in the store.
+const CONFIG_KEY: u8 = 0;
+
+/// Quiet time after a change before it is written, so clicking through the
+/// themes costs one write instead of many.
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+pub type SettingsFlash = Flash<'static, FLASH, Blocking, FLASH_SIZE>;
+
+pub struct Settings {
+    pub flash: SettingsFlash,
+    /// `None` if the flash could not be read; the config then lives in RAM
+    /// only.
+    store: Option<Store>,
+    /// `Config::checksum` of the config as last loaded or saved.
+    saved: u32,
+}
+
+/// Opens the store and sets up `config` with what is stored there.
+pub fn init(res: FlashResources) -> Settings {
+    let mut flash = Flash::new_blocking(res.flash);
+    let defaults = config::defaults();
+
+    let mut old = [0u8; OLD_RECORD_BYTES];
+    let old = flash
+        .blocking_read(OLD_RECORD_OFFSET, &mut old)
+        .map(|()| old);
+
+    let store = Store::open(&mut flash, STORE_OFFSET, STORE_SECTORS)
+        .inspect_err(|e| log::warn!("opening the settings store failed: {:?}", e))
+        .ok();
+
+    let mut buf = [0u8; MAX_BYTES];
+    let stored = store
+        .and_then(|s| s.read(&mut flash, CONFIG_KEY, &mut buf).ok().flatten())
+        .and_then(|len| Config::decode(&buf[..len], &defaults));
+
+    let config = match stored {
+        Some(config) => config,
+        None => match old.ok().and_then(|b| Config::decode(&b, &defaults)) {
+            Some(config) => {
+                log::info!("settings migrated from an earlier build");
+                config
+            }
+            // Erased flash, a corrupt record or one from a newer build.
+            None => {
+                log::info!("no stored settings, using defaults");
+                defaults
+            }
+        },
+    };
+    let saved = config.checksum();
+    config::init(config);
+    graphics::sync_theme();
+
+    Settings {
+        flash,
+        store,
+        saved,
+    }
+}
+
+/// Writes the config back whenever it changes, and erases the crash record
+/// when the host clears it.
+#[embassy_executor::task]
+pub async fn settings_task(mut settings: Settings, mut events: Events) {
+    loop {
+        match select(events.next_message(), CLEAR_CRASH.wait()).await {
+            Either::First(WaitResult::Message(Event::ConfigChanged)) => {
+                settings.save_soon(&mut events).await;
+            }
+            // Events lost to falling behind may have been changes; the
+            // checksum tells.
+            Either::First(WaitResult::Lagged(n)) => {
+                log::warn!("settings_task lost {} events", n);
+                if settings.changed() {
+                    settings.save_soon(&mut events).await;
+                }
+            }
+            Either::First(WaitResult::Message(_)) => {}
+            Either::Second(()) => match crash::erase(&mut settings.flash) {
+                Ok(()) => log::info!("crash record cleared"),
+                Err(e) => log::warn!("clearing the crash record failed: {:?}", e),
+            },
+        }
+    }
+}
+
+impl Settings {
+    fn changed(&self) -> bool {
+        config::get(|c| c.checksum()) != self.saved
+    }
+
+    /// Saves after `SAVE_DELAY`, covering whatever came in meanwhile.
+    async fn save_soon(&mut self, events: &mut Events) {
+        Timer::after(SAVE_DELAY).await;
+        while events.try_next_message().is_some() {}
+        self.save();
+    }
+
+    fn save(&mut self) {
+        let Some(store) = &mut self.store else {
+            return;
+        };
+        let mut buf = [0u8; MAX_BYTES];
+        let config = config::get(|c| c.clone());
+        // Unchanged bytes are not written again.
+        match store.write(&mut self.flash, CONFIG_KEY, config.encode(&mut buf)) {
+            Ok(()) => self.saved = config.checksum(),
+            Err(e) => log::warn!("saving settings failed: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Minimal async SSD1306 driver for 128x64 monochrome panels.
+//!
+//! Frames are still drawn in Gray4 and dithered down to 1bpp on the way out
+//! (see `deej_gfx::mono`) into a page buffer kept here. Partial updates send
+//! the dirty rectangles rounded out to whole 8-row pages.
+
+use deej_gfx::dirty::{DirtyRects, Rect};
+use deej_gfx::frame::Gray4Frame;
+use deej_gfx::mono;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::display::{Display, DisplayError};
+
+pub const WIDTH: usize = 128;
+pub const HEIGHT: usize = 64;
+const PAGE_BYTES: usize = mono::page_bytes(WIDTH, HEIGHT);
+
+/// See `ssd1322::FULL_FLUSH_PERCENT`.
+const FULL_FLUSH_PERCENT: u32 = 60;
+
+mod cmd {
+    pub const SET_MEMORY_MODE: u8 = 0x20;
+    pub const SET_COLUMN_ADDRESS: u8 = 0x21;
+    pub const SET_PAGE_ADDRESS: u8 = 0x22;
+    pub const SET_START_LINE: u8 = 0x40;
+    pub const SET_CONTRAST: u8 = 0x81;
+    pub const CHARGE_PUMP: u8 = 0x8D;
+    pub const SEGMENT_REMAP: u8 = 0xA1;
+    pub const RESUME_FROM_RAM: u8 = 0xA4;
+    pub const NORMAL_DISPLAY: u8 = 0xA6;
+    pub const SET_MUX_RATIO: u8 = 0xA8;
+    pub const DISPLAY_OFF: u8 = 0xAE;
+    pub const DISPLAY_ON: u8 = 0xAF;
+    pub const COM_SCAN_REMAP: u8 = 0xC8;
+    pub const SET_DISPLAY_OFFSET: u8 = 0xD3;
+    pub const SET_CLOCK_DIVIDER: u8 = 0xD5;
+    pub const SET_PRECHARGE: u8 = 0xD9;
+    pub const SET_COM_PINS: u8 = 0xDA;
+    pub const SET_VCOMH: u8 = 0xDB;
+}
+
+pub struct Ssd1306<SPI, DC, RST, PWR> {
+    spi: SPI,
+    dc: DC,
+    reset: RST,
+    pwr: PWR,
+    pages: [u8; PAGE_BYTES],
+    /// See `Ssd1322::shown`.
+    shown: Option<DirtyRects>,
+}
+
+impl<SPI, DC, RST, PWR> Ssd1306<SPI, DC, RST, PWR>
+where
+    SPI: SpiDevice,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+{
+    pub fn new(spi: SPI, dc: DC, reset: RST, pwr: PWR) -> Self {
+        Self {
+            spi,
+            dc,
+            reset,
+            pwr,
+            pages: [0; PAGE_BYTES],
+            shown: None,
+        }
+    }
+
+    /// Sends the whole frame.
+    pub async fn flush_frame<const N: usize>(
+        &mut self,
+        frame: &Gray4Frame<N>,
+    ) -> Result<(), DisplayError> {
+        self.shown = None;
+        let all = Rect::new(0, 0, WIDTH as u16, HEIGHT as u16);
+        self.flush_window(frame, &all).await?;
+        self.shown = Some(*frame.dirty());
+        Ok(())
+    }
+
+    /// Converts and sends the pages `rect` touches.
+    async fn flush_window<const N: usize>(
+        &mut self,
+        frame: &Gray4Frame<N>,
+        rect: &Rect,
+    ) -> Result<(), DisplayError> {
+        mono::pack_pages(frame, rect, &mut self.pages);
+
+        let (x0, x1) = (rect.x0 as usize, rect.x1 as usize);
+        let (first_page, last_page) = (rect.y0 / 8, rect.y1.div_ceil(8) - 1);
+
+        self.command(&[cmd::SET_COLUMN_ADDRESS, x0 as u8, x1 as u8 - 1])
+            .await?;
+        self.command(&[cmd::SET_PAGE_ADDRESS, first_page as u8, last_page as u8])
+            .await?;
+
+        for page in first_page as usize..=last_page as usize {
+            let start = page * WIDTH;
+            self.dc.set_high().map_err(|_| DisplayError::Pin)?;
+            self.spi
+                .write(&self.pages[start + x0..start + x1])
+                .await
+                .map_err(|_| DisplayError::Spi)?;
+        }
+        Ok(())
+    }
+
+    /// Sends a command with its arguments, all in command mode.
+    async fn command(&mut self, bytes: &[u8]) -> Result<(), DisplayError> {
+        self.dc.set_low().map_err(|_| DisplayError::Pin)?;
+        self.spi.write(bytes).await.map_err(|_| DisplayError::Spi)
+    }
+}
+
+impl<SPI, DC, RST, PWR> Display for Ssd1306<SPI, DC, RST, PWR>
+where
+    SPI: SpiDevice,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+{
+    /// Resets the controller, sets it up for a 128x64 module on its internal
+    /// charge pump and turns it on. `pwr` is raised anyway for boards that
+    /// switch the panel supply.
+    async fn init(&mut self, delay: &mut impl DelayNs) -> Result<(), DisplayError> {
+        self.reset.set_low().map_err(|_| DisplayError::Pin)?;
+        delay.delay_ms(1).await;
+        self.reset.set_high().map_err(|_| DisplayError::Pin)?;
+        delay.delay_ms(1).await;
+
+        self.pwr.set_high().map_err(|_| DisplayError::Pin)?;
+
+        self.command(&[cmd::DISPLAY_OFF]).await?;
+        self.command(&[cmd::SET_CLOCK_DIVIDER, 0x80]).await?;
+        self.command(&[cmd::SET_MUX_RATIO, HEIGHT as u8 - 1])
+            .await?;
+        self.command(&[cmd::SET_DISPLAY_OFFSET, 0x00]).await?;
+        self.command(&[cmd::SET_START_LINE]).await?;
+        self.command(&[cmd::CHARGE_PUMP, 0x14]).await?;
+        // Horizontal addressing: a window fills page by page.
+        self.command(&[cmd::SET_MEMORY_MODE, 0x00]).await?;
+        self.command(&[cmd::SEGMENT_REMAP]).await?;
+        self.command(&[cmd::COM_SCAN_REMAP]).await?;
+        self.command(&[cmd::SET_COM_PINS, 0x12]).await?;
+        self.command(&[cmd::SET_CONTRAST, 0xCF]).await?;
+        self.command(&[cmd::SET_PRECHARGE, 0xF1]).await?;
+        self.command(&[cmd::SET_VCOMH, 0x40]).await?;
+        self.command(&[cmd::RESUME_FROM_RAM]).await?;
+        self.command(&[cmd::NORMAL_DISPLAY]).await?;
+
+        delay.delay_ms(100).await;
+        self.command(&[cmd::DISPLAY_ON]).await
+    }
+
+    /// Sends what changed between the frame on the panel and `frame`, see
+    /// `Ssd1322::flush`.
+    async fn flush<const N: usize>(&mut self, frame: &Gray4Frame<N>) -> Result<(), DisplayError> {
+        let Some(mut region) = self.shown.take() else {
+            return self.flush_frame(frame).await;
+        };
+        region.extend(frame.dirty());
+
+        let pages = |r: &Rect| Rect::new(r.x0, r.y0 / 8 * 8, r.x1, r.y1.div_ceil(8) * 8);
+        let area: u32 = region.as_slice().iter().map(|r| pages(r).area()).sum();
+        let total = (WIDTH * HEIGHT) as u32;
+
+        if area * 100 >= total * FULL_FLUSH_PERCENT {
+            return self.flush_frame(frame).await;
+        }
+
+        for rect in region.as_slice() {
+            if !rect.is_empty() {
+                self.flush_window(frame, rect).await?;
+            }
+        }
+        self.shown = Some(*frame.dirty());
+        Ok(())
+    }
+
+    /// Display off and the charge pump with it, then the supply.
+    async fn sleep(&mut self) -> Result<(), DisplayError> {
+        self.shown = None;
+        self.command(&[cmd::DISPLAY_OFF]).await?;
+        self.command(&[cmd::CHARGE_PUMP, 0x10]).await?;
+        self.pwr.set_low().map_err(|_| DisplayError::Pin)
+    }
+}
@@ -0,0 +1,223 @@
+//! Minimal async SSD1322 driver for the 256x64 panel.
+//!
+//! The frame buffer is a `deej_gfx::frame::Gray4Frame`, whose layout is the
+//! controller's RAM layout, so flushing is a single SPI write. Partial
+//! updates write one column/row window per dirty rectangle.
+
+use deej_gfx::dirty::{DirtyRects, Rect};
+use deej_gfx::frame::Gray4Frame;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::display::{Display, DisplayError};
+
+pub const WIDTH: usize = 256;
+pub const HEIGHT: usize = 64;
+
+/// The panel's 256 columns start at column address 0x1C, 4 pixels each.
+const COLUMN_OFFSET: u8 = 0x1C;
+
+/// Pixels per column address.
+const COLUMN_PIXELS: u16 = 4;
+
+/// Partial updates cost a window setup per rectangle and an SPI transfer
+/// per row; past this share of the frame a full flush is cheaper.
+const FULL_FLUSH_PERCENT: u32 = 60;
+
+mod cmd {
+    pub const SET_COLUMN_ADDRESS: u8 = 0x15;
+    pub const WRITE_RAM: u8 = 0x5C;
+    pub const SET_ROW_ADDRESS: u8 = 0x75;
+    pub const SET_REMAP: u8 = 0xA0;
+    pub const SET_START_LINE: u8 = 0xA1;
+    pub const SET_DISPLAY_OFFSET: u8 = 0xA2;
+    pub const NORMAL_DISPLAY: u8 = 0xA6;
+    pub const EXIT_PARTIAL_DISPLAY: u8 = 0xA9;
+    pub const FUNCTION_SELECTION: u8 = 0xAB;
+    pub const DISPLAY_OFF: u8 = 0xAE;
+    pub const DISPLAY_ON: u8 = 0xAF;
+    pub const SET_PHASE_LENGTH: u8 = 0xB1;
+    pub const SET_CLOCK_DIVIDER: u8 = 0xB3;
+    pub const DISPLAY_ENHANCEMENT_A: u8 = 0xB4;
+    pub const SET_GPIO: u8 = 0xB5;
+    pub const SET_SECOND_PRECHARGE: u8 = 0xB6;
+    pub const DEFAULT_GRAYSCALE: u8 = 0xB9;
+    pub const SET_PRECHARGE_VOLTAGE: u8 = 0xBB;
+    pub const SET_VCOMH: u8 = 0xBE;
+    pub const SET_CONTRAST: u8 = 0xC1;
+    pub const MASTER_CURRENT: u8 = 0xC7;
+    pub const SET_MUX_RATIO: u8 = 0xCA;
+    pub const DISPLAY_ENHANCEMENT_B: u8 = 0xD1;
+    pub const COMMAND_LOCK: u8 = 0xFD;
+}
+
+pub struct Ssd1322<SPI, DC, RST, PWR> {
+    spi: SPI,
+    dc: DC,
+    reset: RST,
+    pwr: PWR,
+    /// Dirty set of the frame on the panel, `None` when the panel RAM is
+    /// unknown (before the first flush or after a failed one).
+    shown: Option<DirtyRects>,
+}
+
+impl<SPI, DC, RST, PWR> Ssd1322<SPI, DC, RST, PWR>
+where
+    SPI: SpiDevice,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+{
+    pub fn new(spi: SPI, dc: DC, reset: RST, pwr: PWR) -> Self {
+        Self {
+            spi,
+            dc,
+            reset,
+            pwr,
+            shown: None,
+        }
+    }
+
+    /// Sends the whole frame.
+    pub async fn flush_frame<const N: usize>(
+        &mut self,
+        frame: &Gray4Frame<N>,
+    ) -> Result<(), DisplayError> {
+        self.shown = None;
+        let last_col = COLUMN_OFFSET + (frame.width() / 4) as u8 - 1;
+        let last_row = frame.height() as u8 - 1;
+
+        self.command(cmd::SET_COLUMN_ADDRESS, &[COLUMN_OFFSET, last_col])
+            .await?;
+        self.command(cmd::SET_ROW_ADDRESS, &[0, last_row]).await?;
+        self.command(cmd::WRITE_RAM, &[]).await?;
+        self.data(frame.as_bytes()).await?;
+        self.shown = Some(*frame.dirty());
+        Ok(())
+    }
+
+    /// Sends one window. `rect` must be aligned to whole column addresses.
+    async fn flush_window<const N: usize>(
+        &mut self,
+        frame: &Gray4Frame<N>,
+        rect: &Rect,
+    ) -> Result<(), DisplayError> {
+        let first_col = COLUMN_OFFSET + (rect.x0 / COLUMN_PIXELS) as u8;
+        let last_col = COLUMN_OFFSET + (rect.x1 / COLUMN_PIXELS) as u8 - 1;
+
+        self.command(cmd::SET_COLUMN_ADDRESS, &[first_col, last_col])
+            .await?;
+        self.command(cmd::SET_ROW_ADDRESS, &[rect.y0 as u8, rect.y1 as u8 - 1])
+            .await?;
+        self.command(cmd::WRITE_RAM, &[]).await?;
+
+        let stride = frame.row_bytes();
+        let (b0, b1) = (rect.x0 as usize / 2, rect.x1 as usize / 2);
+        for y in rect.y0 as usize..rect.y1 as usize {
+            self.data(&frame.as_bytes()[y * stride + b0..y * stride + b1])
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn command(&mut self, command: u8, args: &[u8]) -> Result<(), DisplayError> {
+        self.dc.set_low().map_err(|_| DisplayError::Pin)?;
+        self.spi
+            .write(&[command])
+            .await
+            .map_err(|_| DisplayError::Spi)?;
+
+        if !args.is_empty() {
+            self.data(args).await?;
+        }
+        Ok(())
+    }
+
+    async fn data(&mut self, data: &[u8]) -> Result<(), DisplayError> {
+        self.dc.set_high().map_err(|_| DisplayError::Pin)?;
+        self.spi.write(data).await.map_err(|_| DisplayError::Spi)
+    }
+}
+
+impl<SPI, DC, RST, PWR> Display for Ssd1322<SPI, DC, RST, PWR>
+where
+    SPI: SpiDevice,
+    DC: OutputPin,
+    RST: OutputPin,
+    PWR: OutputPin,
+{
+    /// Resets the controller, programs the NHD-3.12 256x64 defaults, powers
+    /// the panel and turns it on.
+    async fn init(&mut self, delay: &mut impl DelayNs) -> Result<(), DisplayError> {
+        self.reset.set_low().map_err(|_| DisplayError::Pin)?;
+        delay.delay_ms(1).await;
+        self.reset.set_high().map_err(|_| DisplayError::Pin)?;
+        delay.delay_ms(1).await;
+
+        self.command(cmd::COMMAND_LOCK, &[0x12]).await?;
+        self.command(cmd::DISPLAY_OFF, &[]).await?;
+        self.command(cmd::SET_CLOCK_DIVIDER, &[0x91]).await?;
+        self.command(cmd::SET_MUX_RATIO, &[0x3F]).await?;
+        self.command(cmd::SET_DISPLAY_OFFSET, &[0x00]).await?;
+        self.command(cmd::SET_START_LINE, &[0x00]).await?;
+        // Horizontal increment, nibble remap, COM scan remap, dual COM.
+        self.command(cmd::SET_REMAP, &[0x14, 0x11]).await?;
+        self.command(cmd::SET_GPIO, &[0x00]).await?;
+        self.command(cmd::FUNCTION_SELECTION, &[0x01]).await?;
+        self.command(cmd::DISPLAY_ENHANCEMENT_A, &[0xA0, 0xFD])
+            .await?;
+        self.command(cmd::SET_CONTRAST, &[0x9F]).await?;
+        self.command(cmd::MASTER_CURRENT, &[0x0F]).await?;
+        self.command(cmd::DEFAULT_GRAYSCALE, &[]).await?;
+        self.command(cmd::SET_PHASE_LENGTH, &[0xE2]).await?;
+        self.command(cmd::DISPLAY_ENHANCEMENT_B, &[0xA2, 0x20])
+            .await?;
+        self.command(cmd::SET_PRECHARGE_VOLTAGE, &[0x1F]).await?;
+        self.command(cmd::SET_SECOND_PRECHARGE, &[0x08]).await?;
+        self.command(cmd::SET_VCOMH, &[0x07]).await?;
+        self.command(cmd::NORMAL_DISPLAY, &[]).await?;
+        self.command(cmd::EXIT_PARTIAL_DISPLAY, &[]).await?;
+
+        self.pwr.set_high().map_err(|_| DisplayError::Pin)?;
+        delay.delay_ms(100).await;
+
+        self.command(cmd::DISPLAY_ON, &[]).await
+    }
+
+    /// Sends what changed between the frame on the panel and `frame`: the
+    /// union of both dirty sets, or the whole frame when that covers most
+    /// of it.
+    async fn flush<const N: usize>(&mut self, frame: &Gray4Frame<N>) -> Result<(), DisplayError> {
+        let Some(mut region) = self.shown.take() else {
+            return self.flush_frame(frame).await;
+        };
+        region.extend(frame.dirty());
+
+        let max_x = frame.width() as u16;
+        let area: u32 = region
+            .as_slice()
+            .iter()
+            .map(|r| r.align_x(COLUMN_PIXELS, max_x).area())
+            .sum();
+        let total = (frame.width() * frame.height()) as u32;
+
+        if area * 100 >= total * FULL_FLUSH_PERCENT {
+            return self.flush_frame(frame).await;
+        }
+
+        for rect in region.as_slice() {
+            self.flush_window(frame, &rect.align_x(COLUMN_PIXELS, max_x))
+                .await?;
+        }
+        self.shown = Some(*frame.dirty());
+        Ok(())
+    }
+
+    /// Display off, which is the controller's sleep mode, then the supply.
+    async fn sleep(&mut self) -> Result<(), DisplayError> {
+        self.shown = None;
+        self.command(cmd::DISPLAY_OFF, &[]).await?;
+        self.pwr.set_low().map_err(|_| DisplayError::Pin)
+    }
+}
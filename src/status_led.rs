@@ -0,0 +1,26 @@
+//! The status LED of boards that have one: lit while the host is there,
+//! dark while it is suspended.
+
+use embassy_rp::gpio::{Level, Output};
+use embassy_sync::pubsub::WaitResult;
+
+use crate::deej_usb;
+use crate::events::{Event, Events, HostState};
+use crate::LedResources;
+
+#[embassy_executor::task]
+pub async fn status_led_task(res: LedResources, mut events: Events) {
+    let mut led = Output::new(res.led, Level::Low);
+
+    loop {
+        let active = match events.next_message().await {
+            WaitResult::Message(Event::HostStateChanged(state)) => state == HostState::Active,
+            WaitResult::Message(_) => continue,
+            WaitResult::Lagged(n) => {
+                log::warn!("status_led_task lost {} events", n);
+                deej_usb::host_active()
+            }
+        };
+        led.set_level(if active { Level::High } else { Level::Low });
+    }
+}
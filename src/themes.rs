@@ -0,0 +1,21 @@
+//! The character themes built into this firmware, one `theme-*` cargo
+//! feature each. The first one is the default until the host picks another
+//! with the `theme` command.
+
+use deej_gfx::theme::{self, Theme};
+
+use crate::assets;
+
+pub const THEMES: &[Theme] = &[
+    #[cfg(feature = "theme-muffet")]
+    theme::MUFFET.build(assets::SHEETS),
+    #[cfg(feature = "theme-cobweb")]
+    theme::COBWEB.build(assets::SHEETS),
+];
+
+const _: () = assert!(!THEMES.is_empty(), "enable at least one theme-* feature");
+
+/// Index of the theme called `name`.
+pub fn find(name: &str) -> Option<usize> {
+    THEMES.iter().position(|t| t.name == name)
+}
@@ -0,0 +1,210 @@
+//! The RP2040 watchdog, fed only while every task is alive.
+//!
+//! Each task in `Task` checks in from its main loop; `watchdog_task` feeds
+//! the hardware as long as all of them did so within their deadline. When
+//! one falls behind it is logged, its index is left in a watchdog scratch
+//! register and the watchdog is left to reset the chip. After the reboot
+//! `init` reads it back for `crash` to record and report.
+//!
+//! Waits in a task's own loop that may legitimately last forever (the host
+//! suspended, a knob left alone) go through `idle`, which keeps checking in
+//! meanwhile. `usb_task` and `logger_task` spend their lives inside
+//! embassy-usb's loops, where nothing can check in, so they are not watched.
+
+use core::future::Future;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use embassy_futures::select::{select, Either};
+use embassy_rp::pac;
+use embassy_rp::watchdog::{ResetReason, Watchdog};
+use embassy_time::{Duration, Instant, Ticker, Timer};
+
+use crate::crash;
+use crate::encoder::ENCODERS;
+use crate::WatchdogResources;
+
+/// Hardware timeout; the chip resets this long after the last feed.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often the deadlines are checked and the hardware fed.
+const CHECK_PERIOD: Duration = Duration::from_millis(250);
+
+/// Check-in period of tasks waiting in `idle`.
+const IDLE_CHECK_IN: Duration = Duration::from_millis(250);
+
+/// Scratch register holding the late task, tagged so a value left by
+/// anything else is not mistaken for one.
+const SCRATCH: usize = 0;
+const SCRATCH_TAG: u32 = 0xDEE0_0000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Task {
+    Adc,
+    Render,
+    PrepareFrame,
+    Buttons,
+    /// One per encoder in `encoder::ENCODERS`, so a hung one cannot hide
+    /// behind the others.
+    Encoder0,
+    Encoder1,
+    Encoder2,
+    Encoder3,
+    Encoder4,
+}
+
+impl Task {
+    const ALL: [Task; 9] = [
+        Task::Adc,
+        Task::Render,
+        Task::PrepareFrame,
+        Task::Buttons,
+        Task::Encoder0,
+        Task::Encoder1,
+        Task::Encoder2,
+        Task::Encoder3,
+        Task::Encoder4,
+    ];
+
+    /// The task of the `index`th encoder in `encoder::ENCODERS`.
+    #[cfg_attr(not(feature = "encoders"), allow(dead_code))]
+    pub fn encoder(index: usize) -> Task {
+        [
+            Task::Encoder0,
+            Task::Encoder1,
+            Task::Encoder2,
+            Task::Encoder3,
+            Task::Encoder4,
+        ][index]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Task::Adc => "adc_task",
+            Task::Render => "render_task",
+            Task::PrepareFrame => "prepare_frame_task",
+            Task::Buttons => "buttons_task",
+            Task::Encoder0 => "encoder_task 0",
+            Task::Encoder1 => "encoder_task 1",
+            Task::Encoder2 => "encoder_task 2",
+            Task::Encoder3 => "encoder_task 3",
+            Task::Encoder4 => "encoder_task 4",
+        }
+    }
+
+    /// Whether the task is in this build at all.
+    fn enabled(self) -> bool {
+        match self {
+            Task::Render | Task::PrepareFrame => cfg!(feature = "screen"),
+            Task::Encoder0 => !ENCODERS.is_empty(),
+            Task::Encoder1 => ENCODERS.len() > 1,
+            Task::Encoder2 => ENCODERS.len() > 2,
+            Task::Encoder3 => ENCODERS.len() > 3,
+            Task::Encoder4 => ENCODERS.len() > 4,
+            _ => true,
+        }
+    }
+
+    /// Longest time allowed between two check-ins.
+    fn deadline(self) -> Duration {
+        match self {
+            // Twenty of its 50 ms loops.
+            Task::Adc => Duration::from_millis(1000),
+            // Room for the panel init on boot.
+            Task::Render => Duration::from_millis(1000),
+            // A dozen 40 ms frames.
+            Task::PrepareFrame => Duration::from_millis(500),
+            // Samples every 5 ms; a stall shows long before a button lags.
+            Task::Buttons => Duration::from_millis(250),
+            // Only ever in `idle`: a few missed check-ins.
+            Task::Encoder0 | Task::Encoder1 | Task::Encoder2 | Task::Encoder3 | Task::Encoder4 => {
+                IDLE_CHECK_IN * 4
+            }
+        }
+    }
+}
+
+/// Uptime in milliseconds of each task's last check-in.
+static LAST_SEEN: [AtomicU32; Task::ALL.len()] = [const { AtomicU32::new(0) }; Task::ALL.len()];
+
+/// Records that `task` is making progress.
+pub fn check_in(task: Task) {
+    LAST_SEEN[task as usize].store(Instant::now().as_millis() as u32, Ordering::Relaxed);
+}
+
+/// Runs `fut`, checking in for `task` until it completes.
+pub async fn idle<F: Future>(task: Task, fut: F) -> F::Output {
+    let keep_alive = async {
+        loop {
+            check_in(task);
+            Timer::after(IDLE_CHECK_IN).await;
+        }
+    };
+    match select(fut, keep_alive).await {
+        Either::First(out) => out,
+        Either::Second(_) => unreachable!(),
+    }
+}
+
+/// Stops the hardware watchdog, whoever owns it. For `crash`, whose screen
+/// outlasts `TIMEOUT` and which resets the chip itself.
+pub fn stop() {
+    pac::WATCHDOG.ctrl().modify(|w| w.set_enable(false));
+}
+
+/// Takes the watchdog. Returns it with the task that was late if the last
+/// reset was the watchdog's doing.
+pub fn init(res: WatchdogResources) -> (Watchdog, Option<Task>) {
+    let mut watchdog = Watchdog::new(res.watchdog);
+
+    let scratch = watchdog.get_scratch(SCRATCH);
+    watchdog.set_scratch(SCRATCH, 0);
+    let late = match watchdog.reset_reason() {
+        Some(ResetReason::TimedOut) if scratch & !0xFF == SCRATCH_TAG => {
+            Task::ALL.get((scratch & 0xFF) as usize).copied()
+        }
+        _ => None,
+    };
+
+    (watchdog, late)
+}
+
+#[embassy_executor::task]
+pub async fn watchdog_task(mut watchdog: Watchdog) {
+    // A debugger halting the core should not reset it.
+    watchdog.pause_on_debug(true);
+
+    // Deadlines count from here rather than from boot, as some tasks are
+    // still setting up (the panel init) before their first check-in.
+    let now = Instant::now().as_millis() as u32;
+    for seen in &LAST_SEEN {
+        seen.store(now, Ordering::Relaxed);
+    }
+    watchdog.start(TIMEOUT);
+
+    let mut ticker = Ticker::every(CHECK_PERIOD);
+    loop {
+        ticker.next().await;
+
+        // Core1's tasks go quiet while its crash handler has the panel; the
+        // crash is what gets reported.
+        if crash::crashing() {
+            continue;
+        }
+
+        let now = Instant::now().as_millis() as u32;
+        let late = Task::ALL.into_iter().filter(|t| t.enabled()).find(|&task| {
+            let seen = LAST_SEEN[task as usize].load(Ordering::Relaxed);
+            now.wrapping_sub(seen) as u64 > task.deadline().as_millis()
+        });
+
+        match late {
+            None => watchdog.feed(),
+            Some(task) => {
+                log::error!("{} missed its deadline, resetting", task.name());
+                watchdog.set_scratch(SCRATCH, SCRATCH_TAG | task as u32);
+                // Stop feeding and let the watchdog bite.
+                core::future::pending::<()>().await;
+            }
+        }
+    }
+}